@@ -1,6 +1,302 @@
 extern crate roguelike;
-use roguelike::run_game;
+use roguelike::{ run_game, continue_path };
+use roguelike::save::{ load_game, convert_save, SaveEncoding };
+use roguelike::config::{ Config, CONFIG_FILE, load_config };
+use roguelike::environment::Generator;
+use roguelike::environment::map::ascii::map_to_ascii;
+use roguelike::replay::{ load_replay, save_replay, run_replay };
+use roguelike::rng::GameRng;
+use roguelike::simulate::simulate;
+use roguelike::visualise::{ record_level, export_frames, FrameFormat };
+use roguelike::state::GameState;
+use roguelike::objects::npc::enemies::data::load_monster_data;
+use roguelike::objects::items::data::load_item_data;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+// Number of commands a simulation runs for, unless told otherwise.
+const DEFAULT_SIMULATION_STEPS: usize = 5000;
+
+const USAGE: &str = "\
+Usage: roguelike [command] [options]
+
+Commands:
+    play                    Opens the game window (the default).
+    gen-map                 Generates a level, and prints it as text.
+    replay <file>           Re-runs a replay without a window, and checks it still plays out the same way.
+    simulate                Plays a game with a simple bot, and prints how it went.
+    convert-save <in> <out> Rewrites a save as JSON or binary (see --to).
+    gen-frames <dir>        Saves each step of generating a level as a numbered text file or image.
+
+Options:
+    --config <file>         Reads settings from a JSON config file (default: config.json, if it exists).
+    --seed <number>         Uses this seed for new games.
+    --save <file>           Saves to, and continues from, this file instead of a save slot.
+    --save-dir <dir>        Keeps save slots in this directory.
+    --depth <number>        Starts new games on this dungeon level.
+    --map-size <w>x<h>      Generates levels at this size.
+    --window-size <w>x<h>   Sets the size of the window, in characters.
+    --skip-menu             Goes straight into a new game.
+    --watch-gen             Shows each step of making a new level before it is played.
+    --continue              Goes straight into the most recent save.
+    --steps <number>        How many commands a simulation runs for (default: 5000).
+    --record <file>         Saves the replay of a simulation.
+    --to <json|binary>      The encoding convert-save writes (default: json).
+    --format <ascii|png>    The kind of files gen-frames writes (default: ascii).
+    --generator <name>      Makes gen-frames use one generator: rectangles, caved-rectangles, open-rectangles,
+                            open-caved-rectangles, drunk-walk, cellular-automata, maze, bsp or
+                            wave-function-collapse.
+    --help                  Shows this message.";
+
+// Flags that are followed by a value, such as "--seed 5".
+const VALUE_FLAGS: [&str; 12] = [
+    "--config", "--seed", "--save", "--save-dir", "--depth", "--map-size", "--window-size",
+    "--steps", "--record", "--to", "--format", "--generator",
+];
+
+// Flags that are given on their own.
+const SWITCH_FLAGS: [&str; 5] = ["--skip-menu", "--watch-gen", "--continue", "--help", "-h"];
+
+// The command line, split into flags (along with their values) and everything else.
+struct Args {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    switches: Vec<String>,
+}
 
 fn main() {
-    run_game();
+    let args = parse_args(env::args().skip(1));
+
+    if args.switches.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    // Finds the value given for a flag, such as the number after "--seed".
+    let flag_value = |flag: &str| args.values.iter().find(|(name, _)| name == flag).map(|(_, value)| value);
+    let has_flag = |flag: &str| args.switches.iter().any(|arg| arg == flag);
+
+    // The settings file is read first, then anything given on the command line replaces what it says.
+    let config_path = flag_value("--config");
+    let mut config = load_config(config_path.map_or(CONFIG_FILE, |path| path.as_str()), config_path.is_some())
+        .unwrap_or_else(|e| fail(&format!("Could not load config: {}", e)));
+    apply_flags(&mut config, &flag_value, &has_flag);
+    if let Err(e) = config.validate() {
+        fail(&format!("Invalid settings: {}", e));
+    }
+
+    // Monster and item definitions are loaded, and checked, before anything else happens.
+    if let Err(e) = load_monster_data(&config.monster_data) {
+        fail(&format!("Could not load monster data: {}", e));
+    }
+    if let Err(e) = load_item_data(&config.item_data) {
+        fail(&format!("Could not load item data: {}", e));
+    }
+
+    // The first argument that isn't a flag is the command, and any after it are what the command works on.
+    let command = args.positional.first().map(|arg| arg.as_str()).unwrap_or("play");
+    let operands = args.positional.get(1..).unwrap_or(&[]);
+    let expect_operands = |count: usize, message: &str| {
+        if operands.len() != count {
+            fail(&format!("{}\n\n{}", message, USAGE));
+        }
+    };
+    match command {
+        "replay" => {
+            expect_operands(1, "replay needs a file");
+            check_replay(&operands[0]);
+        },
+        "gen-map" => {
+            expect_operands(0, "gen-map doesn't take any files");
+            gen_map(&config);
+        },
+        "simulate" => {
+            expect_operands(0, "simulate doesn't take any files");
+            let steps = flag_value("--steps").map_or(DEFAULT_SIMULATION_STEPS, |steps| {
+                steps.parse::<usize>().unwrap_or_else(|_| fail("The number of steps must be a positive whole number"))
+            });
+            run_simulation(&config, steps, flag_value("--record"));
+        },
+        "gen-frames" => {
+            expect_operands(1, "gen-frames needs a directory");
+            let format = match flag_value("--format").map(|format| format.as_str()) {
+                None | Some("ascii") => FrameFormat::Ascii,
+                Some("png") => FrameFormat::Png,
+                Some(other) => fail(&format!("Unknown frame format \"{}\", expected ascii or png", other)),
+            };
+            let generator = flag_value("--generator").map(|name| {
+                Generator::from_name(name).unwrap_or_else(|| fail(&format!("Unknown generator \"{}\"", name)))
+            });
+            gen_frames(&config, &operands[0], format, generator);
+        },
+        "convert-save" => {
+            expect_operands(2, "convert-save needs an input and an output file");
+            let encoding = match flag_value("--to").map(|to| to.as_str()) {
+                None | Some("json") => SaveEncoding::Json,
+                Some("binary") => SaveEncoding::Binary,
+                Some(other) => fail(&format!("Unknown save encoding \"{}\", expected json or binary", other)),
+            };
+            if let Err(e) = convert_save(Path::new(&operands[0]), Path::new(&operands[1]), encoding) {
+                fail(&format!("Could not convert {}: {}", operands[0], e));
+            }
+            println!("Converted {} to {}", operands[0], operands[1]);
+        },
+        "play" => {
+            expect_operands(0, "play doesn't take any files");
+            // "--continue" checks there is a save to load before opening the window.
+            if has_flag("--continue") {
+                if let Err(e) = continue_path(&config).and_then(load_game) {
+                    fail(&format!("Could not continue: {}", e));
+                }
+            }
+            run_game(&config);
+        },
+        other => fail(&format!("Unknown command \"{}\"\n\n{}", other, USAGE)),
+    }
+}
+
+// Sorts the command line into flags and everything else, so that flags can go before or after the command.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Args {
+    let mut parsed = Args { positional: vec![], values: vec![], switches: vec![] };
+    while let Some(arg) = args.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            let value = args.next().unwrap_or_else(|| fail(&format!("{} needs a value", arg)));
+            parsed.values.push((arg, value));
+        } else if SWITCH_FLAGS.contains(&arg.as_str()) {
+            parsed.switches.push(arg);
+        } else if arg.starts_with('-') {
+            fail(&format!("Unknown option \"{}\"\n\n{}", arg, USAGE));
+        } else {
+            parsed.positional.push(arg);
+        }
+    }
+    parsed
+}
+
+// Replaces settings from the config file with any given as flags.
+fn apply_flags<'a>(
+    config: &mut Config,
+    flag_value: &dyn Fn(&str) -> Option<&'a String>,
+    has_flag: &dyn Fn(&str) -> bool,
+) {
+    if let Some(seed) = flag_value("--seed") {
+        config.seed = Some(seed.parse::<u64>().unwrap_or_else(|_| fail("The seed must be a positive whole number")));
+    }
+    if let Some(depth) = flag_value("--depth") {
+        config.start_depth = depth.parse::<u32>().unwrap_or_else(|_| fail("The depth must be a positive whole number"));
+    }
+    if let Some(path) = flag_value("--save") {
+        config.save_file = Some(path.clone());
+    }
+    if let Some(path) = flag_value("--save-dir") {
+        config.save_dir = Some(path.clone());
+    }
+    if let Some(size) = flag_value("--map-size") {
+        let (width, height) = parse_size(size).unwrap_or_else(|e| fail(&format!("Invalid map size: {}", e)));
+        config.map_width = width;
+        config.map_height = height;
+    }
+    if let Some(size) = flag_value("--window-size") {
+        let (width, height) = parse_size(size).unwrap_or_else(|e| fail(&format!("Invalid window size: {}", e)));
+        config.window_width = width;
+        config.window_height = height;
+    }
+    if has_flag("--watch-gen") {
+        config.watch_map_generation = true;
+    }
+    if has_flag("--skip-menu") {
+        config.skip_menu = true;
+    }
+    if has_flag("--continue") {
+        config.continue_game = true;
+    }
+}
+
+// Re-runs a recorded game without opening a window, and reports if it diverged.
+fn check_replay(path: &str) {
+    let replay = load_replay(path).unwrap_or_else(|e| fail(&format!("Could not load replay {}: {}", path, e)));
+    match run_replay(&replay) {
+        Ok(state) => {
+            println!(
+                "Replay of {} steps matched. Finished on dungeon level {}.",
+                replay.steps.len(), state.game.dungeon_level
+            );
+        },
+        Err(divergence) => {
+            println!("Replay diverged at step {} of {}.", divergence.step + 1, replay.steps.len());
+            println!("Expected: {:?}", divergence.expected);
+            println!("Found:    {:?}", divergence.actual);
+            process::exit(1);
+        },
+    }
+}
+
+// Generates the starting level of a run, and prints it along with the seed needed to get it again.
+fn gen_map(config: &Config) {
+    let options = config.run_options(GameRng::random_seed());
+    let state = GameState::new(options, false);
+    println!("Seed: {}, dungeon level: {}", options.seed, state.game.dungeon_level);
+    print!("{}", map_to_ascii(&state.game.map, Some(&state.entities)));
+}
+
+// Records each step of generating a level, and writes them out as frames.
+fn gen_frames(config: &Config, dir: &str, format: FrameFormat, generator: Option<Generator>) {
+    let options = config.run_options(GameRng::random_seed());
+    let recording = record_level(options, generator);
+    match export_frames(&recording, Path::new(dir), format) {
+        Ok(count) => {
+            println!(
+                "Seed: {}, dungeon level: {}, generator: {}",
+                options.seed, options.start_depth, generator.map_or("picked by depth", |generator| generator.name()),
+            );
+            println!("{} frames written to {}", count, dir);
+        },
+        Err(e) => fail(&format!("Could not write frames to {}: {}", dir, e)),
+    }
+}
+
+// Lets the bot play a game, then prints a summary of the run.
+fn run_simulation(config: &Config, steps: usize, record: Option<&String>) {
+    let options = config.run_options(GameRng::random_seed());
+    let state = simulate(options, steps);
+    let stats = &state.stats;
+
+    println!("Seed: {}", options.seed);
+    println!("Commands: {}", state.replay.steps.len());
+    println!("Outcome: {}", if state.entities.player().alive { "alive" } else { "died" });
+    println!("Dungeon level: {} (deepest {})", state.game.dungeon_level, stats.deepest_level);
+    println!("Turns taken: {}", stats.turns);
+    println!("Monsters slain: {}", stats.kills);
+    println!("Damage dealt: {}, taken: {}", stats.damage_dealt, stats.damage_taken);
+    println!("Items picked up: {}, used: {}", stats.items_picked_up, stats.items_used);
+    println!("Levels gained: {}", stats.levels_gained);
+
+    if let Some(path) = record {
+        if let Err(e) = save_replay(&state.replay, path) {
+            fail(&format!("Could not save replay {}: {}", path, e));
+        }
+        println!("Replay saved to {}", path);
+    }
+}
+
+// Reads a size written as "<width>x<height>", such as "120x60".
+fn parse_size(size: &str) -> Result<(i32, i32), String> {
+    let mut parts = size.split('x');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => {
+            match (width.parse::<i32>(), height.parse::<i32>()) {
+                (Ok(width), Ok(height)) => Ok((width, height)),
+                _ => Err(format!("\"{}\" is not a size like 120x60", size)),
+            }
+        },
+        _ => Err(format!("\"{}\" is not a size like 120x60", size)),
+    }
+}
+
+// Reports a problem with how the game was started, and exits.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
 }
@@ -0,0 +1,128 @@
+use crate::environment::{ MapSize, RunOptions, DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT };
+use crate::graphics::layout::{ Layout, DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT };
+use crate::objects::npc::enemies::data::MONSTER_DATA_FILE;
+use crate::objects::items::data::ITEM_DATA_FILE;
+use crate::replay::REPLAY_FILE;
+use crate::save::SaveEncoding;
+use crate::save::slots::{ default_save_dir, default_data_dir };
+
+use std::error::Error;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Serialize, Deserialize };
+
+// File the settings are read from, unless another one is given on the command line.
+pub const CONFIG_FILE: &str = "config.json";
+
+// How many turns pass between autosaves, unless the config says otherwise.
+pub const DEFAULT_AUTOSAVE_TURNS: u32 = 100;
+
+// Settings for running the game. Anything missing from the config file keeps its default,
+// and command line flags are applied on top of whatever the file says.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // If set, every new game uses this seed. Otherwise a random one is picked.
+    pub seed: Option<u64>,
+    pub start_depth: u32,
+    pub map_width: i32,
+    pub map_height: i32,
+    pub window_width: i32,
+    pub window_height: i32,
+    // If set, the game is saved to and continued from this one file, rather than from save slots.
+    pub save_file: Option<String>,
+    // Where save slots are kept. Defaults to the user's data directory.
+    pub save_dir: Option<String>,
+    // Where a description of each finished run is written. Defaults to the user's data directory.
+    pub morgue_dir: Option<String>,
+    // The game is saved every this many turns, as well as on each new level. 0 turns it off.
+    pub autosave_turns: u32,
+    // "json" or "binary". Binary saves are much smaller, JSON ones are easier to look through.
+    pub save_encoding: SaveEncoding,
+    pub replay_file: String,
+    pub monster_data: String,
+    pub item_data: String,
+    // Plays back each step of making a new level, before it is played.
+    pub watch_map_generation: bool,
+    // Goes straight into a new game, rather than showing the main menu.
+    pub skip_menu: bool,
+    // Goes straight into the saved game, rather than showing the main menu.
+    pub continue_game: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            seed: None,
+            start_depth: 1,
+            map_width: DEFAULT_MAP_WIDTH,
+            map_height: DEFAULT_MAP_HEIGHT,
+            window_width: DEFAULT_SCREEN_WIDTH,
+            window_height: DEFAULT_SCREEN_HEIGHT,
+            save_file: None,
+            save_dir: None,
+            morgue_dir: None,
+            autosave_turns: DEFAULT_AUTOSAVE_TURNS,
+            save_encoding: SaveEncoding::Json,
+            replay_file: REPLAY_FILE.into(),
+            monster_data: MONSTER_DATA_FILE.into(),
+            item_data: ITEM_DATA_FILE.into(),
+            watch_map_generation: false,
+            skip_menu: false,
+            continue_game: false,
+        }
+    }
+}
+
+impl Config {
+    // Checks the settings make sense together, so mistakes are reported before the window opens.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.start_depth == 0 {
+            return Err("the starting depth must be at least 1".into());
+        }
+        self.map_size()?;
+        self.layout()?;
+        Ok(())
+    }
+
+    pub fn map_size(&self) -> Result<MapSize, String> {
+        MapSize::new(self.map_width, self.map_height)
+    }
+
+    pub fn layout(&self) -> Result<Layout, String> {
+        Layout::new(self.window_width, self.window_height)
+    }
+
+    pub fn save_dir(&self) -> PathBuf {
+        self.save_dir.as_ref().map_or_else(default_save_dir, PathBuf::from)
+    }
+
+    pub fn morgue_dir(&self) -> PathBuf {
+        self.morgue_dir.as_ref().map_or_else(|| default_data_dir().join("morgue"), PathBuf::from)
+    }
+
+    // Options for a new run. Uses the configured seed if there is one, otherwise the seed given.
+    pub fn run_options(&self, random_seed: u64) -> RunOptions {
+        RunOptions {
+            seed: self.seed.unwrap_or(random_seed),
+            map_size: self.map_size().unwrap_or_default(),
+            start_depth: self.start_depth,
+        }
+    }
+}
+
+// Reads the config file.
+// A missing file just means the defaults are used, unless the file was asked for by name.
+pub fn load_config(path: &str, required: bool) -> Result<Config, Box<dyn Error>> {
+    if !Path::new(path).exists() {
+        if required {
+            return Err(format!("{} does not exist", path).into());
+        }
+        return Ok(Config::default());
+    }
+
+    let json = fs::read_to_string(path)?;
+    let config = serde_json::from_str::<Config>(&json).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(config)
+}
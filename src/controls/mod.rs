@@ -1,122 +1,86 @@
 use crate::*;
-use crate::environment::Game;
 use crate::Tcod;
-use crate::objects::*;
+use crate::objects::items::Targeting;
+use crate::state::{ GameState, PlayerCommand };
+use crate::events::achievements::ACHIEVEMENTS;
+use crate::graphics::gui::target_tile;
 use crate::graphics::gui::menu::inventory_menu;
 
-use serde::{ Serialize, Deserialize };
-
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
-pub enum PlayerAction {
-    TookTurn,
-    DidntTakeTurn,
+// What the front-end should do after a key press.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerInput {
+    Command(PlayerCommand),
+    Nothing,
     Exit,
 }
 
-pub fn handle_keys(
-    tcod: &mut Tcod,
-    mut game: &mut Game,
-    characters: &mut Vec<Character>,
-    mut items: &mut HashMap<i32, Object>,
-    mut player: &mut Character,
-) -> PlayerAction {
+pub fn handle_keys(tcod: &mut Tcod, state: &mut GameState) -> PlayerInput {
     use tcod::input::KeyCode::*;
-    use PlayerAction::*;
+    use PlayerInput::*;
 
-    let player_alive = player.object.alive;
+    let player_alive = state.entities.player().alive;
     match (tcod.key, tcod.key.text(), player_alive) {
         // Movement keys
-        ( Key { code: NumPad7, .. }, _, true) => {
-            Object::player_move_or_attack(-1, -1, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad8, .. }, _, true) => {
-            Object::player_move_or_attack(0, -1, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad9, .. }, _, true) => {
-            Object::player_move_or_attack(1, -1, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad4, .. }, _, true) => {
-            Object::player_move_or_attack(-1, 0, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad6, .. }, _, true) => {
-            Object::player_move_or_attack(1, 0, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad1, .. }, _, true) => {
-            Object::player_move_or_attack(-1, 1, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad2, .. }, _, true) => {
-            Object::player_move_or_attack(0, 1, game, characters, &mut player.object);
-            TookTurn
-        },
-        ( Key { code: NumPad3, .. }, _, true) => {
-            Object::player_move_or_attack(1, 1, game, characters, &mut player.object);
-            TookTurn
-        },
+        ( Key { code: NumPad7, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: -1, dy: -1 }),
+        ( Key { code: NumPad8, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: 0, dy: -1 }),
+        ( Key { code: NumPad9, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: 1, dy: -1 }),
+        ( Key { code: NumPad4, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: -1, dy: 0 }),
+        ( Key { code: NumPad6, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: 1, dy: 0 }),
+        ( Key { code: NumPad1, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: -1, dy: 1 }),
+        ( Key { code: NumPad2, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: 0, dy: 1 }),
+        ( Key { code: NumPad3, .. }, _, true) => Command(PlayerCommand::MoveOrAttack { dx: 1, dy: 1 }),
 
         // Wait a turn
-        ( Key { code: NumPad5, .. }, _, true) => {
-            TookTurn // Wait a turn
-        },
+        ( Key { code: NumPad5, .. }, _, true) => Command(PlayerCommand::Wait),
 
-        ( Key { code: Text, .. }, "<", true) => {
-            // Go down stairs if the player is on top of them.
-            let player_on_stairs = items
-                .values()
-                .any(|obj| obj.pos() == player.object.pos() && obj.name == "Stairs");
-            if player_on_stairs {
-                next_level(tcod, game, &mut player.object, characters, items);
-            }
-            DidntTakeTurn
-        }
+        // Go down stairs if the player is on top of them.
+        ( Key { code: Text, .. }, "<", true) => Command(PlayerCommand::Descend),
+
+        // Go back up if the player is on top of the up stairs.
+        ( Key { code: Text, .. }, ">", true) => Command(PlayerCommand::Ascend),
 
         // Action keys
         // Grab the item at your position.
-        ( Key { code: Text, .. }, "g", true) => {
-            // Pick up an item
-            let mut item_id: i32 = -1;
+        ( Key { code: Text, .. }, "g", true) => Command(PlayerCommand::PickUp),
 
-            for (key, item) in items.iter() {
-                if (item.pos() == player.object.pos()) && (item.item.is_some()) {
-                    item_id = *key
-                }
-            }
+        // Close an open door next to you.
+        ( Key { code: Text, .. }, "x", true) => Command(PlayerCommand::CloseDoor),
 
-            if item_id > 0 {
-                Object::pick_item_up(item_id, game, items, player);
-                TookTurn
-            } else {
-                game.messages.add(
-                    format!("There's no item to grab..."),
-                    RED
-                );
-                DidntTakeTurn
-            }
-        },
+        // Spend a turn searching for secret passages.
+        ( Key { code: Text, .. }, "s", true) => Command(PlayerCommand::Search),
+
+        // Try to disarm a trap you have found.
+        ( Key { code: Text, .. }, "t", true) => Command(PlayerCommand::Disarm),
 
         ( Key { code: Text, .. }, "i", true) => {
             // Show the inventory.
             let inventory_index = inventory_menu(
-                player,
+                state.entities.player(),
                 "Press the key next to an item to use it, or any other to cancel.\n",
                 &mut tcod.root
             );
-            if let Some(inventory_index) = inventory_index {
-                Object::use_item(inventory_index, tcod, game, characters, player, items);
+            match inventory_index {
+                Some(inventory_id) => {
+                    // Some items need a target to be chosen before they can be used.
+                    let item = state.entities.player().inventory.as_ref().and_then(|inv| inv[inventory_id].item.clone());
+                    let target = match item.map(|item| item.targeting()) {
+                        Some(Targeting::Tile { max_range, prompt, color }) => {
+                            state.game.messages.add(prompt, color);
+                            target_tile(tcod, state, max_range)
+                        },
+                        _ => None,
+                    };
+                    Command(PlayerCommand::UseItem { inventory_id, target })
+                },
+                None => Nothing,
             }
-            TookTurn
         },
 
         ( Key { code: Text, .. }, "c", true) => {
             // Displays character information.
-            let level = player.object.level;
-            let level_up_xp = LEVEL_UP_BASE + player.object.level * LEVEL_UP_FACTOR;
-            if let Some(fighter) = player.object.fighter.as_ref() {
+            let level = state.entities.player().level;
+            let level_up_xp = state.level_up_xp();
+            if let Some(fighter) = state.entities.player().fighter.as_ref() {
                 let msg = format!(
                     "Character information
 
@@ -126,24 +90,37 @@ Experience to level up: {}
 
 Maximum HP: {}
 Attack: {}
-Defense: {}",
-                    level, fighter.exp, level_up_xp, fighter.max_hp, fighter.power, fighter.defense
+Defense: {}
+Perception: {}
+Speed: {}
+
+Turns taken: {}
+Monsters slain: {}
+Achievements: {}/{}
+
+Seed: {}",
+                    level, fighter.exp, level_up_xp, fighter.max_hp, fighter.power, fighter.defense,
+                    fighter.perception,
+                    fighter.effective_speed(),
+                    state.stats.turns, state.stats.kills,
+                    state.achievements.unlocked().count(), ACHIEVEMENTS.len(),
+                    state.game.seed
                 );
                 msgbox(&msg, CHARACTER_SCREEN_WIDTH, &mut tcod.root);
             }
-            DidntTakeTurn
+            Nothing
         },
 
         ( Key { code: Text, .. }, "d", true) => {
             // Show the inventory. If an item is selected, drop it.
             let inventory_index = inventory_menu(
-                &player, "Press a listed key to drop an item, or another key to cancel.\n",
+                state.entities.player(), "Press a listed key to drop an item, or another key to cancel.\n",
                 &mut tcod.root,
             );
-            if let Some(inventory_index) = inventory_index {
-                Object::drop_item(inventory_index, &mut game, &mut items, &mut player);
+            match inventory_index {
+                Some(inventory_id) => Command(PlayerCommand::DropItem { inventory_id }),
+                None => Nothing,
             }
-            DidntTakeTurn
         },
 
 /*        // DEBUG-KEYS
@@ -182,10 +159,10 @@ Defense: {}",
             // Alt+Enter: Toggles fullscreen
             let fullscreen = tcod.root.is_fullscreen();
             tcod.root.set_fullscreen(!fullscreen);
-            DidntTakeTurn
+            Nothing
         },
         ( Key { code: Escape, .. }, _, _) => Exit, // Exits game
 
-        _ => DidntTakeTurn,
+        _ => Nothing,
     }
 }
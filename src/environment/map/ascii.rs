@@ -0,0 +1,67 @@
+use crate::environment::{ Map, map_dimensions };
+use crate::environment::map::tiles::TileType;
+use crate::objects::entities::Entities;
+
+// Draws a map as plain text, one line per row, so it can be looked at without a window.
+// Walls are "#", floors are "." and hidden passages are "+".
+// Doors are "'" when open, "D" when closed and "L" when locked.
+// Terrain is "~" for lava, "=" for deep water, '"' for bramble, "*" for crystal and ":" for miasma.
+// Entities are drawn with their own glyphs on top.
+pub fn map_to_ascii(map: &Map, entities: Option<&Entities>) -> String {
+    let (map_width, map_height) = map_dimensions(map);
+
+    let mut rows: Vec<Vec<char>> = (0..map_height)
+        .map(|y| {
+            (0..map_width)
+                .map(|x| {
+                    let tile = &map[x as usize][y as usize];
+                    if tile.secret_path {
+                        '+'
+                    } else if tile.is_door() {
+                        match tile.tiletype {
+                            TileType::OpenDoor => '\'',
+                            TileType::LockedDoor => 'L',
+                            _ => 'D',
+                        }
+                    } else if let Some(glyph) = terrain_glyph(tile.tiletype) {
+                        glyph
+                    } else if tile.blocked {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    // Living things are drawn last, so they show over items and stairs.
+    if let Some(entities) = entities {
+        let mut to_draw: Vec<_> = entities.iter().map(|(_, object)| object).collect();
+        to_draw.sort_by_key(|object| object.blocks);
+        for object in to_draw {
+            if object.x >= 0 && object.y >= 0 && object.x < map_width && object.y < map_height {
+                rows[object.y as usize][object.x as usize] = object.char;
+            }
+        }
+    }
+
+    let mut ascii = String::new();
+    for row in rows {
+        ascii.extend(row);
+        ascii.push('\n');
+    }
+    ascii
+}
+
+// Themed terrain gets its own character, so the different kinds can be told apart.
+fn terrain_glyph(tiletype: TileType) -> Option<char> {
+    match tiletype {
+        TileType::Lava => Some('~'),
+        TileType::DeepWater => Some('='),
+        TileType::Bramble => Some('"'),
+        TileType::Crystal => Some('*'),
+        TileType::Miasma => Some(':'),
+        _ => None,
+    }
+}
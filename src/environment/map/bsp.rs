@@ -0,0 +1,137 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::rng::GameRng;
+use crate::objects::Object;
+use crate::environment::map::{ create_room, create_h_tunnel, create_v_tunnel, create_secret_h_tunnel, create_secret_v_tunnel };
+use crate::environment::*;
+
+use rand::*;
+
+// Leaves are never split smaller than this, so each one has room for a room and its walls.
+const MIN_LEAF_SIZE: i32 = 8;
+
+// Leaves up to this size may be left whole, which gives a mix of large and small rooms.
+const MAX_LEAF_SIZE: i32 = 20;
+
+// Binary space partitioning.
+// The map is split in two, then each half is split again, and so on until the pieces are room sized.
+// A room is placed in each piece, and each pair of pieces that were split apart are joined by a corridor.
+pub fn bsp(
+    rooms: &mut Vec<Rect>,
+    map: &mut Map,
+    colors: &[Color; 7],
+    player: &mut Object,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) {
+    let (map_width, map_height) = map_dimensions(map);
+
+    // The outer edge of the map is left as wall.
+    let whole_map = Rect::new(0, 0, map_width - 1, map_height - 1);
+    split(whole_map, rooms, map, colors, rng, recorder);
+
+    // Rooms are listed in the order the tree was walked, so the first and last rooms are far apart.
+    let (start_x, start_y) = rooms[0].center();
+    player.set_pos(start_x, start_y);
+}
+
+// Splits a leaf in two, or places a room in it if it is small enough.
+// Returns the rooms inside the leaf, so they can be joined up with the other half of the split.
+fn split(
+    leaf: Rect,
+    rooms: &mut Vec<Rect>,
+    map: &mut Map,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> Vec<Rect> {
+    let width = leaf.x2 - leaf.x1;
+    let height = leaf.y2 - leaf.y1;
+    let can_split_x = width >= MIN_LEAF_SIZE * 2;
+    let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+    // Small enough leaves are sometimes kept whole, and leaves which are too small to split always are.
+    let small = width <= MAX_LEAF_SIZE && height <= MAX_LEAF_SIZE;
+    if (!can_split_x && !can_split_y) || (small && rng.gen_range(0, 3) == 0) {
+        let room = place_room(leaf, map, colors, rng);
+        recorder.snapshot(map, 4);
+        rooms.push(room);
+        return vec![room];
+    }
+
+    // Long leaves are cut across their length, so the pieces don't end up as thin strips.
+    let split_x = match (can_split_x, can_split_y) {
+        (true, false) => true,
+        (false, true) => false,
+        _ if width as f32 > height as f32 * 1.25 => true,
+        _ if height as f32 > width as f32 * 1.25 => false,
+        _ => rng.gen(),
+    };
+
+    // Both halves share the line they were split along, which becomes the wall between them.
+    let (first, second) = if split_x {
+        let at = leaf.x1 + rng.gen_range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+        (Rect { x2: at, ..leaf }, Rect { x1: at, ..leaf })
+    } else {
+        let at = leaf.y1 + rng.gen_range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+        (Rect { y2: at, ..leaf }, Rect { y1: at, ..leaf })
+    };
+
+    let mut first_rooms = split(first, rooms, map, colors, rng, recorder);
+    let second_rooms = split(second, rooms, map, colors, rng, recorder);
+
+    // Siblings are joined through their two closest rooms, which keeps corridors short.
+    let mut closest = (first_rooms[0], second_rooms[0]);
+    let mut closest_distance = i32::MAX;
+    for a in &first_rooms {
+        for b in &second_rooms {
+            let ((ax, ay), (bx, by)) = (a.center(), b.center());
+            let distance = (ax - bx).abs() + (ay - by).abs();
+            if distance < closest_distance {
+                closest = (*a, *b);
+                closest_distance = distance;
+            }
+        }
+    }
+    join(closest.0, closest.1, map, colors, rng);
+    recorder.snapshot(map, 4);
+
+    first_rooms.extend(second_rooms);
+    first_rooms
+}
+
+// Places a randomly sized room somewhere inside a leaf.
+fn place_room(leaf: Rect, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) -> Rect {
+    let leaf_width = leaf.x2 - leaf.x1;
+    let leaf_height = leaf.y2 - leaf.y1;
+
+    let w = rng.gen_range(ROOM_MIN_SIZE, leaf_width.min(ROOM_MAX_SIZE) + 1);
+    let h = rng.gen_range(ROOM_MIN_SIZE, leaf_height.min(ROOM_MAX_SIZE) + 1);
+    let x = leaf.x1 + rng.gen_range(0, leaf_width - w + 1);
+    let y = leaf.y1 + rng.gen_range(0, leaf_height - h + 1);
+
+    let room = Rect::new(x, y, w, h);
+    create_room(room, map, colors, rng);
+    room
+}
+
+// Joins two rooms with an L shaped corridor, sometimes with a secret passage around the other way.
+fn join(a: Rect, b: Rect, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
+    let (x1, y1) = a.center();
+    let (x2, y2) = b.center();
+
+    if rng.gen() {
+        create_h_tunnel(x1, x2, y1, map, colors, rng);
+        create_v_tunnel(y1, y2, x2, map, colors, rng);
+        if rng.gen_range(0, 4) == 0 {
+            create_secret_v_tunnel(y1, y2, x1, map, colors, rng);
+            create_secret_h_tunnel(x1, x2, y2, map, colors, rng);
+        }
+    } else {
+        create_v_tunnel(y1, y2, x1, map, colors, rng);
+        create_h_tunnel(x1, x2, y2, map, colors, rng);
+        if rng.gen_range(0, 4) == 0 {
+            create_secret_h_tunnel(x1, x2, y1, map, colors, rng);
+            create_secret_v_tunnel(y1, y2, x2, map, colors, rng);
+        }
+    }
+}
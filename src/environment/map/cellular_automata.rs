@@ -1,6 +1,7 @@
-use crate::graphics::render_map;
+use crate::environment::map::recorder::MapRecorder;
+use crate::rng::GameRng;
+use crate::objects::Object;
 use crate::environment::*;
-use crate::Tcod;
 
 use rand::*;
 
@@ -8,22 +9,24 @@ pub fn cellular_automata(
     map: &mut Map,
     colors: &[Color; 7],
     player: &mut Object,
-    tcod: &mut Tcod,
-    should_render: bool,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
 ) {
+    let (map_width, map_height) = map_dimensions(map);
+
 
     // Gives each tile a 55% chance to become an empty tile, rather than a wall.
-    for x in 1..(MAP_WIDTH - 1) {
-        for y in 1..(MAP_HEIGHT - 1) {
-            if rand::thread_rng().gen::<f32>() > 0.55 {
-                map[x as usize][y as usize] = Tile::empty(colors);
+    for x in 1..(map_width - 1) {
+        for y in 1..(map_height - 1) {
+            if rng.gen::<f32>() > 0.55 {
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
             }
         }
     }
 
     // Establishes a 2d vector of the same size as the map.
     // If a tile in this vector is true, the tile at the same point on the game map will be "purged" and turned into a wall.
-    let mut purge_map = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut purge_map = vec![vec![false; map_height as usize]; map_width as usize];
 
     // Defines how many iterations of the cellular automata rules should be applied
     let cell_cycles = 7;
@@ -32,8 +35,8 @@ pub fn cellular_automata(
     for _ in 1..cell_cycles {
 
         // Iterates through all the tiles on the map.
-        for x in 1..MAP_WIDTH - 1 {
-            for y in 1..MAP_HEIGHT - 1 {
+        for x in 1..map_width - 1 {
+            for y in 1..map_height - 1 {
                 // Variable keeps track of how many walls are attached to a tile.
                 let mut wall_count = 0;
 
@@ -57,8 +60,8 @@ pub fn cellular_automata(
         }
 
         // Iterates once more through the map.
-        for x in 1..MAP_WIDTH - 1 {
-            for y in 1..MAP_HEIGHT - 1 {
+        for x in 1..map_width - 1 {
+            for y in 1..map_height - 1 {
                 // Check the value of the purge map at each point
                 match purge_map[x as usize][y as usize] {
                     // If purge map is true, set that tile to a wall, and reset that point of the purge map.
@@ -67,21 +70,19 @@ pub fn cellular_automata(
                         purge_map[x as usize][y as usize] = false;
                     },
                     // Otherwise, set that tile to an empty tile.
-                    false => { map[x as usize][y as usize] = Tile::empty(colors); },
+                    false => { map[x as usize][y as usize] = Tile::empty(colors, rng); },
                 }
             }
         }
 
         // Displays the map at each iteration of the map.
-        if should_render {
-            render_map(tcod, map, 10);
-        }
+        recorder.snapshot(map, 10);
     }
 
     // Place the player on a random empty tile.
     loop {
-        let player_x = rand::thread_rng().gen_range(1, MAP_WIDTH - 1);
-        let player_y = rand::thread_rng().gen_range(1, MAP_HEIGHT - 1);
+        let player_x = rng.gen_range(1, map_width - 1);
+        let player_y = rng.gen_range(1, map_height - 1);
         if map[player_x as usize][player_y as usize].empty == true {
             // Places player in the center of the room.
             player.set_pos(player_x, player_y);
@@ -0,0 +1,171 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::environment::map::tiles::Tile;
+use crate::environment::*;
+use crate::objects::entities::Entities;
+use crate::pathing::reachable_tiles;
+use crate::rng::GameRng;
+
+use std::collections::VecDeque;
+
+// Most cut off areas that will be tunnelled to, before the level is given up on.
+const MAX_REPAIRS: usize = 250;
+
+// Makes sure every bit of floor, and everything standing on it, can be reached from where the player starts.
+// Anything spawned inside a wall has the wall cleared, and floor that is cut off is tunnelled to rather than filled in.
+// Returns false if the level couldn't be joined up, so it can be made again.
+pub fn repair_connectivity(
+    map: &mut Map,
+    entities: &Entities,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> bool {
+    let start = entities.player().pos();
+    if !in_map(map, start.0, start.1) {
+        return false;
+    }
+
+    for (_, object) in entities.iter() {
+        let (x, y) = object.pos();
+        if in_map(map, x, y) && map[x as usize][y as usize].blocked {
+            map[x as usize][y as usize] = Tile::empty(colors, rng);
+        }
+    }
+
+    let (map_width, map_height) = map_dimensions(map);
+    for _ in 0..MAX_REPAIRS {
+        // Finds the first bit of open ground that can't be reached.
+        let reached = reachable_tiles(map, start);
+        let cut_off = (0..map_width)
+            .flat_map(|x| (0..map_height).map(move |y| (x, y)))
+            .find(|&(x, y)| !map[x as usize][y as usize].blocked && !reached[x as usize][y as usize]);
+
+        match cut_off {
+            // Everything is joined up, and every entity stands on open ground, so they can all be reached.
+            None => return true,
+            Some(tile) => {
+                if !tunnel_to_reachable(tile, &reached, map, colors, rng) {
+                    return false;
+                }
+                recorder.snapshot(map, 4);
+            },
+        }
+    }
+    false
+}
+
+// Tunnels from a cut off area to the closest ground that can be reached.
+// Searches outwards from the whole area at once, straight through walls, so the tunnel is as short as possible.
+fn tunnel_to_reachable(
+    from: (i32, i32),
+    reached: &[Vec<bool>],
+    map: &mut Map,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+) -> bool {
+    let (map_width, map_height) = map_dimensions(map);
+    let area = reachable_tiles(map, from);
+
+    // Where the search came from to get to each tile. Tiles in the cut off area are where it started.
+    let mut came_from = vec![vec![None; map_height as usize]; map_width as usize];
+    let mut frontier = VecDeque::new();
+    for x in 0..map_width {
+        for y in 0..map_height {
+            if area[x as usize][y as usize] {
+                came_from[x as usize][y as usize] = Some((x, y));
+                frontier.push_back((x, y));
+            }
+        }
+    }
+
+    while let Some((x, y)) = frontier.pop_front() {
+        if reached[x as usize][y as usize] {
+            // Digs back along the way the search came, until it is back in the cut off area.
+            let (mut x, mut y) = (x, y);
+            while !area[x as usize][y as usize] {
+                if map[x as usize][y as usize].blocked {
+                    map[x as usize][y as usize] = Tile::empty(colors, rng);
+                }
+                let (previous_x, previous_y) = came_from[x as usize][y as usize].unwrap();
+                x = previous_x;
+                y = previous_y;
+            }
+            return true;
+        }
+
+        // Only moves straight, so the tunnels look like corridors. The outer wall is never dug through.
+        for (dx, dy) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (next_x, next_y) = (x + dx, y + dy);
+            if next_x > 0 && next_y > 0 && next_x < map_width - 1 && next_y < map_height - 1 &&
+                came_from[next_x as usize][next_y as usize].is_none()
+            {
+                came_from[next_x as usize][next_y as usize] = Some((x, y));
+                frontier.push_back((next_x, next_y));
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::gen_colors;
+    use crate::objects::Object;
+
+    // A map of solid wall, with the player standing in the first room given.
+    fn walled_map(rooms: &[(i32, i32, i32, i32)], colors: &[Color; 7], rng: &mut GameRng) -> (Map, Entities) {
+        let mut map = vec![vec![Tile::wall(colors); 20]; 30];
+        for &(x1, y1, x2, y2) in rooms {
+            for x in x1..=x2 {
+                for y in y1..=y2 {
+                    map[x as usize][y as usize] = Tile::empty(colors, rng);
+                }
+            }
+        }
+        let mut entities = Entities::new(Object::new_player());
+        entities.player_mut().set_pos(rooms[0].0, rooms[0].1);
+        (map, entities)
+    }
+
+    fn all_reachable(map: &Map, start: (i32, i32)) -> bool {
+        let reached = reachable_tiles(map, start);
+        map.iter().enumerate().all(|(x, column)| {
+            column.iter().enumerate().all(|(y, tile)| tile.blocked || reached[x][y])
+        })
+    }
+
+    #[test]
+    fn separate_rooms_are_joined_up() {
+        let mut rng = GameRng::from_seed(5);
+        let colors = gen_colors(&mut rng);
+        let (mut map, entities) = walled_map(&[(2, 2, 6, 6), (20, 10, 25, 15), (3, 14, 5, 17)], &colors, &mut rng);
+        assert!(!all_reachable(&map, (2, 2)));
+
+        assert!(repair_connectivity(&mut map, &entities, &colors, &mut rng, &mut MapRecorder::new(false)));
+        assert!(all_reachable(&map, (2, 2)));
+    }
+
+    #[test]
+    fn entities_in_walls_are_dug_out() {
+        let mut rng = GameRng::from_seed(5);
+        let colors = gen_colors(&mut rng);
+        let (mut map, mut entities) = walled_map(&[(2, 2, 6, 6)], &colors, &mut rng);
+        let mut chest = Object::new_player();
+        chest.set_pos(15, 15);
+        entities.insert(chest);
+
+        assert!(repair_connectivity(&mut map, &entities, &colors, &mut rng, &mut MapRecorder::new(false)));
+        assert!(!map[15][15].blocked);
+        assert!(reachable_tiles(&map, (2, 2))[15][15]);
+    }
+
+    #[test]
+    fn players_off_the_map_fail() {
+        let mut rng = GameRng::from_seed(5);
+        let colors = gen_colors(&mut rng);
+        let (mut map, mut entities) = walled_map(&[(2, 2, 6, 6)], &colors, &mut rng);
+        entities.player_mut().set_pos(-1, 40);
+        assert!(!repair_connectivity(&mut map, &entities, &colors, &mut rng, &mut MapRecorder::new(false)));
+    }
+}
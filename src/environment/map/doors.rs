@@ -0,0 +1,56 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::environment::map::tiles::{ Tile, TileType };
+use crate::environment::*;
+use crate::rng::GameRng;
+
+use rand::*;
+
+// Chance, out of 100, of a doorway getting a door.
+const DOOR_CHANCE: u32 = 60;
+
+// Chance, out of 100, of a door being locked.
+const LOCKED_CHANCE: u32 = 15;
+
+// Puts doors in the gaps where corridors have been dug through the walls of rooms.
+// A gap only counts as a doorway if it is one tile wide, with open ground on both sides of it.
+pub fn place_doors(rooms: &[Rect], map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
+    for room in rooms {
+        // The top and bottom walls. Doorways here lead up and down, with wall to the left and right.
+        for x in (room.x1 + 1)..room.x2 {
+            for &y in &[room.y1, room.y2] {
+                if doorway(x, y, (1, 0), map) {
+                    add_door(x, y, map, colors, rng);
+                }
+            }
+        }
+        // The left and right walls.
+        for y in (room.y1 + 1)..room.y2 {
+            for &x in &[room.x1, room.x2] {
+                if doorway(x, y, (0, 1), map) {
+                    add_door(x, y, map, colors, rng);
+                }
+            }
+        }
+    }
+    recorder.snapshot(map, 4);
+}
+
+// Checks for floor with wall either side of it along the room's wall, and open ground through it.
+fn doorway(x: i32, y: i32, (along_x, along_y): (i32, i32), map: &Map) -> bool {
+    let (map_width, map_height) = map_dimensions(map);
+    if x <= 0 || y <= 0 || x >= map_width - 1 || y >= map_height - 1 {
+        return false;
+    }
+    let tile = |x: i32, y: i32| &map[x as usize][y as usize];
+
+    tile(x, y).tiletype == TileType::Empty &&
+        tile(x - along_x, y - along_y).wall && tile(x + along_x, y + along_y).wall &&
+        !tile(x - along_y, y - along_x).blocked && !tile(x + along_y, y + along_x).blocked
+}
+
+fn add_door(x: i32, y: i32, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
+    if rng.gen_range(0, 100) < DOOR_CHANCE {
+        let locked = rng.gen_range(0, 100) < LOCKED_CHANCE;
+        map[x as usize][y as usize] = Tile::door(colors, locked);
+    }
+}
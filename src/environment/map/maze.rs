@@ -1,6 +1,7 @@
-use crate::graphics::render_map;
+use crate::environment::map::recorder::MapRecorder;
+use crate::rng::GameRng;
+use crate::objects::Object;
 use crate::environment::*;
-use crate::Tcod;
 
 use std::cmp;
 
@@ -8,21 +9,22 @@ pub fn maze(
     map: &mut Map,
     colors: &[Color; 7],
     player: &mut Object,
-    tcod: &mut Tcod,
-    should_render: bool,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
 ) {
-    let mut maze = Maze::new(MAP_WIDTH as u32, MAP_HEIGHT as u32);
+    let (map_width, map_height) = map_dimensions(map);
+
+    let mut maze = Maze::new(map_width as u32, map_height as u32);
     // Creates a grid of empty tiles, and adds each point into a vector.
-    for x in 0..MAP_WIDTH {
-        for y in 0..MAP_HEIGHT {
+    // The outer edge is left alone, so the maze is always walled in whatever the map size.
+    for x in 1..(map_width - 1) {
+        for y in 1..(map_height - 1) {
             if x % 2 == 1 && y % 2 == 1 {
-                map[x as usize][y as usize] = Tile::empty(colors);
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
                 maze.points.push(Point::new(x as u32, y as u32));
             }
         }
-        if should_render {
-            render_map(tcod, map, 2);
-        }
+        recorder.snapshot(map, 2);
     }
 
     let mut counter = 0;
@@ -31,13 +33,13 @@ pub fn maze(
         match maze.get_point(maze.get_x(), maze.get_y()) {
             Some(point) => {
                 maze.visit(point);
-                match maze.find_next(point) {
+                match maze.find_next(point, rng) {
                     Some(new_point) => {
-                        Maze::break_wall(point, new_point, map, colors);
+                        Maze::break_wall(point, new_point, map, colors, rng);
                         maze.pos_move(new_point);
                         counter += 1;
-                        if should_render && counter % 4 == 0 {
-                            render_map(tcod, map, 2);
+                        if counter % 4 == 0 {
+                            recorder.snapshot(map, 2);
                         }
                     },
                     None => {
@@ -55,8 +57,8 @@ pub fn maze(
 
     // Place the player on a random empty tile.
     loop {
-        let player_x = rand::thread_rng().gen_range(1, MAP_WIDTH - 1);
-        let player_y = rand::thread_rng().gen_range(1, MAP_HEIGHT - 1);
+        let player_x = rng.gen_range(1, map_width - 1);
+        let player_y = rng.gen_range(1, map_height - 1);
         if map[player_x as usize][player_y as usize].empty == true {
             // Places player in the center of the room.
             player.set_pos(player_x, player_y);
@@ -154,7 +156,7 @@ impl Maze {
             }
         }
 
-        if (x + 1) < (MAP_WIDTH) as u32 {
+        if (x + 1) < self.x {
             match self.get_point(point.x + 2, point.y) {
                 Some(neighbor) => if !neighbor.visited {
                     neighbors.push(neighbor)
@@ -172,7 +174,7 @@ impl Maze {
             }
         }
 
-        if (y + 1) < (MAP_HEIGHT) as u32 {
+        if (y + 1) < self.y {
             match self.get_point(point.x, point.y + 2) {
                 Some(neighbor) => if !neighbor.visited {
                     neighbors.push(neighbor)
@@ -183,29 +185,29 @@ impl Maze {
         neighbors
     }
 
-    fn find_next(&mut self, point: Point) -> Option<Point> {
+    fn find_next(&mut self, point: Point, rng: &mut GameRng) -> Option<Point> {
         let neighbors = self.get_neighbors(point);
         if !neighbors.is_empty() {
             if neighbors.len() == 1 {
                 return Some(neighbors[0])
             } else {
-                return Some(neighbors[rand::thread_rng().gen_range(0, neighbors.len())])
+                return Some(neighbors[rng.gen_range(0, neighbors.len())])
             }
         }
         None
     }
 
-    fn break_wall(one: Point, two: Point, map: &mut Map, colors: &[Color; 7]) {
+    fn break_wall(one: Point, two: Point, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
         let (x1, y1) = one.get_xy();
         let (x2, y2) = two.get_xy();
 
         if x1 == x2 {
             for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
-                map[x1 as usize][y as usize] = Tile::empty(colors);
+                map[x1 as usize][y as usize] = Tile::empty(colors, rng);
             }
         } else if y1 == y2 {
             for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
-                map[x as usize][y1 as usize] = Tile::empty(colors);
+                map[x as usize][y1 as usize] = Tile::empty(colors, rng);
             }
         }
     }
@@ -3,12 +3,24 @@ pub mod modifiers;
 pub mod drunk_walk;
 pub mod cellular_automata;
 pub mod maze;
+pub mod bsp;
+pub mod wfc;
+pub mod vaults;
+pub mod connectivity;
+pub mod doors;
+pub mod terrain;
 
 pub mod tiles;
+pub mod recorder;
+pub mod ascii;
 
-use crate::graphics::render_map;
-use crate::Tcod;
-use crate::environment::{ Map, MAP_WIDTH, MAP_HEIGHT };
+use recorder::MapRecorder;
+
+use crate::rng::GameRng;
+
+use rand::Rng;
+
+use crate::environment::{ Map, map_dimensions };
 use crate::environment::tiles::Tile;
 
 use crate::Point;
@@ -76,53 +88,53 @@ impl Rect {
 }
 
 // Places a rect onto a map.
-pub fn create_room(room: Rect, map: &mut Map, colors: &[Color; 7]) {
+pub fn create_room(room: Rect, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
     // Go through the tiles in the rectangle and make them passable.
     // Note: the +1's are to allow to for a wall around the rectangle.
     for x in (room.x1 + 1)..room.x2 {
         for y in (room.y1 + 1)..room.y2 {
-            map[x as usize][y as usize] = Tile::empty(colors);
+            map[x as usize][y as usize] = Tile::empty(colors, rng);
         }
     }
 }
 
 // Draws a horizontal line of empty tiles on the map.
-pub fn create_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map, colors: &[Color; 7]) {
+pub fn create_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
     // Horizontal tunnel. 'min()' and 'max()' are used in case 'x1 > x2'
     for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
-        map[x as usize][y as usize] = Tile::empty(colors);
+        map[x as usize][y as usize] = Tile::empty(colors, rng);
     }
 }
 
 // Draws a vertical line of empty tiles on the map.
-pub fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map, colors: &[Color; 7]) {
+pub fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
     // Vertical tunnel. Functions essentially the same as the horizontal tunnel
     for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
-        map[x as usize][y as usize] = Tile::empty(colors);
+        map[x as usize][y as usize] = Tile::empty(colors, rng);
     }
 }
 
 // Draws a horizontal line of secret path tiles on the map.
-pub fn create_secret_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map, colors: &[Color; 7]) {
+pub fn create_secret_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
     // Horizontal tunnel. 'min()' and 'max()' are used in case 'x1 > x2'
     for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
         if map[x as usize][y as usize].wall {
-            map[x as usize][y as usize] = Tile::hidden_passage(colors);
+            map[x as usize][y as usize] = Tile::hidden_passage(colors, rng);
         }
     }
 }
 
 // Draws a vertical line of secret path tiles on the map.
-pub fn create_secret_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map, colors: &[Color; 7]) {
+pub fn create_secret_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
     // Vertical tunnel. Functions essentially the same as the horizontal tunnel
     for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
         if map[x as usize][y as usize].wall {
-            map[x as usize][y as usize] = Tile::hidden_passage(colors);
+            map[x as usize][y as usize] = Tile::hidden_passage(colors, rng);
         }
     }
 }
 
-pub fn create_tunnels(rooms: &mut Vec<Rect>, mut map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn create_tunnels(rooms: &mut Vec<Rect>, mut map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
 
     // Variables to keep track of depth in the rooms vector, and if the loop should continue.
     let mut keep_connecting = true;
@@ -133,31 +145,29 @@ pub fn create_tunnels(rooms: &mut Vec<Rect>, mut map: &mut Map, colors: &[Color;
         let (x1, y1) = rooms[room_num].center();
         let (x2, y2) = rooms[room_num + 1].center();
 
-        if rand::random() {
+        if rng.gen() {
             // Horizontal tunnel first
-            create_h_tunnel(x1, x2, y1, &mut map, &colors);
-            create_v_tunnel(y1, y2, x2, &mut map, &colors);
+            create_h_tunnel(x1, x2, y1, &mut map, &colors, rng);
+            create_v_tunnel(y1, y2, x2, &mut map, &colors, rng);
 
-            if rand::random() {
+            if rng.gen() {
                 // Horizontal secret tunnel first
-                create_secret_v_tunnel(y1, y2, x1, &mut map, &colors);
-                create_secret_h_tunnel(x1, x2, y2, &mut map, &colors);
+                create_secret_v_tunnel(y1, y2, x1, &mut map, &colors, rng);
+                create_secret_h_tunnel(x1, x2, y2, &mut map, &colors, rng);
             }
         } else {
             // Vertical tunnel first
-            create_v_tunnel(y1, y2, x1, &mut map, &colors);
-            create_h_tunnel(x1, x2, y2, &mut map, &colors);
+            create_v_tunnel(y1, y2, x1, &mut map, &colors, rng);
+            create_h_tunnel(x1, x2, y2, &mut map, &colors, rng);
 
-            if rand::random() {
+            if rng.gen() {
                 // Vertical secret tunnel first
-                create_secret_h_tunnel(x1, x2, y1, &mut map, &colors);
-                create_secret_v_tunnel(y1, y2, x2, &mut map, &colors);
+                create_secret_h_tunnel(x1, x2, y1, &mut map, &colors, rng);
+                create_secret_v_tunnel(y1, y2, x2, &mut map, &colors, rng);
             }
         }
 
-        if should_render {
-            render_map(tcod, map, 4);
-        }
+        recorder.snapshot(map, 4);
 
         room_num += 1;
 
@@ -182,7 +192,7 @@ pub fn room_sorter<T: Ord>(rooms: &mut Vec<T>) {
     }
 }
 
-pub fn joiner(points: &mut Vec<(i32, i32)>, mut map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn joiner(points: &mut Vec<(i32, i32)>, mut map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
 
     // Variables to keep track of the depth of the vector, and if the loop should continue.
     let mut keep_connecting = true;
@@ -193,19 +203,17 @@ pub fn joiner(points: &mut Vec<(i32, i32)>, mut map: &mut Map, colors: &[Color;
         let (x1, y1) = points[point_num];
         let (x2, y2) = points[point_num + 1];
 
-        if rand::random() {
+        if rng.gen() {
             // Horizontal tunnel first
-            create_h_tunnel(x1, x2, y1, &mut map, &colors);
-            create_v_tunnel(y1, y2, x2, &mut map, &colors);
+            create_h_tunnel(x1, x2, y1, &mut map, &colors, rng);
+            create_v_tunnel(y1, y2, x2, &mut map, &colors, rng);
         } else {
             // Vertical tunnel first
-            create_v_tunnel(y1, y2, x1, &mut map, &colors);
-            create_h_tunnel(x1, x2, y2, &mut map, &colors);
+            create_v_tunnel(y1, y2, x1, &mut map, &colors, rng);
+            create_h_tunnel(x1, x2, y2, &mut map, &colors, rng);
         }
 
-        if should_render {
-            render_map(tcod, map, 4);
-        }
+        recorder.snapshot(map, 4);
 
         point_num += 1;
 
@@ -216,8 +224,10 @@ pub fn joiner(points: &mut Vec<(i32, i32)>, mut map: &mut Map, colors: &[Color;
 }
 
 pub fn cull_tiles(map: &mut Map, colors: &[Color; 7], points: &HashMap<Point, Point>) {
-    for x in 0..MAP_WIDTH {
-        for y in 0..MAP_HEIGHT {
+    let (map_width, map_height) = map_dimensions(map);
+
+    for x in 0..map_width {
+        for y in 0..map_height {
             match points.keys().find(|found| **found == (x as u32, y as u32)) {
                 Some((x, y)) => {
                     map[*x as usize][*y as usize] = Tile::wall(colors);
@@ -1,6 +1,6 @@
-use crate::graphics::render_map;
-use crate::Tcod;
-use crate::environment::{ Map, MAP_WIDTH, MAP_HEIGHT };
+use crate::environment::map::recorder::MapRecorder;
+use crate::rng::GameRng;
+use crate::environment::{ Map, map_dimensions };
 use crate::environment::tiles::Tile;
 use crate::environment::map::Rect;
 
@@ -8,13 +8,15 @@ use tcod::colors::*;
 use rand::*;
 
 // Creates some randomness along the outside of a rect.
-pub fn mine_drunkenly(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn mine_drunkenly(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
+    let (map_width, map_height) = map_dimensions(map);
+
     for room in rooms {
         // Creates a random amount of miners.
-        let miner_max = rand::thread_rng().gen_range(1, 5);
+        let miner_max = rng.gen_range(1, 5);
 
         // The amount of tiles carved out also varies.
-        let tiles_to_carve = rand::thread_rng().gen_range(20, 40);
+        let tiles_to_carve = rng.gen_range(20, 40);
 
         let tiles_per_miner = tiles_to_carve / miner_max;
 
@@ -29,19 +31,19 @@ pub fn mine_drunkenly(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tco
 
                 // If the miner is on a wall, it is made empty, and the tiles carved will increment.
                 if !map[x as usize][y as usize].empty {
-                    map[x as usize][y as usize] = Tile::empty(colors);
+                    map[x as usize][y as usize] = Tile::empty(colors, rng);
                     tiles_carved += 1
                 } else { // Otherwise, it will move to a space within the map boundary.
-                    let four_sided_dice = rand::thread_rng().gen_range(1, 5);
+                    let four_sided_dice = rng.gen_range(1, 5);
                     match four_sided_dice {
                         1 => { y += 1; // Moves down
-                            if y >= MAP_HEIGHT - 1 { y -= 1; } // If too close to edge, it moves up
+                            if y >= map_height - 1 { y -= 1; } // If too close to edge, it moves up
                         },
                         2 => { y -= 1; // Moves up
                             if y <= 1 { y += 1; } // If too close to edge, it moves down
                         },
                         3 => { x += 1; // Moves right
-                            if x >= MAP_WIDTH - 1 { x -= 1; } // If too close to edge, it moves left
+                            if x >= map_width - 1 { x -= 1; } // If too close to edge, it moves left
                         },
                         _ => { x -= 1; // Moves left
                             if x <= 1 { x += 1; } // If too close to edge, it moves right.
@@ -51,41 +53,41 @@ pub fn mine_drunkenly(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tco
             }
 
             // Map is rendered after each miner finishes their work.
-            if should_render {
-                render_map(tcod, map, 4);
-            }
+            recorder.snapshot(map, 4);
         }
     }
 }
 
 // Below are various forms of similar modifiers
-pub fn caved_in(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn caved_in(map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
     // Randomly decides what type of cave-in occurs.
-    if rand::random() {
-        butterfly(map, &colors, tcod, should_render);
+    if rng.gen() {
+        butterfly(map, &colors, rng, recorder);
     } else {
-        random_hole(map, &colors, tcod, should_render);
+        random_hole(map, &colors, rng, recorder);
     }
 }
 
 // Creates a random mirrored pattern from the center of the map.
-pub fn butterfly(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn butterfly(map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
+    let (map_width, map_height) = map_dimensions(map);
+
     // Creates two instances of the center point, and amount of tiles to be carved.
     let (mut left_x, mut left_y, mut right_x, mut right_y) =
-        (MAP_WIDTH / 2, MAP_HEIGHT / 2, MAP_WIDTH / 2, MAP_HEIGHT / 2);
+        (map_width / 2, map_height / 2, map_width / 2, map_height / 2);
     let mut tiles_to_carve = 250;
 
     // This is how many tiles will be removed per "carve"
     let brush = 2;
 
     // First, it removes the center tile that it begins on.
-    map[left_x as usize][left_y as usize] = Tile::empty(colors);
+    map[left_x as usize][left_y as usize] = Tile::empty(colors, rng);
 
     while tiles_to_carve > 0 {
 
         // Decides a random direction to move
         // If new position would be outside the map boundary, it returns to its previous position.
-        let four_sided_dice = rand::thread_rng().gen_range(1, 5);
+        let four_sided_dice = rng.gen_range(1, 5);
         match four_sided_dice {
             1 => {
                 left_y -= 1;
@@ -98,7 +100,7 @@ pub fn butterfly(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_ren
             },
             2 => {
                 left_y += 1;
-                if left_y >= MAP_HEIGHT - (brush + 1) {
+                if left_y >= map_height - (brush + 1) {
                     left_y -= 1;
                 } else {
                     right_y += 1;
@@ -116,7 +118,7 @@ pub fn butterfly(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_ren
             },
             _ => {
                 left_x += 1;
-                if left_x >= MAP_WIDTH / 2 {
+                if left_x >= map_width / 2 {
                     left_x -= 1;
                 } else {
                     right_x -= 1;
@@ -128,54 +130,56 @@ pub fn butterfly(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_ren
         // Removes the tiles according to brush size based on the new position.
         for x in (left_x - brush)..(left_x + brush) {
             for y in (left_y - brush)..(left_y + brush) {
-                map[x as usize][y as usize] = Tile::empty(colors);
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
             }
         }
 
         // Also removes the tiles on the mirrored side of the map.
         for x in (right_x - brush)..(right_x + brush) {
             for y in (right_y - brush)..(right_y + brush) {
-                map[x as usize][y as usize] = Tile::empty(colors);
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
             }
         }
 
-        if should_render && tiles_to_carve % 100 == 0 {
-            render_map(tcod, map, 5);
+        if tiles_to_carve % 100 == 0 {
+            recorder.snapshot(map, 5);
         }
     }
 }
 
 // Creates a random pattern from the center of the map.
-pub fn random_hole(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn random_hole(map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
+    let (map_width, map_height) = map_dimensions(map);
+
     // Creates two instances of the center point, and amount of tiles to be carved.
-    let mut x = MAP_WIDTH / 2;
-    let mut y = MAP_HEIGHT / 2;
+    let mut x = map_width / 2;
+    let mut y = map_height / 2;
     let mut tiles_to_carve = 500;
 
     // This is how many tiles will be removed per "carve"
     let brush = 2;
 
     // First, it removes the center tile that it begins on.
-    map[x as usize][y as usize] = Tile::empty(colors);
+    map[x as usize][y as usize] = Tile::empty(colors, rng);
 
     while tiles_to_carve > 0 {
 
         // Decides a random direction to move
         // If new position would be outside the map boundary, it returns to its previous position.
-        let four_sided_dice = rand::thread_rng().gen_range(1, 5);
+        let four_sided_dice = rng.gen_range(1, 5);
         match four_sided_dice {
             1 => {
                 y -= 1;
                 if y <= (brush + 1) {
-                    y = MAP_HEIGHT / 2;
+                    y = map_height / 2;
                 } else {
                     tiles_to_carve -= 1;
                 }
             },
             2 => {
                 y += 1;
-                if y >= MAP_HEIGHT - (brush + 1) {
-                    y = MAP_HEIGHT / 2;
+                if y >= map_height - (brush + 1) {
+                    y = map_height / 2;
                 } else {
                     tiles_to_carve -= 1;
                 }
@@ -183,15 +187,15 @@ pub fn random_hole(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_r
             3 => {
                 x -= 1;
                 if x <= (brush + 1) {
-                    x = MAP_WIDTH / 2;
+                    x = map_width / 2;
                 } else {
                     tiles_to_carve -= 1;
                 }
             },
             _ => {
                 x += 1;
-                if x >= MAP_WIDTH - (brush + 1) {
-                    x = MAP_WIDTH / 2;
+                if x >= map_width - (brush + 1) {
+                    x = map_width / 2;
                 } else {
                     tiles_to_carve -= 1;
                 }
@@ -201,18 +205,18 @@ pub fn random_hole(map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_r
         // Removes the tiles according to brush size based on the new position.
         for x in (x - brush)..(x + brush) {
             for y in (y - brush)..(y + brush) {
-                map[x as usize][y as usize] = Tile::empty(colors);
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
             }
         }
 
-        if should_render && tiles_to_carve % 100 == 0 {
-            render_map(tcod, map, 5);
+        if tiles_to_carve % 100 == 0 {
+            recorder.snapshot(map, 5);
         }
     }
 }
 
 // Scatters some random wall tiles into rooms, without impeding entrance/stairs
-pub fn rubble(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn rubble(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
     // Designates the room prior to the stairs.
     // This allows you to stop before the stair room, so the tile is always accessible.
     let final_room = &rooms[rooms.len() - 2];
@@ -225,19 +229,17 @@ pub fn rubble(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut
 
         for _ in 1..possible_debris {
             // Finds random tile in range of respective room.
-            let x = rand::thread_rng().gen_range(room.x1 + 2, room.x2 - 1);
-            let y = rand::thread_rng().gen_range(room.y1 + 2, room.y2 - 1);
+            let x = rng.gen_range(room.x1 + 2, room.x2 - 1);
+            let y = rng.gen_range(room.y1 + 2, room.y2 - 1);
 
             // Flips a coin. If heads, debris is placed. Otherwise, nothing happens.
-            if rand::random() {
+            if rng.gen() {
                 map[x as usize][y as usize] = Tile::wall(colors);
             }
         }
 
         // Toggles on/off depending on map-generation visualization option in make_map().
-        if should_render {
-            render_map(tcod, map, 4);
-        }
+        recorder.snapshot(map, 4);
 
         // If the current room is the room before the stair room, break the loop.
         if room == final_room {
@@ -247,7 +249,7 @@ pub fn rubble(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut
 }
 
 // Places wall tiles as pillars in the four corners of each room, without impeding the entrances/stairs.
-pub fn pillars(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut Tcod, should_render: bool) {
+pub fn pillars(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], rng: &mut GameRng, recorder: &mut MapRecorder) {
     // Designates the room prior to the stairs.
     // This allows you to stop before the stair room, so the tile is always accessible.
     let final_room = &rooms[rooms.len() - 2];
@@ -259,7 +261,7 @@ pub fn pillars(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut
         let possible_pillars = tiles / 5;
 
         // If so, place pillars in the four corners of the room.
-        if possible_pillars < 16 && rand::random() {
+        if possible_pillars < 16 && rng.gen() {
             map[(room.x1 + 2) as usize][(room.y1 + 2) as usize] = Tile::wall(colors);
             map[(room.x2 - 2) as usize][(room.y1 + 2) as usize] = Tile::wall(colors);
             map[(room.x1 + 2) as usize][(room.y2 - 2) as usize] = Tile::wall(colors);
@@ -267,9 +269,7 @@ pub fn pillars(rooms: &Vec<Rect>, map: &mut Map, colors: &[Color; 7], tcod: &mut
         }
 
         // Toggles on/off depending on map-generation visualization option in make_map().
-        if should_render {
-            render_map(tcod, map, 4);
-        }
+        recorder.snapshot(map, 4);
 
         // If the current room is the room before the stair room, break the loop.
         if room == final_room {
@@ -0,0 +1,31 @@
+use crate::environment::Map;
+
+// Collects snapshots of the map while it is being generated.
+// The generators don't need a window to run, so instead of drawing each step straight away,
+// they hand the map to the recorder, and a front-end can play the snapshots back afterwards.
+pub struct MapRecorder {
+    pub enabled: bool,
+    frames: Vec<(Map, u32)>,
+}
+
+impl MapRecorder {
+    // Recorder constructor. Nothing is stored unless it is enabled.
+    pub fn new(enabled: bool) -> MapRecorder {
+        MapRecorder {
+            enabled,
+            frames: Vec::new(),
+        }
+    }
+
+    // Stores a copy of the map, alongside how many frames it should be shown for.
+    pub fn snapshot(&mut self, map: &Map, frames: u32) {
+        if self.enabled {
+            self.frames.push((map.clone(), frames));
+        }
+    }
+
+    // Hands over every snapshot taken so far, leaving the recorder empty.
+    pub fn drain(&mut self) -> Vec<(Map, u32)> {
+        self.frames.drain(..).collect()
+    }
+}
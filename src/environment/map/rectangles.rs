@@ -1,5 +1,6 @@
-use crate::graphics::render_map;
-use crate::Tcod;
+use crate::environment::map::recorder::MapRecorder;
+use crate::rng::GameRng;
+use crate::objects::Object;
 use crate::environment::map::create_room;
 use crate::environment::*;
 
@@ -10,17 +11,19 @@ pub fn rectangles(
     mut map: &mut Map,
     colors: &[Color; 7],
     player: &mut Object,
-    tcod: &mut Tcod,
-    should_render: bool,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
 ) {
+    let (map_width, map_height) = map_dimensions(map);
+
     // MAX ROOMS is just the maximum possible rooms. It doesn't guarantee that amount.
     for _ in 0..MAX_ROOMS {
         // Random width and height
-        let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
         // Random position without going outside the map boundaries
-        let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-        let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
+        let x = rng.gen_range(0, map_width - w);
+        let y = rng.gen_range(0, map_height - h);
 
         let new_room = Rect::new(x, y, w, h);
 
@@ -30,7 +33,7 @@ pub fn rectangles(
         // Adds in rooms according to world path value
         if !failed {
             // Paints room onto map tiles
-            create_room(new_room, &mut map, &colors);
+            create_room(new_room, &mut map, &colors, rng);
 
             // Center coordinates of the new room, will be used later
             let (new_x, new_y) = new_room.center();
@@ -44,8 +47,6 @@ pub fn rectangles(
         }
 
         // Possibly renders map after each room is placed.
-        if should_render {
-            render_map(tcod, map, 4);
-        }
+        recorder.snapshot(map, 4);
     }
 }
@@ -0,0 +1,125 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::environment::map::tiles::{ Tile, TileType };
+use crate::environment::*;
+use crate::objects::entities::Entities;
+use crate::rng::GameRng;
+
+use rand::*;
+
+// Random spots tried when looking for somewhere to start each patch of terrain.
+const SEED_ATTEMPTS: u32 = 50;
+
+// The terrain that each theme fills parts of its floors with. Some themes don't have any.
+fn theme_terrain(theme: MapTheme) -> Option<TileType> {
+    match theme {
+        MapTheme::Fire => Some(TileType::Lava),
+        MapTheme::Water => Some(TileType::DeepWater),
+        MapTheme::Nature => Some(TileType::Bramble),
+        MapTheme::Crystal => Some(TileType::Crystal),
+        MapTheme::Death => Some(TileType::Miasma),
+        MapTheme::Light | MapTheme::Earth => None,
+    }
+}
+
+// Scatters patches of the theme's terrain across the floor, once everything has been spawned.
+// Terrain never goes under anything, or right next to a door, so doorways and stairs are always left clear.
+pub fn themed_terrain(
+    map: &mut Map,
+    entities: &Entities,
+    theme: MapTheme,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) {
+    let terrain = match theme_terrain(theme) {
+        Some(terrain) => terrain,
+        None => return,
+    };
+    let (map_width, map_height) = map_dimensions(map);
+    let tile = Tile::terrain(terrain, colors);
+
+    for _ in 0..rng.gen_range(2, 7) {
+        // Finds some open floor to start the patch from.
+        let mut start = None;
+        for _ in 0..SEED_ATTEMPTS {
+            let x = rng.gen_range(1, map_width - 1);
+            let y = rng.gen_range(1, map_height - 1);
+            if can_cover(x, y, &tile, map, entities) {
+                start = Some((x, y));
+                break;
+            }
+        }
+        let (mut x, mut y) = match start {
+            Some(start) => start,
+            None => continue,
+        };
+
+        // The patch spreads out by wandering around, covering floor as it goes.
+        for _ in 0..rng.gen_range(5, 25) {
+            if can_cover(x, y, &tile, map, entities) {
+                map[x as usize][y as usize] = tile;
+            }
+            let (dx, dy) = match rng.gen_range(0, 4) {
+                0 => (1, 0),
+                1 => (-1, 0),
+                2 => (0, 1),
+                _ => (0, -1),
+            };
+            if x + dx > 0 && y + dy > 0 && x + dx < map_width - 1 && y + dy < map_height - 1 {
+                x += dx;
+                y += dy;
+            }
+        }
+        recorder.snapshot(map, 4);
+    }
+}
+
+// Terrain only replaces plain floor, with nothing on it and no door beside it.
+// Lava and miasma are also kept out of corridors, as there would be no way around them.
+fn can_cover(x: i32, y: i32, tile: &Tile, map: &Map, entities: &Entities) -> bool {
+    if map[x as usize][y as usize].tiletype != TileType::Empty || entities.at(x, y).next().is_some() {
+        return false;
+    }
+    if tile.harmful() && in_corridor(x, y, map) {
+        return false;
+    }
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            if in_map(map, x + dx, y + dy) && map[(x + dx) as usize][(y + dy) as usize].is_door() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// A tile with something blocking it on both sides, either across or up and down, such as in a corridor or doorway.
+fn in_corridor(x: i32, y: i32, map: &Map) -> bool {
+    let blocked = |x: i32, y: i32| !in_map(map, x, y) || map[x as usize][y as usize].blocked;
+    (blocked(x - 1, y) && blocked(x + 1, y)) || (blocked(x, y - 1) && blocked(x, y + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::gen_colors;
+    use crate::objects::Object;
+
+    #[test]
+    fn corridors_are_kept_clear_of_lava() {
+        let mut rng = GameRng::from_seed(4);
+        let colors = gen_colors(&mut rng);
+        let mut entities = Entities::new(Object::new_player());
+        entities.player_mut().set_pos(1, 1);
+
+        // A single long corridor is the only floor on the map.
+        let mut map = vec![vec![Tile::wall(&colors); 11]; 40];
+        for column in map.iter_mut().skip(2).take(36) {
+            column[5] = Tile::empty(&colors, &mut rng);
+        }
+        for _ in 0..20 {
+            themed_terrain(&mut map, &entities, MapTheme::Fire, &colors, &mut rng, &mut MapRecorder::new(false));
+        }
+        assert!(map.iter().all(|column| column[5].tiletype != TileType::Lava));
+    }
+}
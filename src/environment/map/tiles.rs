@@ -1,4 +1,5 @@
 use crate::*;
+use crate::rng::GameRng;
 
 use tcod::colors::*;
 
@@ -6,12 +7,24 @@ use rand::*;
 
 use serde::{ Serialize, Deserialize };
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+// Color doors are drawn in.
+const DOOR_COLOR: Color = Color { r: 191, g: 127, b: 63 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Empty,
     Wall,
     SecretPath,
     Debug,
+    OpenDoor,
+    ClosedDoor,
+    LockedDoor,
+    // Terrain placed on some floors, depending on their theme.
+    Lava,
+    DeepWater,
+    Bramble,
+    Crystal,
+    Miasma,
 }
 
 // Tile struct definition.
@@ -32,12 +45,12 @@ pub struct Tile {
 impl Tile {
 
     // Used to create an empty tile.
-    pub fn empty(colors: &[Color; 7]) -> Tile {
+    pub fn empty(colors: &[Color; 7], rng: &mut GameRng) -> Tile {
         // Max chance is used to vary the frequency at which color variants appear.
-        let max_chance = rand::thread_rng().gen_range(4, 15);
+        let max_chance = rng.gen_range(4, 15);
 
         // A higher max chance means a lower likelihood of variants showing up.
-        let color_light = match rand::thread_rng().gen_range(1, max_chance) {
+        let color_light = match rng.gen_range(1, max_chance) {
             1 => colors[LIGHT_GROUND_COLOR + V_ONE],
             2 => colors[LIGHT_GROUND_COLOR + V_TWO],
             _ => colors[LIGHT_GROUND_COLOR]
@@ -81,9 +94,9 @@ impl Tile {
         }
     }
 
-    pub fn hidden_passage(colors: &[Color; 7]) -> Tile {
+    pub fn hidden_passage(colors: &[Color; 7], rng: &mut GameRng) -> Tile {
         // Secret path colors are the variants pulled from the base wall color.
-        let color_light = match rand::thread_rng().gen_range(1, 3) {
+        let color_light = match rng.gen_range(1, 3) {
             1 => colors[LIGHT_WALL_COLOR + V_ONE],
             _ => colors[LIGHT_WALL_COLOR + V_TWO],
         };
@@ -104,6 +117,102 @@ impl Tile {
         }
     }
 
+    // Doors start closed, and are colored like the wall they sit in.
+    pub fn door(colors: &[Color; 7], locked: bool) -> Tile {
+        let color_light = colors[LIGHT_WALL_COLOR + V_ONE];
+        let color_dark = color_light - colors[DARKNESS_MODIFIER];
+        let tiletype = match locked {
+            true => TileType::LockedDoor,
+            false => TileType::ClosedDoor,
+        };
+        Tile::from_type(tiletype, color_light, color_dark)
+    }
+
+    pub fn is_door(&self) -> bool {
+        match self.tiletype {
+            TileType::OpenDoor | TileType::ClosedDoor | TileType::LockedDoor => true,
+            _ => false,
+        }
+    }
+
+    // Opens, closes, or locks a door, keeping its colors and whether it has been seen.
+    // The FOV map has to be told about the change separately.
+    pub fn set_door(&mut self, tiletype: TileType) {
+        let explored = self.explored;
+        *self = Tile::from_type(tiletype, self.color_light, self.color_dark);
+        self.explored = explored;
+    }
+
+    // Themed terrain has its own colors, rather than taking them from the level.
+    pub fn terrain(tiletype: TileType, colors: &[Color; 7]) -> Tile {
+        let color_light = match tiletype {
+            TileType::Lava => Color { r: 207, g: 63, b: 0 },
+            TileType::DeepWater => Color { r: 0, g: 63, b: 159 },
+            TileType::Bramble => Color { r: 47, g: 95, b: 31 },
+            TileType::Crystal => Color { r: 127, g: 223, b: 223 },
+            TileType::Miasma => Color { r: 95, g: 47, b: 111 },
+            _ => colors[LIGHT_GROUND_COLOR],
+        };
+        let color_dark = color_light - colors[DARKNESS_MODIFIER];
+        Tile::from_type(tiletype, color_light, color_dark)
+    }
+
+    // Whether standing in the tile does damage.
+    pub fn harmful(&self) -> bool {
+        self.tiletype == TileType::Lava || self.tiletype == TileType::Miasma
+    }
+
+    // The character drawn over a tile, if it has one, along with its color.
+    pub fn glyph(&self) -> Option<(char, Color)> {
+        match self.tiletype {
+            TileType::OpenDoor => Some(('\'', DOOR_COLOR)),
+            TileType::ClosedDoor | TileType::LockedDoor => Some(('+', DOOR_COLOR)),
+            TileType::Lava => Some(('~', LIGHT_YELLOW)),
+            TileType::DeepWater => Some(('~', LIGHT_BLUE)),
+            TileType::Bramble => Some(('"', LIGHT_GREEN)),
+            TileType::Crystal => Some(('*', LIGHTEST_CYAN)),
+            TileType::Miasma => Some((':', LIGHT_PURPLE)),
+            _ => None,
+        }
+    }
+
+    // Marks a secret passage as found. It is drawn like the floor from then on, and can be seen through.
+    pub fn reveal(&mut self, colors: &[Color; 7]) {
+        self.found = true;
+        self.block_sight = false;
+        self.color_light = colors[LIGHT_GROUND_COLOR + V_TWO];
+        self.color_dark = self.color_light - colors[DARKNESS_MODIFIER];
+    }
+
+    // Rebuilds a tile from its type and colors, as every other flag follows from the type.
+    // Used when loading compact saves, which only keep the type, colors, and what the player has discovered.
+    pub fn from_type(tiletype: TileType, color_light: Color, color_dark: Color) -> Tile {
+        let (empty, wall, secret_path, blocked, block_sight) = match tiletype {
+            TileType::Empty => (true, false, false, false, false),
+            TileType::Wall => (false, true, false, true, true),
+            TileType::SecretPath => (false, false, true, false, true),
+            TileType::Debug => (true, false, true, false, true),
+            TileType::OpenDoor => (false, false, false, false, false),
+            TileType::ClosedDoor | TileType::LockedDoor => (false, false, false, true, true),
+            TileType::Lava | TileType::DeepWater => (false, false, false, false, false),
+            TileType::Bramble | TileType::Miasma => (false, false, false, false, true),
+            TileType::Crystal => (false, false, false, true, false),
+        };
+
+        Tile {
+            empty: empty,
+            wall: wall,
+            secret_path: secret_path,
+            found: false,
+            blocked: blocked,
+            explored: false,
+            block_sight: block_sight,
+            color_light: color_light,
+            color_dark: color_dark,
+            tiletype: tiletype,
+        }
+    }
+
     // Found, and Path are just debug tiles.
     pub fn found() -> Tile {
         Tile {
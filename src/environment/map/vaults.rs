@@ -0,0 +1,301 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::environment::map::tiles::Tile;
+use crate::environment::*;
+use crate::pathing::reachable_tiles;
+use crate::rng::GameRng;
+
+use rand::*;
+
+// Chance, out of 100, of a level having a vault at all.
+const VAULT_CHANCE: u32 = 50;
+
+// Number of places tried when fitting a vault into a cave, before giving up.
+const CAVE_ATTEMPTS: u32 = 40;
+
+// Furthest a vault entrance will tunnel out to reach the rest of a cave.
+const MAX_ENTRANCE_TUNNEL: i32 = 8;
+
+// A hand made room, drawn as text.
+//   '#' wall, '.' floor, '+' secret passage,
+//   'M' a monster, 'I' an item, '<' the stairs (each on a floor tile),
+//   ' ' leaves whatever was generated there alone.
+// Floor on the outer edge is an entrance, and should lead into the rest of the vault.
+pub struct Vault {
+    pub name: &'static str,
+    // Dungeon levels the vault can appear on, inclusive.
+    pub min_level: u32,
+    pub max_level: u32,
+    pub rows: &'static [&'static str],
+}
+
+// Every vault is small enough to fit in the largest rooms, leaving a walkway around it.
+pub const VAULTS: &[Vault] = &[
+    Vault {
+        name: "Shrine",
+        min_level: 1,
+        max_level: 6,
+        rows: &[
+            "###.###",
+            "#.....#",
+            "#.#.#.#",
+            "..#I#..",
+            "#.#M#.#",
+            "#.....#",
+            "###.###",
+        ],
+    },
+    Vault {
+        name: "Pillared hall",
+        min_level: 2,
+        max_level: 15,
+        rows: &[
+            "#.#####.#",
+            ".........",
+            "..#.M.#..",
+            "....I....",
+            "..#.M.#..",
+            ".........",
+            "#.#####.#",
+        ],
+    },
+    Vault {
+        name: "Hidden treasury",
+        min_level: 3,
+        max_level: 12,
+        rows: &[
+            "#########",
+            "#I.I#.M.#",
+            "#...+....",
+            "#I.I#.M.#",
+            "#########",
+        ],
+    },
+    Vault {
+        name: "Guarded stairs",
+        min_level: 5,
+        max_level: 30,
+        rows: &[
+            "#######",
+            "#M...M#",
+            "#.###.#",
+            "#.#<#.#",
+            "#.#.#.#",
+            "#.....#",
+            "###.###",
+        ],
+    },
+    Vault {
+        name: "Crossroads",
+        min_level: 8,
+        max_level: 30,
+        rows: &[
+            "   ###   ",
+            "   #M#   ",
+            "####.####",
+            "#M..I..M#",
+            "####.####",
+            "   #.#   ",
+            "   #.#   ",
+            "   #.#   ",
+            "   #.#   ",
+        ],
+    },
+];
+
+// Where a vault wants things spawned. These are filled in after the level's usual spawner has run.
+#[derive(Clone, Debug, Default)]
+pub struct VaultSpawns {
+    pub monsters: Vec<(i32, i32)>,
+    pub items: Vec<(i32, i32)>,
+    pub stairs: Option<(i32, i32)>,
+}
+
+// Rows of characters, turned and flipped to however the vault is being placed.
+type Layout = Vec<Vec<char>>;
+
+// Picks a vault suitable for the level, randomly turned and flipped, if the level gets one at all.
+fn choose_vault(level: u32, rng: &mut GameRng) -> Option<Layout> {
+    if rng.gen_range(0, 100) >= VAULT_CHANCE {
+        return None;
+    }
+    let suitable: Vec<&Vault> = VAULTS.iter()
+        .filter(|vault| level >= vault.min_level && level <= vault.max_level)
+        .collect();
+    if suitable.is_empty() {
+        return None;
+    }
+
+    let vault = suitable[rng.gen_range(0, suitable.len())];
+    let mut layout: Layout = vault.rows.iter().map(|row| row.chars().collect()).collect();
+    for _ in 0..rng.gen_range(0, 4) {
+        layout = rotate(&layout);
+    }
+    if rng.gen() {
+        for row in layout.iter_mut() {
+            row.reverse();
+        }
+    }
+    Some(layout)
+}
+
+// Turns a layout a quarter turn clockwise.
+fn rotate(layout: &Layout) -> Layout {
+    let height = layout.len();
+    let width = layout[0].len();
+    (0..width)
+        .map(|column| (0..height).rev().map(|row| layout[row][column]).collect())
+        .collect()
+}
+
+// Width and height of a layout, in tiles.
+fn layout_size(layout: &Layout) -> (i32, i32) {
+    (layout[0].len() as i32, layout.len() as i32)
+}
+
+// Places a vault inside one of the rooms, other than the first and last (where the player and the stairs go).
+// The vault sits in the middle of the room, with a walkway left around it for the corridors coming in.
+pub fn vault_in_rooms(
+    rooms: &[Rect],
+    start: (i32, i32),
+    map: &mut Map,
+    colors: &[Color; 7],
+    level: u32,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> VaultSpawns {
+    let layout = match choose_vault(level, rng) {
+        Some(layout) => layout,
+        None => return VaultSpawns::default(),
+    };
+    let (width, height) = layout_size(&layout);
+
+    let candidates: Vec<&Rect> = rooms.iter()
+        .take(rooms.len().saturating_sub(1))
+        .filter(|room| {
+            let (inside_width, inside_height) = (room.x2 - room.x1 - 1, room.y2 - room.y1 - 1);
+            let holds_start = start.0 > room.x1 && start.0 < room.x2 && start.1 > room.y1 && start.1 < room.y2;
+            !holds_start && inside_width >= width + 2 && inside_height >= height + 2
+        })
+        .collect();
+    if candidates.is_empty() {
+        return VaultSpawns::default();
+    }
+
+    let room = candidates[rng.gen_range(0, candidates.len())];
+    let x = room.x1 + 1 + (room.x2 - room.x1 - 1 - width) / 2;
+    let y = room.y1 + 1 + (room.y2 - room.y1 - 1 - height) / 2;
+    let spawns = try_stamp(&layout, (x, y), start, map, colors, rng).unwrap_or_default();
+    recorder.snapshot(map, 4);
+    spawns
+}
+
+// Places a vault somewhere in a cave, tunnelling out from its entrances to join it up.
+pub fn vault_in_cave(
+    start: (i32, i32),
+    map: &mut Map,
+    colors: &[Color; 7],
+    level: u32,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> VaultSpawns {
+    let layout = match choose_vault(level, rng) {
+        Some(layout) => layout,
+        None => return VaultSpawns::default(),
+    };
+    let (width, height) = layout_size(&layout);
+    let (map_width, map_height) = map_dimensions(map);
+
+    for _ in 0..CAVE_ATTEMPTS {
+        let x = rng.gen_range(1, map_width - width);
+        let y = rng.gen_range(1, map_height - height);
+        if let Some(spawns) = try_stamp(&layout, (x, y), start, map, colors, rng) {
+            recorder.snapshot(map, 4);
+            return spawns;
+        }
+    }
+    VaultSpawns::default()
+}
+
+// Stamps a vault onto the map with its top left corner at the given point.
+// The map is put back as it was if the vault would cut anything off, or couldn't be reached itself.
+fn try_stamp(
+    layout: &Layout,
+    (left, top): (i32, i32),
+    start: (i32, i32),
+    map: &mut Map,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+) -> Option<VaultSpawns> {
+    let (width, height) = layout_size(layout);
+    if start.0 >= left && start.0 < left + width && start.1 >= top && start.1 < top + height {
+        return None;
+    }
+
+    let before = map.clone();
+    let reachable_before = reachable_tiles(map, start);
+    let mut spawns = VaultSpawns::default();
+    let mut floor = vec![];
+
+    for (row, line) in layout.iter().enumerate() {
+        for (column, marker) in line.iter().enumerate() {
+            let (x, y) = (left + column as i32, top + row as i32);
+            let tile = &mut map[x as usize][y as usize];
+            match marker {
+                '#' => *tile = Tile::wall(colors),
+                '+' => *tile = Tile::hidden_passage(colors, rng),
+                ' ' => continue,
+                _ => *tile = Tile::empty(colors, rng),
+            }
+            match marker {
+                'M' => spawns.monsters.push((x, y)),
+                'I' => spawns.items.push((x, y)),
+                '<' => spawns.stairs = Some((x, y)),
+                _ => {},
+            }
+            if *marker != '#' {
+                floor.push((x, y));
+            }
+        }
+    }
+
+    // Entrances on the edge of the vault tunnel straight out, until they meet open ground.
+    for &(x, y) in &floor {
+        let outward = (
+            if x == left { -1 } else if x == left + width - 1 { 1 } else { 0 },
+            if y == top { -1 } else if y == top + height - 1 { 1 } else { 0 },
+        );
+        if outward != (0, 0) {
+            tunnel_out(x, y, outward, map, colors, rng);
+        }
+    }
+
+    // Nothing which could be reached before may be cut off, and the whole vault has to be reachable.
+    let reachable_after = reachable_tiles(map, start);
+    let (map_width, map_height) = map_dimensions(map);
+    let cut_off = (0..map_width as usize).any(|x| (0..map_height as usize).any(|y| {
+        reachable_before[x][y] && !map[x][y].blocked && !reachable_after[x][y]
+    }));
+    let unreachable = floor.iter().any(|&(x, y)| !reachable_after[x as usize][y as usize]);
+    if cut_off || unreachable {
+        *map = before;
+        return None;
+    }
+    Some(spawns)
+}
+
+// Digs out from an entrance in one direction, as long as open ground is found close enough.
+fn tunnel_out(x: i32, y: i32, (dx, dy): (i32, i32), map: &mut Map, colors: &[Color; 7], rng: &mut GameRng) {
+    let mut path = vec![];
+    let (mut x, mut y) = (x + dx, y + dy);
+    while path.len() < MAX_ENTRANCE_TUNNEL as usize && in_map(map, x, y) {
+        if !map[x as usize][y as usize].blocked {
+            for (x, y) in path {
+                map[x as usize][y as usize] = Tile::empty(colors, rng);
+            }
+            return;
+        }
+        path.push((x, y));
+        x += dx;
+        y += dy;
+    }
+}
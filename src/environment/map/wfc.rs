@@ -0,0 +1,434 @@
+use crate::environment::map::recorder::MapRecorder;
+use crate::environment::map::{ rectangles::rectangles, maze::maze, room_sorter, create_tunnels };
+use crate::environment::map::tiles::TileType;
+use crate::environment::*;
+use crate::objects::Object;
+use crate::rng::GameRng;
+use crate::{ LIGHT_GROUND_COLOR, DARKNESS_MODIFIER };
+
+use rand::*;
+
+use std::collections::HashMap;
+
+// Width and height of the patterns learnt from the samples.
+const N: usize = 3;
+
+// Times the level is started again after running into a contradiction, before giving up.
+const MAX_ATTEMPTS: u32 = 10;
+
+// Levels with less floor than this, out of 100, are thrown away and made again.
+const MIN_FLOOR_PERCENT: i32 = 30;
+
+// Cells decided between each snapshot, when the generation is being watched.
+const SNAPSHOT_EVERY: usize = 150;
+
+// Hand made samples to learn from. '#' is wall and '.' is floor.
+const SAMPLES: &[&[&str]] = &[
+    &[
+        "################",
+        "#....##.....####",
+        "#....##.....####",
+        "#...........####",
+        "#....##.....#..#",
+        "######.######..#",
+        "######.######..#",
+        "#.......#......#",
+        "#.......#..#####",
+        "#..............#",
+        "#.......#......#",
+        "################",
+    ],
+    &[
+        "##############",
+        "###...####..##",
+        "##.....##....#",
+        "#......#.....#",
+        "#..##.......##",
+        "#..###.....###",
+        "##..##....####",
+        "###.......####",
+        "####...##...##",
+        "###.....#....#",
+        "##...........#",
+        "##############",
+    ],
+];
+
+// The four directions cells are joined in.
+const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+// Walls and floors, stored as columns in the same way as the map. True is a wall.
+type Grid = Vec<Vec<bool>>;
+
+// A small square of walls and floors, read left to right, top to bottom.
+type Pattern = [bool; N * N];
+
+// Everything learnt from the samples.
+struct Model {
+    patterns: Vec<Pattern>,
+    // How often each pattern was seen, which decides how often it is picked.
+    weights: Vec<u32>,
+    // For each direction, and each pattern, the patterns that can sit next to it in that direction.
+    propagator: Vec<Vec<Vec<usize>>>,
+}
+
+// Wave function collapse.
+// Every 3x3 pattern of wall and floor in the samples is learnt, along with which patterns overlap each other.
+// The map starts out with every pattern possible everywhere. One spot at a time is decided on, and the choice
+// spreads out to rule out any pattern that no longer fits next to it, until the whole map is decided.
+// Returns false if no level could be made, so that another generator can be used instead.
+pub fn wave_function_collapse(
+    map: &mut Map,
+    colors: &[Color; 7],
+    player: &mut Object,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> bool {
+    let (map_width, map_height) = map_dimensions(map);
+
+    // Learns from the hand made samples, along with a couple of levels from the other generators.
+    let mut samples: Vec<Grid> = SAMPLES.iter().map(|rows| text_to_grid(rows)).collect();
+    samples.extend(generated_samples(colors, rng));
+    let model = learn(&samples);
+
+    for _ in 0..MAX_ATTEMPTS {
+        let grid = match collapse(&model, map_width as usize, map_height as usize, map, colors, rng, recorder) {
+            Some(grid) => grid,
+            None => continue,
+        };
+
+        // The outer edge is always wall, whatever was generated there.
+        let floor: Vec<(i32, i32)> = (1..map_width - 1)
+            .flat_map(|x| (1..map_height - 1).map(move |y| (x, y)))
+            .filter(|&(x, y)| !grid[x as usize][y as usize])
+            .collect();
+        if (floor.len() as i32) * 100 < map_width * map_height * MIN_FLOOR_PERCENT {
+            continue;
+        }
+
+        fill_walls(map, colors);
+        for &(x, y) in &floor {
+            map[x as usize][y as usize] = Tile::empty(colors, rng);
+        }
+        recorder.snapshot(map, 4);
+
+        // The player starts on a random bit of floor. Anything cut off from it is joined up afterwards.
+        let (player_x, player_y) = floor[rng.gen_range(0, floor.len())];
+        player.set_pos(player_x, player_y);
+        return true;
+    }
+
+    fill_walls(map, colors);
+    false
+}
+
+// Reads a hand made sample.
+fn text_to_grid(rows: &[&str]) -> Grid {
+    let width = rows[0].len();
+    (0..width)
+        .map(|x| rows.iter().map(|row| row.as_bytes()[x] == b'#').collect())
+        .collect()
+}
+
+// Makes a small level with the rooms and maze generators, to learn their corridors and corners.
+fn generated_samples(colors: &[Color; 7], rng: &mut GameRng) -> Vec<Grid> {
+    let mut recorder = MapRecorder::new(false);
+    let mut player = Object::new_player();
+    let blank = || vec![vec![Tile::wall(colors); MIN_MAP_HEIGHT as usize]; MIN_MAP_WIDTH as usize];
+
+    let mut rooms = vec![];
+    let mut rooms_map = blank();
+    rectangles(&mut rooms, &mut rooms_map, colors, &mut player, rng, &mut recorder);
+    if rooms.len() > 1 {
+        room_sorter(&mut rooms);
+        create_tunnels(&mut rooms, &mut rooms_map, colors, rng, &mut recorder);
+    }
+
+    let mut maze_map = blank();
+    maze(&mut maze_map, colors, &mut player, rng, &mut recorder);
+
+    vec![map_to_grid(&rooms_map), map_to_grid(&maze_map)]
+}
+
+// Secret passages are learnt as wall, as that is what they look like.
+fn map_to_grid(map: &Map) -> Grid {
+    map.iter().map(|column| column.iter().map(|tile| !tile.empty).collect()).collect()
+}
+
+fn fill_walls(map: &mut Map, colors: &[Color; 7]) {
+    for column in map.iter_mut() {
+        for tile in column.iter_mut() {
+            *tile = Tile::wall(colors);
+        }
+    }
+}
+
+// Collects every pattern in the samples, turned and flipped every way, and works out which can overlap.
+fn learn(samples: &[Grid]) -> Model {
+    let mut patterns: Vec<Pattern> = vec![];
+    let mut weights: Vec<u32> = vec![];
+    let mut index: HashMap<Pattern, usize> = HashMap::new();
+
+    for sample in samples {
+        let (width, height) = (sample.len(), sample[0].len());
+        if width < N || height < N {
+            continue;
+        }
+        for x in 0..=(width - N) {
+            for y in 0..=(height - N) {
+                let mut pattern = [false; N * N];
+                for i in 0..N {
+                    for j in 0..N {
+                        pattern[i + j * N] = sample[x + i][y + j];
+                    }
+                }
+
+                // Each of the four turns, both as it is and mirrored.
+                for _ in 0..4 {
+                    pattern = rotate(&pattern);
+                    for variant in [pattern, reflect(&pattern)].iter() {
+                        match index.get(variant) {
+                            Some(&id) => weights[id] += 1,
+                            None => {
+                                index.insert(*variant, patterns.len());
+                                patterns.push(*variant);
+                                weights.push(1);
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let propagator = DIRECTIONS.iter().map(|&(dx, dy)| {
+        patterns.iter().map(|a| {
+            (0..patterns.len()).filter(|&b| agrees(a, &patterns[b], dx, dy)).collect()
+        }).collect()
+    }).collect();
+
+    Model { patterns, weights, propagator }
+}
+
+fn rotate(pattern: &Pattern) -> Pattern {
+    let mut turned = [false; N * N];
+    for i in 0..N {
+        for j in 0..N {
+            turned[i + j * N] = pattern[(N - 1 - j) + i * N];
+        }
+    }
+    turned
+}
+
+fn reflect(pattern: &Pattern) -> Pattern {
+    let mut mirrored = [false; N * N];
+    for i in 0..N {
+        for j in 0..N {
+            mirrored[i + j * N] = pattern[(N - 1 - i) + j * N];
+        }
+    }
+    mirrored
+}
+
+// Checks whether pattern b can sit at an offset from pattern a, with the parts that overlap matching.
+fn agrees(a: &Pattern, b: &Pattern, dx: i32, dy: i32) -> bool {
+    let n = N as i32;
+    for x in 0..n {
+        for y in 0..n {
+            let (bx, by) = (x - dx, y - dy);
+            if bx >= 0 && by >= 0 && bx < n && by < n && a[(x + y * n) as usize] != b[(bx + by * n) as usize] {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// Decides a pattern for every cell of the map. Each cell is the top left corner of a pattern,
+// so there are fewer cells than tiles, and the patterns along the far edges fill in the rest.
+// Returns None if the map ran into a spot where nothing could fit.
+fn collapse(
+    model: &Model,
+    width: usize,
+    height: usize,
+    map: &mut Map,
+    colors: &[Color; 7],
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> Option<Grid> {
+    let pattern_count = model.patterns.len();
+    let (cells_wide, cells_high) = (width - N + 1, height - N + 1);
+    let cell_count = cells_wide * cells_high;
+
+    // Which patterns are still possible in each cell, and how many there are.
+    let mut wave = vec![vec![true; pattern_count]; cell_count];
+    let mut remaining = vec![pattern_count; cell_count];
+
+    // For each cell, pattern and direction, how many patterns in the neighbouring cell that way still allow it.
+    // Once that drops to zero, the pattern can't go in the cell.
+    let opposite = [1, 0, 3, 2];
+    let mut support = vec![[0u16; 4]; cell_count * pattern_count];
+    for cell in 0..cell_count {
+        for pattern in 0..pattern_count {
+            for direction in 0..4 {
+                support[cell * pattern_count + pattern][direction] =
+                    model.propagator[opposite[direction]][pattern].len() as u16;
+            }
+        }
+    }
+
+    let mut banned: Vec<(usize, usize)> = vec![];
+    let mut decided = 0;
+    loop {
+        // Picks the undecided cell with the fewest patterns left, starting the search from a random cell.
+        let start = rng.gen_range(0, cell_count);
+        let mut chosen = None;
+        for offset in 0..cell_count {
+            let cell = (start + offset) % cell_count;
+            if remaining[cell] > 1 && chosen.map_or(true, |best| remaining[cell] < remaining[best]) {
+                chosen = Some(cell);
+            }
+        }
+        let cell = match chosen {
+            Some(cell) => cell,
+            None => break,
+        };
+
+        // Picks one of the patterns left in that cell, with common patterns more likely.
+        let total: u32 = (0..pattern_count).filter(|&p| wave[cell][p]).map(|p| model.weights[p]).sum();
+        let mut roll = rng.gen_range(0, total);
+        let mut pick = 0;
+        for pattern in (0..pattern_count).filter(|&p| wave[cell][p]) {
+            if roll < model.weights[pattern] {
+                pick = pattern;
+                break;
+            }
+            roll -= model.weights[pattern];
+        }
+        for pattern in 0..pattern_count {
+            if pattern != pick && wave[cell][pattern] {
+                wave[cell][pattern] = false;
+                remaining[cell] -= 1;
+                banned.push((cell, pattern));
+            }
+        }
+
+        // Spreads the choice out, ruling out patterns that no longer have anything to sit next to.
+        while let Some((cell, pattern)) = banned.pop() {
+            let (cell_x, cell_y) = ((cell % cells_wide) as i32, (cell / cells_wide) as i32);
+            for (direction, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (next_x, next_y) = (cell_x + dx, cell_y + dy);
+                if next_x < 0 || next_y < 0 || next_x >= cells_wide as i32 || next_y >= cells_high as i32 {
+                    continue;
+                }
+                let next = next_y as usize * cells_wide + next_x as usize;
+                for &other in &model.propagator[direction][pattern] {
+                    let count = &mut support[next * pattern_count + other][direction];
+                    *count -= 1;
+                    if *count == 0 && wave[next][other] {
+                        wave[next][other] = false;
+                        remaining[next] -= 1;
+                        if remaining[next] == 0 {
+                            return None;
+                        }
+                        banned.push((next, other));
+                    }
+                }
+            }
+        }
+
+        decided += 1;
+        // Snapshots use plain floor, rather than the varied floor from Tile::empty, so that watching the
+        // generation doesn't use up the level's random numbers and change what gets made.
+        if recorder.enabled && decided % SNAPSHOT_EVERY == 0 {
+            let grid = to_grid(model, &wave, width, height);
+            let floor = Tile::from_type(
+                TileType::Empty,
+                colors[LIGHT_GROUND_COLOR],
+                colors[LIGHT_GROUND_COLOR] - colors[DARKNESS_MODIFIER],
+            );
+            for x in 1..width - 1 {
+                for y in 1..height - 1 {
+                    map[x][y] = match grid[x][y] {
+                        true => Tile::wall(colors),
+                        false => floor.clone(),
+                    };
+                }
+            }
+            recorder.snapshot(map, 2);
+        }
+    }
+
+    Some(to_grid(model, &wave, width, height))
+}
+
+// Turns the patterns chosen for each cell into walls and floors.
+// Cells which haven't been decided yet show the first pattern still possible there.
+fn to_grid(model: &Model, wave: &[Vec<bool>], width: usize, height: usize) -> Grid {
+    let (cells_wide, cells_high) = (width - N + 1, height - N + 1);
+    (0..width).map(|x| {
+        (0..height).map(|y| {
+            let (cell_x, cell_y) = (x.min(cells_wide - 1), y.min(cells_high - 1));
+            let cell = cell_y * cells_wide + cell_x;
+            match wave[cell].iter().position(|&possible| possible) {
+                Some(pattern) => model.patterns[pattern][(x - cell_x) + (y - cell_y) * N],
+                None => true,
+            }
+        }).collect()
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::gen_colors;
+
+    // Generates a map at the smallest size allowed, returning the map and where the player was put.
+    fn generate(seed: u64) -> Option<(Map, (i32, i32))> {
+        let mut rng = GameRng::from_seed(seed);
+        let colors = gen_colors(&mut rng);
+        let mut map = vec![vec![Tile::wall(&colors); MIN_MAP_HEIGHT as usize]; MIN_MAP_WIDTH as usize];
+        let mut player = Object::new_player();
+        match wave_function_collapse(&mut map, &colors, &mut player, &mut rng, &mut MapRecorder::new(false)) {
+            true => Some((map, player.pos())),
+            false => None,
+        }
+    }
+
+    fn tile_types(map: &Map) -> Vec<TileType> {
+        map.iter().flat_map(|column| column.iter().map(|tile| tile.tiletype)).collect()
+    }
+
+    #[test]
+    fn levels_are_walled_in_with_enough_floor() {
+        let (map, (player_x, player_y)) = generate(11).expect("no level was generated");
+        let (map_width, map_height) = map_dimensions(&map);
+        for x in 0..map_width {
+            for y in 0..map_height {
+                if x == 0 || y == 0 || x == map_width - 1 || y == map_height - 1 {
+                    assert_eq!(map[x as usize][y as usize].tiletype, TileType::Wall);
+                }
+            }
+        }
+
+        let floor = map.iter().flat_map(|column| column.iter()).filter(|tile| !tile.blocked).count() as i32;
+        assert!(floor * 100 >= map_width * map_height * MIN_FLOOR_PERCENT);
+        assert!(!map[player_x as usize][player_y as usize].blocked);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_level() {
+        let (first, first_start) = generate(23).expect("no level was generated");
+        let (second, second_start) = generate(23).expect("no level was generated");
+        assert_eq!(tile_types(&first), tile_types(&second));
+        assert_eq!(first_start, second_start);
+    }
+
+    #[test]
+    fn patterns_are_learnt_in_every_orientation() {
+        // An L shaped corner should be learnt in all four rotations, and their reflections.
+        let model = learn(&[text_to_grid(&["###", "#..", "#.."])]);
+        assert_eq!(model.patterns.len(), 4);
+        assert_eq!(model.weights.iter().sum::<u32>(), 8);
+    }
+}
@@ -1,12 +1,19 @@
 pub mod map;
 use map::tiles::Tile;
 use map::*;
+use map::recorder::MapRecorder;
 
 use map::{ // List of map gen variants go here
     rectangles::rectangles,
     drunk_walk::drunk_walk,
     cellular_automata::cellular_automata,
     maze::maze,
+    bsp::bsp,
+    wfc::wave_function_collapse,
+    vaults::{ vault_in_rooms, vault_in_cave },
+    connectivity::repair_connectivity,
+    doors::place_doors,
+    terrain::themed_terrain,
     modifiers::*,
 };
 
@@ -15,22 +22,25 @@ use spawner::{
     rooms_spawner,
     no_rooms_spawner,
     maze_spawner,
+    vault_spawner,
+    create_up_stairs,
 };
 
-use crate::{ Tcod, initialise_fov };
 use crate::graphics::gui::Messages;
-use crate::objects::{ Object, Character };
+use crate::events::{ EventBus, GameEvent };
+use crate::objects::Object;
+use crate::objects::entities::Entities;
 use crate::graphics::gen_colors;
-use crate::pathing::remove_inaccessible_tiles;
+use crate::rng::GameRng;
 use crate::environment::spawner::{ Transition, from_dungeon_level };
 
-use std::collections::HashMap;
-
 use rand::*;
 use rand::distributions::{ IndependentSample, Weighted, WeightedChoice };
 
 use serde::{ Serialize, Deserialize };
 
+use std::collections::BTreeMap;
+
 use tcod::map::FovAlgorithm;
 use tcod::colors::*;
 
@@ -39,9 +49,13 @@ pub const FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic; // Default FOV Algorithm
 pub const FOV_LIGHT_WALLS: bool = true;
 pub const TORCH_RADIUS: i32 = 10;
 
-// Size of the map
-pub const MAP_WIDTH: i32 = 81;
-pub const MAP_HEIGHT: i32 = 43;
+// Default size of the map. Each level can be generated at any size from MIN_MAP_SIZE upwards.
+pub const DEFAULT_MAP_WIDTH: i32 = 81;
+pub const DEFAULT_MAP_HEIGHT: i32 = 43;
+
+// Smallest map the generators can work with, as rooms, mazes and spawn regions need some space.
+pub const MIN_MAP_WIDTH: i32 = 40;
+pub const MIN_MAP_HEIGHT: i32 = 25;
 
 // Dungeon room limitations
 const ROOM_MAX_SIZE: i32 = 12;
@@ -50,66 +64,208 @@ const MAX_ROOMS: i32 = 18;
 
 const GROUND_COLOR: usize = 3;
 
-// Bool value to decide whether or not the map generation should be rendered or not.
-const RENDER: bool = false;
+// Levels that can't be joined up are thrown away and made again. After this many tries, the plain rooms generator is used.
+const MAX_GENERATION_ATTEMPTS: u32 = 5;
 
 // Map type definition.
 pub type Map = Vec<Vec<Tile>>;
 
+// Width and height, in tiles, of a generated map.
+// Maps are stored as columns of tiles, so the width is the number of columns.
+pub fn map_dimensions(map: &Map) -> (i32, i32) {
+    (map.len() as i32, map.first().map_or(0, |column| column.len()) as i32)
+}
+
+// Checks that a point lies within the bounds of a map.
+pub fn in_map(map: &Map, x: i32, y: i32) -> bool {
+    let (width, height) = map_dimensions(map);
+    x >= 0 && y >= 0 && x < width && y < height
+}
+
+// Size that new levels are generated at.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl MapSize {
+    // Makes a map size, as long as it is big enough for the map generators.
+    pub fn new(width: i32, height: i32) -> Result<MapSize, String> {
+        if width < MIN_MAP_WIDTH || height < MIN_MAP_HEIGHT {
+            return Err(format!(
+                "the map must be at least {}x{}, but {}x{} was given",
+                MIN_MAP_WIDTH, MIN_MAP_HEIGHT, width, height,
+            ));
+        }
+        Ok(MapSize { width, height })
+    }
+}
+
+impl Default for MapSize {
+    fn default() -> MapSize {
+        MapSize { width: DEFAULT_MAP_WIDTH, height: DEFAULT_MAP_HEIGHT }
+    }
+}
+
+// Everything that decides how a new run is set up. Two runs started with the same options play out the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunOptions {
+    pub seed: u64,
+    #[serde(default)]
+    pub map_size: MapSize,
+    // Dungeon level the run begins on. Anything deeper than 1 is mostly useful for testing later floors.
+    #[serde(default = "first_level")]
+    pub start_depth: u32,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+impl RunOptions {
+    // Options for a normal run from the first floor, at the default map size.
+    pub fn new(seed: u64) -> RunOptions {
+        RunOptions {
+            seed,
+            map_size: MapSize::default(),
+            start_depth: first_level(),
+        }
+    }
+}
+
 // Game struct definition.
 #[derive(Serialize, Deserialize)]
 pub struct Game {
     pub map: Map,
     pub messages: Messages,
     pub dungeon_level: u32,
+    pub seed: u64,
+    pub rng: GameRng,
+    // Size of every level generated in this run.
+    pub map_size: MapSize,
+    // The colors the current level was generated with. Compact saves store tile colors as indexes into it.
+    pub palette: [Color; 7],
+    // Levels the player has left, by depth, so that they can be gone back to.
+    pub levels: BTreeMap<u32, StoredLevel>,
+    // Events waiting to be sent out. These only ever exist mid-step, so they aren't saved.
+    #[serde(skip)]
+    pub events: EventBus,
+}
+
+// A level the player has left, kept just as it was: explored tiles, corpses, dropped items and all.
+#[derive(Serialize, Deserialize)]
+pub struct StoredLevel {
+    pub map: Map,
+    pub palette: [Color; 7],
+    pub entities: Vec<Object>,
+    // Where the player left from, which is the stairs they will come back by.
+    pub player_pos: (i32, i32),
 }
 
 impl Game {
     // A new game is made by just creating a new map alongside an empty Messages list
-    // And setting the dungeon level to 1.
+    // And setting the dungeon level to the starting depth (normally 1).
+    // The seed decides everything random in the run, so the same seed always gives the same floors.
     pub fn new(
-        mut characters: &mut Vec<Character>,
-        mut items: &mut HashMap<i32, Object>,
-        player: &mut Object,
-        tcod: &mut Tcod
+        options: RunOptions,
+        entities: &mut Entities,
+        recorder: &mut MapRecorder,
     ) -> Game {
-        let map = make_map(player, &mut characters, &mut items, 1, tcod);
+        let mut rng = GameRng::from_seed(options.seed);
+        let (map, palette) = make_map(entities, options.start_depth, options.map_size, None, &mut rng, recorder);
         Game {
             map: map,
             messages: Messages::new(),
-            dungeon_level: 1,
+            dungeon_level: options.start_depth,
+            seed: options.seed,
+            rng: rng,
+            map_size: options.map_size,
+            palette: palette,
+            levels: BTreeMap::new(),
+            events: EventBus::new(),
         }
     }
 }
 
 pub fn next_level(
-    tcod: &mut Tcod,
     game: &mut Game,
-    player: &mut Object,
-    characters: &mut Vec<Character>,
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
+    recorder: &mut MapRecorder,
 ) {
-    // Heals half of the players HP, and displays a message about it.
-    game.messages.add(
-        "You take a moment to rest, and recover your strength.",
-        GREEN,
-    );
+    // The level being left is kept, so it can be come back to.
+    // The FOV map is re-generated by the game state afterwards.
+    store_level(game, entities);
+    game.dungeon_level += 1;
+
+    // Levels that have been visited before are put back as they were left.
+    if restore_level(game, entities) {
+        game.events.emit(GameEvent::LevelReturned { level: game.dungeon_level, went_up: false });
+        return;
+    }
+
+    // Heals half of the players HP.
+    let player = entities.player_mut();
     let heal_hp = player.fighter.map_or(0, |f| f.max_hp / 2);
     player.heal(heal_hp);
 
+    // Makes new dungeon map.
+    let (map, palette) = make_map(entities, game.dungeon_level, game.map_size, None, &mut game.rng, recorder);
+    game.map = map;
+    game.palette = palette;
+
+    // The way back up is wherever the player arrives.
+    let (x, y) = entities.player().pos();
+    create_up_stairs(entities, x, y);
+
     // Sends the player deeper down
-    game.messages.add(
-        "After taking a moment to rest, you dive deeper into the caverns...",
-        RED,
-    );
+    game.events.emit(GameEvent::LevelEntered { level: game.dungeon_level });
+}
 
-    // Updates the dungeon depth, makes new dungeon map, and re-generates FOV map.
-    game.dungeon_level += 1;
-    game.map = make_map(player, characters, items, game.dungeon_level, tcod);
-    initialise_fov(tcod, &game.map);
+// Takes the player back up to the level above, if it has been kept.
+// Returns false if there is no level to go back to.
+pub fn previous_level(game: &mut Game, entities: &mut Entities) -> bool {
+    let level = game.dungeon_level;
+    if level <= 1 || !game.levels.contains_key(&(level - 1)) {
+        return false;
+    }
+
+    store_level(game, entities);
+    game.dungeon_level -= 1;
+    restore_level(game, entities);
+    game.events.emit(GameEvent::LevelReturned { level: game.dungeon_level, went_up: true });
+    true
+}
+
+// Puts the current level away, along with everything on it other than the player.
+fn store_level(game: &mut Game, entities: &mut Entities) {
+    let level = StoredLevel {
+        map: std::mem::take(&mut game.map),
+        palette: game.palette,
+        entities: entities.take_level(),
+        player_pos: entities.player().pos(),
+    };
+    game.levels.insert(game.dungeon_level, level);
+}
+
+// Brings back the kept level for the current depth, with the player on the stairs they left by.
+// Returns false if the level hasn't been visited before.
+fn restore_level(game: &mut Game, entities: &mut Entities) -> bool {
+    match game.levels.remove(&game.dungeon_level) {
+        Some(level) => {
+            game.map = level.map;
+            game.palette = level.palette;
+            for object in level.entities {
+                entities.insert(object);
+            }
+            entities.player_mut().set_pos(level.player_pos.0, level.player_pos.1);
+            true
+        },
+        None => false,
+    }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum MapTheme {
     Fire, //Red
     Nature, //Green
@@ -156,21 +312,103 @@ enum MapType {
     DrunkenWalk,
     CellularAutomata,
     Maze,
+    Bsp,
+    WaveFunctionCollapse,
 }
 
+// Each of the map generators make_map picks between, so that one can be asked for by name when debugging it.
+// They are listed in the same order as the weighting table in make_map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    Rectangles,
+    CavedRectangles,
+    OpenRectangles,
+    OpenCavedRectangles,
+    DrunkWalk,
+    CellularAutomata,
+    Maze,
+    Bsp,
+    WaveFunctionCollapse,
+}
+
+impl Generator {
+    pub const ALL: [Generator; 9] = [
+        Generator::Rectangles,
+        Generator::CavedRectangles,
+        Generator::OpenRectangles,
+        Generator::OpenCavedRectangles,
+        Generator::DrunkWalk,
+        Generator::CellularAutomata,
+        Generator::Maze,
+        Generator::Bsp,
+        Generator::WaveFunctionCollapse,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Generator::Rectangles => "rectangles",
+            Generator::CavedRectangles => "caved-rectangles",
+            Generator::OpenRectangles => "open-rectangles",
+            Generator::OpenCavedRectangles => "open-caved-rectangles",
+            Generator::DrunkWalk => "drunk-walk",
+            Generator::CellularAutomata => "cellular-automata",
+            Generator::Maze => "maze",
+            Generator::Bsp => "bsp",
+            Generator::WaveFunctionCollapse => "wave-function-collapse",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Generator> {
+        Generator::ALL.iter().copied().find(|generator| generator.name() == name)
+    }
+
+    // The item for this generator in the weighting table.
+    fn table_item(self) -> u32 {
+        Generator::ALL.iter().position(|generator| *generator == self).unwrap() as u32 + 1
+    }
+}
+
+// Generates a new level. The generator is picked at random based on the depth, unless one is asked for.
+// Every level is checked afterwards, so that everything on it can be reached from where the player starts.
 pub fn make_map(
-    mut player: &mut Object,
-    mut characters: &mut Vec<Character>,
-    mut items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     level: u32,
-    tcod: &mut Tcod,
-) -> Map {
+    size: MapSize,
+    generator: Option<Generator>,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> (Map, [Color; 7]) {
+    let mut generator = generator;
+    let mut attempt = 1;
+    loop {
+        let (mut map, colors) = generate_map(entities, level, size, generator, rng, recorder);
+        if repair_connectivity(&mut map, entities, &colors, rng, recorder) {
+            return (map, colors);
+        }
+
+        // A level that can't be joined up is never kept. After too many tries, plain rooms are used instead,
+        // as their tunnels already join every room up, and they are tried until one works.
+        attempt += 1;
+        if attempt >= MAX_GENERATION_ATTEMPTS {
+            generator = Some(Generator::Rectangles);
+        }
+    }
+}
+
+fn generate_map(
+    entities: &mut Entities,
+    level: u32,
+    size: MapSize,
+    generator: Option<Generator>,
+    rng: &mut GameRng,
+    recorder: &mut MapRecorder,
+) -> (Map, [Color; 7]) {
     // Generate dungeon floor colors alongside variation
-    let colors = gen_colors();
+    let colors = gen_colors(rng);
     let map_theme = set_map_theme(colors[GROUND_COLOR]);
 
     // Fill map with wall tiles
-    let mut map = vec![vec![Tile::wall(&colors); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut map = vec![vec![Tile::wall(&colors); size.height as usize]; size.width as usize];
 
     // Creates vector to store rooms
     // Another vector to store important points in non-room-based map gen.
@@ -259,52 +497,95 @@ pub fn make_map(
             ),
             item: 7
         },
+        Weighted { // Weighting for binary space partitioning map gen.
+            weight: from_dungeon_level(
+                &[
+                    Transition { level: 2, value: 20, }, // Becomes available from the second floor.
+                    Transition { level: 6, value: 25, }, // Slightly more likely once the rectangle maps start to fade out.
+                    Transition { level: 9, value: 0, }, // Chance is zero to force the maze to appear for one floor.
+                    Transition { level: 10, value: 15, }, // Stays around afterwards, alongside the caves.
+                ],
+                level,
+            ),
+            item: 8,
+        },
+        Weighted { // Weighting for wave function collapse map gen.
+            weight: from_dungeon_level(
+                &[
+                    Transition { level: 4, value: 10, }, // Becomes available from the fourth floor.
+                    Transition { level: 9, value: 0, }, // Chance is zero to force the maze to appear for one floor.
+                    Transition { level: 10, value: 15, }, // Slightly more likely afterwards, alongside the caves.
+                ],
+                level,
+            ),
+            item: 9,
+        },
     ];
     let map_gen = WeightedChoice::new(&mut map_chances);
 
-    let map_type = match map_gen.ind_sample(&mut rand::thread_rng()) {
+    let chosen = match generator {
+        Some(generator) => generator.table_item(),
+        None => map_gen.ind_sample(rng),
+    };
+
+    let map_type = match chosen {
         // Standard rectangles map
         1 => {
-            rectangles(&mut rects, &mut map, &colors, &mut player, tcod, RENDER);
+            rectangles(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder);
             MapType::Rectangles
         },
 
         // Rectangles map with the drunken miner modifier
         2 => {
-            rectangles(&mut rects, &mut map, &colors, &mut player, tcod, RENDER);
-            caved_in(&mut map, &colors, tcod, RENDER);
+            rectangles(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder);
+            caved_in(&mut map, &colors, rng, recorder);
             MapType::Rectangles
         },
 
         // Rectangles map with an open area in the middle
         3 => {
-            rectangles(&mut rects, &mut map, &colors, &mut player, tcod, RENDER);
-            mine_drunkenly(&rects, &mut map, &colors, tcod, RENDER);
+            rectangles(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder);
+            mine_drunkenly(&rects, &mut map, &colors, rng, recorder);
             MapType::Rectangles
         },
 
         // Rectangles map with the open area and drunken miner modifiers
         4 => {
-            rectangles(&mut rects, &mut map, &colors, &mut player, tcod, RENDER);
-            mine_drunkenly(&rects, &mut map, &colors, tcod, RENDER);
-            caved_in(&mut map, &colors, tcod, RENDER);
+            rectangles(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder);
+            mine_drunkenly(&rects, &mut map, &colors, rng, recorder);
+            caved_in(&mut map, &colors, rng, recorder);
             MapType::Rectangles
         },
 
         // Creates a map entirely using the walking drunkard algorithm.
         5 => {
-            drunk_walk(&mut points, &mut map, &colors, &mut player, tcod, RENDER);
+            drunk_walk(&mut points, &mut map, &colors, entities.player_mut(), rng, recorder);
             MapType::DrunkenWalk
         },
 
         // Creates a map following the rules of cellular automata.
         6 => {
-            cellular_automata(&mut map, &colors, &mut player, tcod, RENDER);
+            cellular_automata(&mut map, &colors, entities.player_mut(), rng, recorder);
             MapType::CellularAutomata
         },
 
+        // Creates a dense map of rooms, by repeatedly splitting the map in two.
+        8 => {
+            bsp(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder);
+            MapType::Bsp
+        },
+
+        // Creates a map from patterns learnt from small sample levels.
+        // Falls back to cellular automata in the rare case that nothing could be made.
+        9 => {
+            if !wave_function_collapse(&mut map, &colors, entities.player_mut(), rng, recorder) {
+                cellular_automata(&mut map, &colors, entities.player_mut(), rng, recorder);
+            }
+            MapType::WaveFunctionCollapse
+        },
+
         _ => {
-            maze(&mut map, &colors, &mut player, tcod, RENDER);
+            maze(&mut map, &colors, entities.player_mut(), rng, recorder);
             MapType::Maze
         },
     };
@@ -314,9 +595,9 @@ pub fn make_map(
     match map_type {
         MapType::Rectangles => {
             // Map modifiers
-            match rand::thread_rng().gen_range(1, 4) {
-                1 => pillars(&rects, &mut map, &colors, tcod, RENDER),
-                2 => rubble(&rects, &mut map, &colors, tcod, RENDER),
+            match rng.gen_range(1, 4) {
+                1 => pillars(&rects, &mut map, &colors, rng, recorder),
+                2 => rubble(&rects, &mut map, &colors, rng, recorder),
                 _ => {},
             }
 
@@ -324,33 +605,83 @@ pub fn make_map(
             room_sorter(&mut rects);
 
             // Tunnels and spawns
-            create_tunnels(&mut rects, &mut map, &colors, tcod, RENDER);
-            rooms_spawner(&rects, &mut items, &map, &mut characters, level, map_theme);
+            create_tunnels(&mut rects, &mut map, &colors, rng, recorder);
+            let start = entities.player().pos();
+            let vault = vault_in_rooms(&rects, start, &mut map, &colors, level, rng, recorder);
+            place_doors(&rects, &mut map, &colors, rng, recorder);
+            rooms_spawner(&rects, entities, &map, level, map_theme, rng);
+            vault_spawner(&vault, entities, &map, level, map_theme, rng);
+        },
+
+        // Rooms are already joined up as the map is split, so only the doors and spawns are left.
+        MapType::Bsp => {
+            let start = entities.player().pos();
+            let vault = vault_in_rooms(&rects, start, &mut map, &colors, level, rng, recorder);
+            place_doors(&rects, &mut map, &colors, rng, recorder);
+            rooms_spawner(&rects, entities, &map, level, map_theme, rng);
+            vault_spawner(&vault, entities, &map, level, map_theme, rng);
         },
 
         MapType::DrunkenWalk => {
             // Sorts the point vector.
             room_sorter(&mut points);
 
-            joiner(&mut points, &mut map, &colors, tcod, RENDER); // Joins the different segments of the map together.
-            no_rooms_spawner(&mut items, &map, &mut characters, level, map_theme);
+            joiner(&mut points, &mut map, &colors, rng, recorder); // Joins the different segments of the map together.
+            let vault = vault_in_cave(entities.player().pos(), &mut map, &colors, level, rng, recorder);
+            no_rooms_spawner(entities, &map, level, map_theme, rng);
+            vault_spawner(&vault, entities, &map, level, map_theme, rng);
         },
 
-        MapType::CellularAutomata => {
-            remove_inaccessible_tiles(&mut map, &player, &colors);
-            no_rooms_spawner(&mut items, &map, &mut characters, level, map_theme);
+        // The separate caves are joined up afterwards, along with every other map type.
+        MapType::CellularAutomata | MapType::WaveFunctionCollapse => {
+            let vault = vault_in_cave(entities.player().pos(), &mut map, &colors, level, rng, recorder);
+            no_rooms_spawner(entities, &map, level, map_theme, rng);
+            vault_spawner(&vault, entities, &map, level, map_theme, rng);
         },
 
         MapType::Maze => {
-            if rand::random() { // Opens some space up in the maze.
-                rectangles(&mut rects, &mut map, &colors, &mut player, tcod, RENDER); // Scatters some rooms throughout
+            if rng.gen() { // Opens some space up in the maze.
+                rectangles(&mut rects, &mut map, &colors, entities.player_mut(), rng, recorder); // Scatters some rooms throughout
             } else {
-                caved_in(&mut map, &colors, tcod, RENDER); // Creates a large open chunk in the middle of the maze
+                caved_in(&mut map, &colors, rng, recorder); // Creates a large open chunk in the middle of the maze
             }
-            maze_spawner(&mut items, &map, &mut characters, level, map_theme);
+            maze_spawner(entities, &map, level, map_theme, rng);
         },
     }
 
-    // Returns finished map.
-    map
+    // Floors of some themes get patches of terrain to match, such as lava on fire floors.
+    themed_terrain(&mut map, entities, map_theme, &colors, rng, recorder);
+
+    // Returns finished map, along with the colors it was made from.
+    (map, colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathing::reachable_tiles;
+
+    #[test]
+    fn every_generator_makes_joined_up_levels() {
+        let mut rng = GameRng::from_seed(9);
+        let mut recorder = MapRecorder::new(false);
+        for generator in Generator::ALL.iter() {
+            for level in &[1, 5, 12] {
+                let mut entities = Entities::new(Object::new_player());
+                let size = MapSize::new(MIN_MAP_WIDTH, MIN_MAP_HEIGHT).unwrap();
+                let (map, _) = make_map(&mut entities, *level, size, Some(*generator), &mut rng, &mut recorder);
+
+                // Every bit of open ground, and everything on the level, can be walked to from the start.
+                let reached = reachable_tiles(&map, entities.player().pos());
+                for (x, column) in map.iter().enumerate() {
+                    for (y, tile) in column.iter().enumerate() {
+                        assert!(tile.blocked || reached[x][y], "{} left ({}, {}) cut off", generator.name(), x, y);
+                    }
+                }
+                for (_, object) in entities.iter() {
+                    assert!(reached[object.x as usize][object.y as usize], "{} left {} cut off", generator.name(), object.name);
+                }
+            }
+        }
+    }
 }
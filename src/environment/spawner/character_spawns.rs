@@ -1,7 +1,9 @@
 use crate::environment::{ Map, MapTheme };
 use crate::environment::map::Rect;
-use crate::objects::{ Object, Character };
+use crate::objects::Object;
+use crate::objects::entities::Entities;
 use crate::objects::npc::enemies::{ generate_monster, monster_level_up };
+use crate::rng::GameRng;
 use super::*;
 
 use rand::*;
@@ -78,7 +80,46 @@ fn monster_strength_weighting(level: u32) -> [Weighted<&'static str>; 3] {
     ]
 }
 
-pub fn room_characters(room: Rect, map: &Map, characters: &mut Vec<Character>, level: u32, theme: MapTheme) {
+// Creates a monster of a random strength, levelled up to suit the depth.
+fn random_monster(
+    x: i32,
+    y: i32,
+    monster_choice: &WeightedChoice<&'static str>,
+    level: u32,
+    theme: MapTheme,
+    rng: &mut GameRng,
+) -> Object {
+    let mut monster = match monster_choice.ind_sample(rng) {
+        "weak_monster" => generate_monster(x, y, 1, level, theme, rng),
+        "medium_monster" => generate_monster(x, y, 2, level, theme, rng),
+        "powerful_monster" => generate_monster(x, y, 3, level, theme, rng),
+        _ => unreachable!(),
+    };
+    monster.alive = true;
+
+    // Level up the monster to increase the difficulty.
+    let mut level_up = level - 1;
+    while level_up > 0 {
+        monster.fighter.as_mut().map(|mut f| monster_level_up(&mut f, rng));
+        level_up -= 1;
+    }
+    monster
+}
+
+// Places a monster on each of the given points, such as the spawn points in a vault.
+pub fn characters_at(points: &[(i32, i32)], map: &Map, entities: &mut Entities, level: u32, theme: MapTheme, rng: &mut GameRng) {
+    let mut monster_chances = monster_strength_weighting(level);
+    let monster_choice = WeightedChoice::new(&mut monster_chances);
+
+    for &(x, y) in points {
+        if !Object::is_blocked(x, y, map, entities) {
+            let monster = random_monster(x, y, &monster_choice, level, theme, rng);
+            entities.insert(monster);
+        }
+    }
+}
+
+pub fn room_characters(room: Rect, map: &Map, entities: &mut Entities, level: u32, theme: MapTheme, rng: &mut GameRng) {
     // Creates maximum number of monsters per room.
     let max_monsters = from_dungeon_level(
         &[
@@ -90,7 +131,7 @@ pub fn room_characters(room: Rect, map: &Map, characters: &mut Vec<Character>, l
     );
 
     // Choose random number of monsters
-    let num_monsters = rand::thread_rng().gen_range(0, max_monsters + 1);
+    let num_monsters = rng.gen_range(0, max_monsters + 1);
 
     let mut monster_chances = monster_strength_weighting(level);
     let monster_choice = WeightedChoice::new(&mut monster_chances);
@@ -98,31 +139,19 @@ pub fn room_characters(room: Rect, map: &Map, characters: &mut Vec<Character>, l
     for _ in 0..num_monsters {
 
         // Choose random spot for the monster
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
-
-        if !Object::is_blocked(x, y, map, characters) {
-            let mut monster = match monster_choice.ind_sample(&mut rand::thread_rng()) {
-                "weak_monster" => generate_monster(x, y, 1, level, theme),
-                "medium_monster" => generate_monster(x, y, 2, level, theme),
-                "powerful_monster" => generate_monster(x, y, 3, level, theme),
-                _ => unreachable!(),
-            };
-            monster.object.alive = true;
-
-            // Level up the monster to increase the difficulty.
-            let mut level_up = level - 1;
-            while level_up > 0 {
-                monster.object.fighter.as_mut().map(|mut f| monster_level_up(&mut f));
-                level_up -= 1;
-            }
+        let x = rng.gen_range(room.x1 + 1, room.x2);
+        let y = rng.gen_range(room.y1 + 1, room.y2);
 
-            characters.push(monster);
+        if !Object::is_blocked(x, y, map, entities) {
+            let monster = random_monster(x, y, &monster_choice, level, theme, rng);
+            entities.insert(monster);
         }
     }
 }
 
-pub fn no_room_characters(map: &Map, characters: &mut Vec<Character>, level: u32, theme: MapTheme) {
+pub fn no_room_characters(map: &Map, entities: &mut Entities, level: u32, theme: MapTheme, rng: &mut GameRng) {
+    let (map_width, map_height) = map_dimensions(map);
+
 
     // Creates maximum number of monsters per room.
     let max_monsters = from_dungeon_level(
@@ -142,7 +171,7 @@ pub fn no_room_characters(map: &Map, characters: &mut Vec<Character>, level: u32
 
     for _ in 0..map_regions {
         // Choose random number of monsters
-        let num_monsters = rand::thread_rng().gen_range(0, max_monsters + 1);
+        let num_monsters = rng.gen_range(0, max_monsters + 1);
 
         let mut monsters_placed = 0;
         let mut attempts = 0;
@@ -151,28 +180,14 @@ pub fn no_room_characters(map: &Map, characters: &mut Vec<Character>, level: u32
         while monsters_placed < num_monsters {
 
             // Choose random spot for the monster
-            let x = rand::thread_rng().gen_range(map_region_start, map_region_start + 10);
-            let y = rand::thread_rng().gen_range(1, MAP_HEIGHT - 1);
-
-            if x >= MAP_WIDTH - 1 { break; }
-
-            if !Object::is_blocked(x, y, map, characters) {
-                let mut monster = match monster_choice.ind_sample(&mut rand::thread_rng()) {
-                    "weak_monster" => generate_monster(x, y, 1, level, theme),
-                    "medium_monster" => generate_monster(x, y, 2, level, theme),
-                    "powerful_monster" => generate_monster(x, y, 3, level, theme),
-                    _ => unreachable!(),
-                };
-                monster.object.alive = true;
-
-                // Level up the monster to increase the difficulty.
-                let mut level_up = level - 1;
-                while level_up > 0 {
-                    monster.object.fighter.as_mut().map(|mut f| monster_level_up(&mut f));
-                    level_up -= 1;
-                }
+            let x = rng.gen_range(map_region_start, map_region_start + 10);
+            let y = rng.gen_range(1, map_height - 1);
+
+            if x >= map_width - 1 { break; }
 
-                characters.push(monster);
+            if !Object::is_blocked(x, y, map, entities) {
+                let monster = random_monster(x, y, &monster_choice, level, theme, rng);
+                entities.insert(monster);
                 monsters_placed += 1;
             } else {
                 attempts += 1;
@@ -1,96 +1,53 @@
 use crate::environment::Map;
 use crate::environment::map::Rect;
-use crate::objects::{ Object, Character, items::Item };
+use crate::objects::Object;
+use crate::objects::items::data::item_data;
+use crate::objects::entities::Entities;
+use crate::rng::GameRng;
 use super::*;
 
-use std::collections::HashMap;
-
 use rand::*;
 use rand::distributions::{ IndependentSample, Weighted, WeightedChoice };
 
-fn item_weighting(level: u32) -> [Weighted<Item>; 8] {
-    [
-        Weighted {
-            weight: 35,
-            item: Item::Heal,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 4, value: 25, }
-                ],
-                level,
-            ),
-            item: Item::LightningBoltScroll,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 6, value: 25, },
-                    Transition { level: 8, value: 50, },
-                    Transition { level: 10, value: 10, },
-                ],
-                level,
-            ),
-            item: Item::FireballScroll,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 2, value: 10, },
-                    Transition { level: 12, value: 20, },
-                ],
-                level,
-            ),
-            item: Item::ConfusionScroll,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 4, value: 10, },
-                    Transition { level: 16, value: 20, },
-                ],
-                level,
-            ),
-            item: Item::FearScroll,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 5, value: 10, },
-                ],
-                level,
-            ),
-            item: Item::HpUp,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 7, value: 10, },
-                ],
-                level,
-            ),
-            item: Item::PowUp,
-        },
-        Weighted {
-            weight: from_dungeon_level(
-                &[
-                    Transition { level: 10, value: 10, },
-                ],
-                level,
-            ),
-            item: Item::DefUp,
-        },
-    ]
+// Chance of each item in the item data spawning on the given level, by its index in the data.
+fn item_weighting(level: u32) -> Vec<Weighted<usize>> {
+    item_data().items
+        .iter()
+        .enumerate()
+        .map(|(index, def)| Weighted {
+            weight: from_dungeon_level(&def.spawn_chance, level),
+            item: index,
+        })
+        .collect()
+}
+
+// Deeper levels can go without any items, in which case there is nothing to pick from.
+fn no_items(item_chances: &[Weighted<usize>]) -> bool {
+    item_chances.iter().all(|chance| chance.weight == 0)
+}
+
+// Places a random item on each of the given points, such as the spawn points in a vault.
+pub fn items_at(points: &[(i32, i32)], entities: &mut Entities, map: &Map, level: u32, rng: &mut GameRng) {
+    let mut item_chances = item_weighting(level);
+    if no_items(&item_chances) {
+        return;
+    }
+    let item_choice = WeightedChoice::new(&mut item_chances);
+
+    for &(x, y) in points {
+        if !Object::is_blocked(x, y, map, entities) {
+            let item = Object::item_from_def(&item_data().items[item_choice.ind_sample(rng)], x, y);
+            entities.insert(item);
+        }
+    }
 }
 
 pub fn room_items(
     room: Rect,
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     map: &Map,
-    characters: &mut Vec<Character>,
-    item_counter: &mut i32,
-    level: u32
+    level: u32,
+    rng: &mut GameRng,
 ) {
     // Decides maximum number of items per room.
     let max_items = from_dungeon_level(
@@ -102,64 +59,34 @@ pub fn room_items(
     );
 
     let mut item_chances = item_weighting(level);
+    if no_items(&item_chances) {
+        return;
+    }
     let item_choice = WeightedChoice::new(&mut item_chances);
 
     // Choose random number of items.
-    let num_items = rand::thread_rng().gen_range(0, max_items + 1);
+    let num_items = rng.gen_range(0, max_items + 1);
 
     for _ in 0..num_items {
         // Select random spot for the item.
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
-
-        if !Object::is_blocked(x, y, map, characters) {
-            let item = match item_choice.ind_sample(&mut rand::thread_rng()) {
-                Item::Heal => {
-                    // Create a health potion.
-                    Object::health_pot(x, y)
-                },
-                Item::LightningBoltScroll => {
-                    // Creates a lightning bolt scroll.
-                    Object::lightning_bolt_scroll(x, y)
-                },
-                Item::FireballScroll => {
-                    // Creates a fireball scroll
-                    Object::fireball_scroll(x, y)
-                },
-                Item::ConfusionScroll => {
-                    // Creates a confusion scroll
-                    Object::confusion_scroll(x, y)
-                },
-                Item::FearScroll => {
-                    // Creates a confusion scroll
-                    Object::fear_scroll(x, y)
-                },
-                Item::HpUp => {
-                    // Creates a Health upgrade
-                    Object::health_up(x, y)
-                },
-                Item::PowUp => {
-                    // Creates a Power upgrade
-                    Object::power_up(x, y)
-                },
-                Item::DefUp => {
-                    // Creates a Defense upgrade
-                    Object::defense_up(x, y)
-                },
-            };
-            items.insert(*item_counter, item);
-            *item_counter += 1;
+        let x = rng.gen_range(room.x1 + 1, room.x2);
+        let y = rng.gen_range(room.y1 + 1, room.y2);
+
+        if !Object::is_blocked(x, y, map, entities) {
+            let item = Object::item_from_def(&item_data().items[item_choice.ind_sample(rng)], x, y);
+            entities.insert(item);
         }
     }
 }
 
 pub fn no_room_items(
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     map: &Map,
-    characters: &mut Vec<Character>,
-    item_counter: &mut i32,
-    level: u32
+    level: u32,
+    rng: &mut GameRng,
 ) {
+    let (map_width, map_height) = map_dimensions(map);
+
     // Decides maximum number of items per room.
     let max_items = from_dungeon_level(
         &[
@@ -170,9 +97,12 @@ pub fn no_room_items(
         level,
     );
     // Choose random number of items.
-    let num_items = rand::thread_rng().gen_range(0, max_items + 1);
+    let num_items = rng.gen_range(0, max_items + 1);
 
     let mut item_chances = item_weighting(level);
+    if no_items(&item_chances) {
+        return;
+    }
     let item_choice = WeightedChoice::new(&mut item_chances);
 
     let map_regions = 7;
@@ -186,48 +116,14 @@ pub fn no_room_items(
         while region_items <= num_items {
 
             // Select random spot for the item.
-            let x = rand::thread_rng().gen_range(map_region_start, map_region_start + 10);
-            let y = rand::thread_rng().gen_range(1, MAP_HEIGHT - 1);
-
-            if x >= MAP_WIDTH { break; }
-
-            if !Object::is_blocked(x, y, map, characters) {
-                let item = match item_choice.ind_sample(&mut rand::thread_rng()) {
-                    Item::Heal => {
-                        // Create a health potion.
-                        Object::health_pot(x, y)
-                    },
-                    Item::LightningBoltScroll => {
-                        // Creates a lightning bolt scroll.
-                        Object::lightning_bolt_scroll(x, y)
-                    },
-                    Item::FireballScroll => {
-                        // Creates a fireball scroll
-                        Object::fireball_scroll(x, y)
-                    },
-                    Item::ConfusionScroll => {
-                        // Creates a confusion scroll
-                        Object::confusion_scroll(x, y)
-                    },
-                    Item::FearScroll => {
-                        // Creates a confusion scroll
-                        Object::confusion_scroll(x, y)
-                    },
-                    Item::HpUp => {
-                        // Creates a Health upgrade
-                        Object::health_up(x, y)
-                    },
-                    Item::PowUp => {
-                        // Creates a Power upgrade
-                        Object::power_up(x, y)
-                    },
-                    Item::DefUp => {
-                        // Creates a Defense upgrade
-                        Object::defense_up(x, y)
-                    },
-                };
-                items.insert(*item_counter, item);
-                *item_counter += 1;
+            let x = rng.gen_range(map_region_start, map_region_start + 10);
+            let y = rng.gen_range(1, map_height - 1);
+
+            if x >= map_width { break; }
+
+            if !Object::is_blocked(x, y, map, entities) {
+                let item = Object::item_from_def(&item_data().items[item_choice.ind_sample(rng)], x, y);
+                entities.insert(item);
                 region_items += 1;
             } else {
                 attempts += 1;
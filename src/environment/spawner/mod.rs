@@ -1,97 +1,158 @@
 pub mod character_spawns;
-use character_spawns::{ room_characters, no_room_characters };
+use character_spawns::{ room_characters, no_room_characters, characters_at };
 
 pub mod item_spawns;
-use item_spawns::{ room_items, no_room_items };
+use item_spawns::{ room_items, no_room_items, items_at };
 
-use crate::environment::{ Map, MAP_WIDTH, MAP_HEIGHT, MapTheme };
+pub mod trap_spawns;
+use trap_spawns::{ room_traps, no_room_traps };
+
+use crate::environment::{ Map, map_dimensions, MapTheme };
 use crate::environment::map::Rect;
-use crate::objects::{ Object, Character };
+use crate::environment::map::vaults::VaultSpawns;
+use crate::objects::Object;
+use crate::objects::entities::Entities;
+use crate::rng::GameRng;
 
-use std::collections::HashMap;
 use rand::*;
 
+use serde::{ Serialize, Deserialize };
+
 use tcod::colors::*;
 
 pub fn rooms_spawner(
     rooms: &Vec<Rect>,
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     map: &Map,
-    characters: &mut Vec<Character>,
     level: u32,
     theme: MapTheme,
+    rng: &mut GameRng,
 ) {
-    // Ensures that there are no existing entities in the character, or item collections.
-    characters.clear();
-    items.clear();
-
-    // Keeps track of total items spawned on a map.
-    let mut item_counter = 1;
+    // Ensures that nothing but the player is left over from the previous level.
+    entities.clear_level();
 
     for room in rooms {
-        room_characters(*room, &map, characters, level, theme);
-        room_items(*room, items, &map, characters, &mut item_counter, level);
+        room_characters(*room, &map, entities, level, theme, rng);
+        room_items(*room, entities, &map, level, rng);
     }
 
     // Create stairs at the center of the last room.
     let (last_room_center_x, last_room_center_y) = rooms[rooms.len() - 1].center();
-    create_stairs(items, last_room_center_x, last_room_center_y);
+    create_stairs(entities, last_room_center_x, last_room_center_y);
+
+    // Traps go in last, so that they are never under anything else.
+    for room in rooms {
+        room_traps(*room, entities, &map, level, theme, rng);
+    }
 }
 
 pub fn no_rooms_spawner(
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     map: &Map,
-    characters: &mut Vec<Character>,
     level: u32,
     theme: MapTheme,
+    rng: &mut GameRng,
 ) {
-    // Ensures that there are no existing entities in the character, or item collections.
-    characters.clear();
-    items.clear();
+    let (map_width, map_height) = map_dimensions(map);
 
-    // Keeps track of total items spawned on a map.
-    let mut item_counter = 1;
+    // Ensures that nothing but the player is left over from the previous level.
+    entities.clear_level();
 
-    no_room_characters(&map, characters, level, theme);
-    no_room_items(items, &map, characters, &mut item_counter, level);
+    no_room_characters(&map, entities, level, theme, rng);
+    no_room_items(entities, &map, level, rng);
 
     let mut stairs_placed = true;
     while stairs_placed {
-        let x = rand::thread_rng().gen_range(1, MAP_WIDTH - 1);
-        let y = rand::thread_rng().gen_range(1, MAP_HEIGHT - 1);
+        let x = rng.gen_range(1, map_width - 1);
+        let y = rng.gen_range(1, map_height - 1);
 
         if map[x as usize][y as usize].empty == true {
-            create_stairs(items, x, y);
+            create_stairs(entities, x, y);
             stairs_placed = false;
         }
     }
+
+    no_room_traps(entities, &map, level, theme, rng);
 }
 
 pub fn maze_spawner(
-    items: &mut HashMap<i32, Object>,
+    entities: &mut Entities,
     map: &Map,
-    characters: &mut Vec<Character>,
     level: u32,
     theme: MapTheme,
+    rng: &mut GameRng,
 ) {
-    // Ensures that there are no existing entities in the character, or item collections.
-    characters.clear();
-    items.clear();
+    // Ensures that nothing but the player is left over from the previous level.
+    entities.clear_level();
+
+    no_room_characters(&map, entities, level, theme, rng);
+    no_room_items(entities, &map, level, rng);
+
+    // The stairs go in one of the maze's corners.
+    // Maze cells are on odd tiles inside the outer wall, so the far corners depend on the size of the map.
+    // Rooms or cave-ins can change what is in the corner, so the stairs go on the closest floor to it.
+    let (map_width, map_height) = map_dimensions(map);
+    let far_x = last_odd_inside(map_width);
+    let far_y = last_odd_inside(map_height);
+    let (corner_x, corner_y) = match rng.gen_range(0, 4) {
+        0 => (1, 1),
+        1 => (1, far_y),
+        2 => (far_x, 1),
+        _ => (far_x, far_y),
+    };
+    let (x, y) = closest_floor(map, corner_x, corner_y);
+    create_stairs(entities, x, y);
 
-    // Keeps track of total items spawned on a map.
-    let mut item_counter = 1;
+    no_room_traps(entities, &map, level, theme, rng);
+}
+
+// Finds the open tile closest to a point. Falls back to the point itself on a map with no floor.
+fn closest_floor(map: &Map, x: i32, y: i32) -> (i32, i32) {
+    let (map_width, map_height) = map_dimensions(map);
+    (0..map_width)
+        .flat_map(|floor_x| (0..map_height).map(move |floor_y| (floor_x, floor_y)))
+        .filter(|&(floor_x, floor_y)| !map[floor_x as usize][floor_y as usize].blocked)
+        .min_by_key(|&(floor_x, floor_y)| (floor_x - x).pow(2) + (floor_y - y).pow(2))
+        .unwrap_or((x, y))
+}
 
-    no_room_characters(&map, characters, level, theme);
-    no_room_items(items, &map, characters, &mut item_counter, level);
+// Finds the last odd coordinate that is still inside the outer wall of a map of the given size.
+fn last_odd_inside(size: i32) -> i32 {
+    let last = size - 2;
+    if last % 2 == 1 { last } else { last - 1 }
+}
 
-    match rand::thread_rng().gen_range(0, 4) {
-        0 => create_stairs(items, 1, 1),
-        1 => create_stairs(items, 1, 41),
-        2 => create_stairs(items, 79, 1),
-        _ => create_stairs(items, 79, 41),
+// Fills in the monsters and items a vault asks for. Runs after the level's usual spawner.
+// If the vault holds the stairs, they are moved into it.
+// Nothing in a vault is ever left sitting on a trap, so any the usual spawner put there are removed.
+pub fn vault_spawner(
+    spawns: &VaultSpawns,
+    entities: &mut Entities,
+    map: &Map,
+    level: u32,
+    theme: MapTheme,
+    rng: &mut GameRng,
+) {
+    let spots = spawns.stairs.iter().chain(spawns.monsters.iter()).chain(spawns.items.iter());
+    for &(x, y) in spots {
+        let traps: Vec<_> = entities.at(x, y).filter(|(_, object)| object.trap.is_some()).map(|(id, _)| id).collect();
+        for id in traps {
+            entities.remove(id);
+        }
     }
+
+    if let Some((x, y)) = spawns.stairs {
+        let stairs_id = entities.iter().find(|(_, object)| object.name == "Stairs").map(|(id, _)| id);
+        match stairs_id {
+            Some(id) => entities[id].set_pos(x, y),
+            None => create_stairs(entities, x, y),
+        }
+    }
+    characters_at(&spawns.monsters, map, entities, level, theme, rng);
+    items_at(&spawns.items, entities, map, level, rng);
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Transition {
     pub level: u32,
     pub value: u32,
@@ -103,30 +164,61 @@ pub fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
         .map_or(0, |transition| transition.value)
 }
 
-pub fn create_stairs(items: &mut HashMap<i32, Object>, x: i32, y: i32) {
-    let stairs = Object {
+pub fn create_stairs(entities: &mut Entities, x: i32, y: i32) {
+    entities.insert(new_stairs(x, y, '<', "Stairs"));
+}
+
+// Stairs back up to the previous level.
+pub fn create_up_stairs(entities: &mut Entities, x: i32, y: i32) {
+    entities.insert(new_stairs(x, y, '>', "Up stairs"));
+}
+
+fn new_stairs(x: i32, y: i32, char: char, name: &str) -> Object {
+    Object {
         x: x,
         y: y,
-        char: '<',
+        char: char,
         color: WHITE,
-        name: "Stairs".into(),
+        name: name.into(),
         blocks: false,
         alive: false,
-        corpse_type: "Stairs".into(),
+        corpse_type: name.into(),
         fighter: None,
         ai: None,
         item: None,
+        inventory: None,
         level: 1,
         always_visible: true,
-    };
+        trap: None,
+    }
+}
 
-    let mut stairs_id = 1; // Sets up id for stairs to use in items hashmap.
-    for _ in 0..items.len() {
-        if items.contains_key(&stairs_id) {
-            stairs_id += 1;
-        } else {
-            break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::map::tiles::Tile;
+    use crate::graphics::gen_colors;
+    use crate::objects::traps::TrapKind;
+
+    #[test]
+    fn vault_contents_clear_traps() {
+        let mut rng = GameRng::from_seed(8);
+        let colors = gen_colors(&mut rng);
+        let map = vec![vec![Tile::empty(&colors, &mut rng); 10]; 10];
+        let mut entities = Entities::new(Object::new_player());
+        entities.player_mut().set_pos(1, 1);
+        for &(x, y) in &[(3, 3), (4, 4), (5, 5), (6, 6)] {
+            entities.insert(Object::new_trap(x, y, TrapKind::Pit));
         }
+
+        let spawns = VaultSpawns { monsters: vec![(3, 3)], items: vec![(4, 4)], stairs: Some((5, 5)) };
+        vault_spawner(&spawns, &mut entities, &map, 3, MapTheme::Fire, &mut rng);
+
+        // Only the trap outside the vault is left.
+        let traps: Vec<_> = entities.with_trap().map(|(_, trap)| trap.pos()).collect();
+        assert_eq!(traps, vec![(6, 6)]);
+        assert!(entities.at(3, 3).any(|(_, object)| object.ai.is_some()));
+        assert!(entities.at(4, 4).any(|(_, object)| object.item.is_some()));
+        assert!(entities.at(5, 5).any(|(_, object)| object.name == "Stairs"));
     }
-    items.insert(stairs_id, stairs); // Finally, inserts stairs into the items hashmap.
 }
@@ -0,0 +1,114 @@
+use crate::environment::{ Map, MapTheme, map_dimensions };
+use crate::environment::map::Rect;
+use crate::objects::Object;
+use crate::objects::entities::Entities;
+use crate::objects::traps::TrapKind;
+use crate::rng::GameRng;
+use super::*;
+
+use rand::*;
+use rand::distributions::{ IndependentSample, Weighted, WeightedChoice };
+
+// Random spots tried for each trap in a cave, before giving up on it.
+const MAX_TRAP_TRIES: u32 = 25;
+
+// Chance of each kind of trap on the given level. Elemental traps take the level's theme.
+fn trap_weighting(level: u32, theme: MapTheme) -> Vec<Weighted<TrapKind>> {
+    vec![
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 30 }, Transition { level: 8, value: 15 }], level),
+            item: TrapKind::Pit,
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 25 }], level),
+            item: TrapKind::Dart,
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 15 }], level),
+            item: TrapKind::Alarm,
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 4, value: 10 }], level),
+            item: TrapKind::Teleport,
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 15 }, Transition { level: 8, value: 30 }], level),
+            item: TrapKind::Elemental { theme },
+        },
+    ]
+}
+
+// Most traps that can be in a single room.
+fn max_room_traps(level: u32) -> u32 {
+    from_dungeon_level(
+        &[
+            Transition { level: 2, value: 1 },
+            Transition { level: 7, value: 2 },
+        ],
+        level,
+    )
+}
+
+// Checks a spot is open floor with nothing already on it.
+fn free_spot(x: i32, y: i32, map: &Map, entities: &Entities) -> bool {
+    !map[x as usize][y as usize].blocked && entities.at(x, y).next().is_none()
+}
+
+pub fn room_traps(
+    room: Rect,
+    entities: &mut Entities,
+    map: &Map,
+    level: u32,
+    theme: MapTheme,
+    rng: &mut GameRng,
+) {
+    let max_traps = max_room_traps(level);
+    if max_traps == 0 {
+        return;
+    }
+
+    let mut trap_chances = trap_weighting(level, theme);
+    let trap_choice = WeightedChoice::new(&mut trap_chances);
+
+    // Choose random number of traps.
+    let num_traps = rng.gen_range(0, max_traps + 1);
+    for _ in 0..num_traps {
+        // Select random spot for the trap.
+        let x = rng.gen_range(room.x1 + 1, room.x2);
+        let y = rng.gen_range(room.y1 + 1, room.y2);
+
+        if free_spot(x, y, map, entities) {
+            entities.insert(Object::new_trap(x, y, trap_choice.ind_sample(rng)));
+        }
+    }
+}
+
+pub fn no_room_traps(
+    entities: &mut Entities,
+    map: &Map,
+    level: u32,
+    theme: MapTheme,
+    rng: &mut GameRng,
+) {
+    let max_traps = max_room_traps(level);
+    if max_traps == 0 {
+        return;
+    }
+    let (map_width, map_height) = map_dimensions(map);
+
+    let mut trap_chances = trap_weighting(level, theme);
+    let trap_choice = WeightedChoice::new(&mut trap_chances);
+
+    // Roughly as many traps as a level of rooms the same size would have.
+    let num_traps = rng.gen_range(0, (map_width * map_height / 400) as u32 * max_traps + 1);
+    for _ in 0..num_traps {
+        for _ in 0..MAX_TRAP_TRIES {
+            let x = rng.gen_range(1, map_width - 1);
+            let y = rng.gen_range(1, map_height - 1);
+            if free_spot(x, y, map, entities) {
+                entities.insert(Object::new_trap(x, y, trap_choice.ind_sample(rng)));
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::graphics::gui::Messages;
+use super::{ DamageSource, GameEvent, Subscriber };
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::*;
+
+// An achievement is unlocked once enough matching events have been seen.
+// "counts" decides how much progress an event is worth towards the goal.
+pub struct Achievement {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub goal: i32,
+    counts: fn(&GameEvent) -> i32,
+}
+
+// List of every achievement in the game.
+pub static ACHIEVEMENTS: [Achievement; 6] = [
+    Achievement {
+        name: "First Blood",
+        description: "Slay your first monster.",
+        goal: 1,
+        counts: |event| match event { GameEvent::Died { is_player: false, source: DamageSource::Player, .. } => 1, _ => 0 },
+    },
+    Achievement {
+        name: "Exterminator",
+        description: "Slay 50 monsters in a single run.",
+        goal: 50,
+        counts: |event| match event { GameEvent::Died { is_player: false, source: DamageSource::Player, .. } => 1, _ => 0 },
+    },
+    Achievement {
+        name: "Hoarder",
+        description: "Pick up 20 items.",
+        goal: 20,
+        counts: |event| match event { GameEvent::ItemPickedUp { .. } => 1, _ => 0 },
+    },
+    Achievement {
+        name: "Scholar",
+        description: "Use 10 items.",
+        goal: 10,
+        counts: |event| match event { GameEvent::ItemUsed { .. } => 1, _ => 0 },
+    },
+    Achievement {
+        name: "Delver",
+        description: "Reach the fifth floor.",
+        goal: 1,
+        counts: |event| match event { GameEvent::LevelEntered { level } if *level >= 5 => 1, _ => 0 },
+    },
+    Achievement {
+        name: "Heavy Hitter",
+        description: "Deal 25 damage in a single blow.",
+        goal: 1,
+        counts: |event| match event {
+            GameEvent::Attacked { by_player: true, damage, .. } if *damage >= 25 => 1,
+            _ => 0,
+        },
+    },
+];
+
+// Tracks the progress towards each achievement, in the same order as the list above.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Achievements {
+    progress: Vec<i32>,
+    unlocked: Vec<bool>,
+}
+
+impl Achievements {
+    pub fn new() -> Achievements {
+        Achievements {
+            progress: vec![0; ACHIEVEMENTS.len()],
+            unlocked: vec![false; ACHIEVEMENTS.len()],
+        }
+    }
+
+    // Every achievement which has been unlocked so far.
+    pub fn unlocked(&self) -> impl Iterator<Item = &'static Achievement> + '_ {
+        ACHIEVEMENTS.iter()
+            .zip(self.unlocked.iter())
+            .filter(|(_, unlocked)| **unlocked)
+            .map(|(achievement, _)| achievement)
+    }
+}
+
+impl Subscriber for Achievements {
+    fn notify(&mut self, event: &GameEvent, messages: &mut Messages) {
+        for (index, achievement) in ACHIEVEMENTS.iter().enumerate() {
+            if self.unlocked[index] {
+                continue;
+            }
+            self.progress[index] += (achievement.counts)(event);
+
+            // Lets the player know as soon as an achievement is unlocked.
+            if self.progress[index] >= achievement.goal {
+                self.unlocked[index] = true;
+                messages.add(format!("Achievement unlocked: {}!", achievement.name), GOLD);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delver_unlocked(achievements: &Achievements) -> bool {
+        achievements.unlocked().any(|achievement| achievement.name == "Delver")
+    }
+
+    #[test]
+    fn delver_needs_the_fifth_floor() {
+        let mut achievements = Achievements::new();
+        let mut messages = Messages::new();
+
+        // Going up and down between the early floors doesn't count towards it.
+        for level in &[2, 3, 4, 2, 3, 4] {
+            achievements.notify(&GameEvent::LevelEntered { level: *level }, &mut messages);
+        }
+        assert!(!delver_unlocked(&achievements));
+
+        achievements.notify(&GameEvent::LevelEntered { level: 5 }, &mut messages);
+        assert!(delver_unlocked(&achievements));
+    }
+}
@@ -0,0 +1,166 @@
+pub mod stats;
+pub mod achievements;
+
+use crate::graphics::gui::Messages;
+use crate::objects::items::Item;
+use crate::state::LevelUpStat;
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::*;
+
+// Everything of note that happens in the game core.
+// The core only emits these, and subscribers (the message log, stats, achievements, replays) decide what to do with them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    TurnTaken,
+    Attacked { attacker: String, target: String, damage: i32, by_player: bool, color: Color },
+    Damaged { target: String, damage: i32, is_player: bool, source: DamageSource },
+    Died { name: String, exp: i32, is_player: bool, source: DamageSource },
+    ItemPickedUp { name: String, color: Color },
+    ItemUsed { item: Item },
+    ItemDropped { name: String },
+    LevelEntered { level: u32 },
+    LevelReturned { level: u32, went_up: bool },
+    LevelUpReady,
+    LevelUp { level: i32, stat: LevelUpStat },
+    DoorOpened { x: i32, y: i32 },
+    DoorClosed { x: i32, y: i32 },
+    DoorUnlocked { x: i32, y: i32 },
+    DoorBashed { x: i32, y: i32, broke: bool },
+    SecretFound { x: i32, y: i32 },
+    TrapFound { trap: String },
+    TrapTriggered { trap: String, target: String, is_player: bool },
+    TrapDisarmed { trap: String },
+    TrapDisarmFailed { trap: String, set_off: bool },
+}
+
+// Where damage came from, so that the player is only credited with what they did,
+// and so that a death can be put down to the right thing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DamageSource {
+    // The player's attacks and items, including any that catch the player.
+    Player,
+    Monster { name: String },
+    // Traps and terrain. The cause is written out in full, such as "Killed by a pit trap".
+    Hazard { cause: String },
+}
+
+impl DamageSource {
+    // What to put in the morgue file, if this killed the player.
+    pub fn cause_of_death(&self) -> String {
+        match self {
+            DamageSource::Player => "Died by their own hand".into(),
+            DamageSource::Monster { name } => format!("Killed by a {}", name),
+            DamageSource::Hazard { cause } => cause.clone(),
+        }
+    }
+}
+
+// Collects events as they are emitted, until the game state hands them out to the subscribers.
+#[derive(Default)]
+pub struct EventBus {
+    pending: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus { pending: Vec::new() }
+    }
+
+    // Queues up an event to be sent to the subscribers.
+    pub fn emit(&mut self, event: GameEvent) {
+        self.pending.push(event);
+    }
+
+    // Takes every event which hasn't been sent out yet.
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        self.pending.drain(..).collect()
+    }
+}
+
+// Anything which wants to react to game events.
+// Subscribers are given the message log, so that they can tell the player about whatever they did.
+pub trait Subscriber {
+    fn notify(&mut self, event: &GameEvent, messages: &mut Messages);
+}
+
+// Turns events into the lines shown in the message log.
+pub struct MessageLog;
+
+impl Subscriber for MessageLog {
+    fn notify(&mut self, event: &GameEvent, messages: &mut Messages) {
+        match event {
+            GameEvent::Attacked { attacker, target, damage, color, .. } => {
+                if *damage > 0 {
+                    messages.add(format!("{} attacks {} dealing {} damage.", attacker, target, damage), *color);
+                } else {
+                    messages.add(format!("{} attacks {} but it has no effect!", attacker, target), WHITE);
+                }
+            },
+            GameEvent::Died { is_player: true, .. } => {
+                messages.add("You died, lmao!", RED);
+            },
+            GameEvent::Died { name, exp, source: DamageSource::Player, .. } => {
+                messages.add(format!("{} is dead! You gain {} experience points.", name, exp), DARK_RED);
+            },
+            GameEvent::Died { name, .. } => {
+                messages.add(format!("{} is dead!", name), DARK_RED);
+            },
+            GameEvent::ItemPickedUp { name, color } => {
+                messages.add(format!("You picked found a {}", name), *color);
+            },
+            GameEvent::ItemDropped { name } => {
+                messages.add(format!("You dropped a {}.", name), YELLOW);
+            },
+            GameEvent::LevelEntered { .. } => {
+                messages.add("You take a moment to rest, and recover your strength.", GREEN);
+                messages.add("After taking a moment to rest, you dive deeper into the caverns...", RED);
+            },
+            GameEvent::LevelReturned { level, went_up: true } => {
+                messages.add(format!("You climb back up to level {}.", level), LIGHT_GREY);
+            },
+            GameEvent::LevelReturned { level, went_up: false } => {
+                messages.add(format!("You head back down to level {}.", level), LIGHT_GREY);
+            },
+            GameEvent::LevelUp { level, .. } => {
+                messages.add(format!("Your power grows - You have reached level {}!", level), GOLD);
+            },
+            GameEvent::SecretFound { .. } => {
+                messages.add("You notice a secret passage!", LIGHT_CYAN);
+            },
+            GameEvent::TrapFound { trap } => {
+                messages.add(format!("You spot a {}!", trap.to_lowercase()), LIGHT_CYAN);
+            },
+            GameEvent::TrapTriggered { trap, is_player: true, .. } => {
+                messages.add(format!("You set off a {}!", trap.to_lowercase()), ORANGE);
+            },
+            GameEvent::TrapTriggered { trap, target, .. } => {
+                messages.add(format!("The {} sets off a {}!", target, trap.to_lowercase()), LIGHT_GREY);
+            },
+            GameEvent::TrapDisarmed { trap } => {
+                messages.add(format!("You disarm the {}.", trap.to_lowercase()), GREEN);
+            },
+            GameEvent::TrapDisarmFailed { trap, set_off: false } => {
+                messages.add(format!("You fail to disarm the {}.", trap.to_lowercase()), WHITE);
+            },
+            GameEvent::TrapDisarmFailed { trap, set_off: true } => {
+                messages.add(format!("Your hands slip while disarming the {}...", trap.to_lowercase()), RED);
+            },
+            GameEvent::DoorClosed { .. } => {
+                messages.add("You close the door.", LIGHT_GREY);
+            },
+            GameEvent::DoorUnlocked { .. } => {
+                messages.add("The lock clicks, and the door swings open.", GOLD);
+            },
+            GameEvent::DoorBashed { broke: true, .. } => {
+                messages.add("You smash the lock, and the door flies open!", ORANGE);
+            },
+            GameEvent::DoorBashed { broke: false, .. } => {
+                messages.add("You slam into the locked door, but it holds.", WHITE);
+            },
+            // Everything else is either described by the item that caused it, or isn't worth a message.
+            _ => {},
+        }
+    }
+}
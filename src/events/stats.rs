@@ -0,0 +1,53 @@
+use crate::graphics::gui::Messages;
+use super::{ DamageSource, GameEvent, Subscriber };
+
+use serde::{ Serialize, Deserialize };
+
+use std::collections::BTreeMap;
+
+// Running totals for the current run, built up entirely from game events.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub turns: u32,
+    pub kills: u32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub items_picked_up: u32,
+    pub items_used: u32,
+    pub deepest_level: u32,
+    pub levels_gained: u32,
+    // Monsters slain, by name.
+    pub kills_by_type: BTreeMap<String, u32>,
+    // What ended the run, once the player has died.
+    pub cause_of_death: Option<String>,
+}
+
+impl RunStats {
+    pub fn new() -> RunStats {
+        RunStats {
+            deepest_level: 1, // Every run starts on the first floor.
+            ..Default::default()
+        }
+    }
+}
+
+impl Subscriber for RunStats {
+    fn notify(&mut self, event: &GameEvent, _messages: &mut Messages) {
+        match event {
+            GameEvent::TurnTaken => self.turns += 1,
+            GameEvent::Damaged { damage, is_player: true, .. } => self.damage_taken += damage,
+            // Monsters are also hurt by traps and terrain, which the player doesn't get the credit for.
+            GameEvent::Damaged { damage, is_player: false, source: DamageSource::Player, .. } => self.damage_dealt += damage,
+            GameEvent::Died { is_player: true, source, .. } => self.cause_of_death = Some(source.cause_of_death()),
+            GameEvent::Died { name, is_player: false, source: DamageSource::Player, .. } => {
+                self.kills += 1;
+                *self.kills_by_type.entry(name.clone()).or_insert(0) += 1;
+            },
+            GameEvent::ItemPickedUp { .. } => self.items_picked_up += 1,
+            GameEvent::ItemUsed { .. } => self.items_used += 1,
+            GameEvent::LevelEntered { level } => self.deepest_level = self.deepest_level.max(*level),
+            GameEvent::LevelUp { .. } => self.levels_gained += 1,
+            _ => {},
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use crate::*;
+use crate::state::LevelUpStat;
+use crate::objects::Object;
 
 const INVENTORY_WIDTH: i32 = 50;
 
@@ -16,7 +18,7 @@ pub fn menu<T: AsRef<str>>(
     let header_height = if header.is_empty() {
         0 // If there is no header, there is no height.
     } else {
-        root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header)
+        root.get_height_rect(0, 0, width, root.height(), header)
     };
     let height = options.len() as i32 + header_height;
 
@@ -52,8 +54,9 @@ pub fn menu<T: AsRef<str>>(
     }
 
     // Blit the contents of "window" to the root console.
-    let x = SCREEN_WIDTH / 2 - width / 2;
-    let y = SCREEN_HEIGHT / 2 - height / 2;
+    // Menus are centred in the window, whatever size it is.
+    let x = root.width() / 2 - width / 2;
+    let y = root.height() / 2 - height / 2;
     blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
 
     // Present the root console to the player, and await a keypress.
@@ -73,7 +76,7 @@ pub fn menu<T: AsRef<str>>(
     }
 }
 
-pub fn inventory_menu(player: &Character, header: &str, root: &mut Root) -> Option<usize> {
+pub fn inventory_menu(player: &Object, header: &str, root: &mut Root) -> Option<usize> {
     // Collects inventory items, using an empty vec, in case the inventory is inaccessible for some reason.
     let empty_vec = Vec::new();
     let inventory = match &player.inventory {
@@ -105,3 +108,29 @@ pub fn msgbox(text: &str, width: i32, root: &mut Root) {
     let options: &[&str] = &[];
     menu(text, options, width, root);
 }
+
+// Asks the player which stat to raise when they level up.
+pub fn level_up_menu(player: &Object, root: &mut Root) -> LevelUpStat {
+    let fighter = player.fighter.unwrap();
+    let mut choice = None;
+    while choice.is_none() {
+        // Continuously requests for a choice to be made, until it is made.
+        choice = menu(
+            "Level up! Choose a state to raise:\n",
+            &[
+                format!("Constitution (+20 HP, from {})", fighter.max_hp),
+                format!("Strength (+1 Attack, from {})", fighter.power),
+                format!("Agility (+1 Defense, from {})", fighter.defense),
+                format!("Perception (+1 Perception, from {})", fighter.perception),
+            ],
+            LEVEL_SCREEN_WIDTH,
+            root,
+        );
+    }
+    match choice.unwrap() {
+        0 => LevelUpStat::Constitution,
+        1 => LevelUpStat::Strength,
+        2 => LevelUpStat::Agility,
+        _ => LevelUpStat::Perception,
+    }
+}
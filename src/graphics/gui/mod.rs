@@ -1,28 +1,36 @@
 pub mod menu;
 
 use crate::*;
-use crate::objects::*;
+use crate::objects::entities::Entities;
+use crate::state::GameState;
 
 use serde::{ Serialize, Deserialize };
 
+use tcod::map::Map as FovMap;
+
 // Renders GUI elements
 // Render panel is a separate function, to allow for future possible GUI elements to be added.
-pub fn render_gui(tcod: &mut Tcod, game: &Game, characters: &[Character], items: &HashMap<i32, Object>, player: &Object) {
-    render_panel(tcod, game, characters, items, player);
+pub fn render_gui(tcod: &mut Tcod, state: &GameState) {
+    render_panel(tcod, state);
 }
 
 // Renders the panel below the map.
-fn render_panel(tcod: &mut Tcod, game: &Game, characters: &[Character], items: &HashMap<i32, Object>, player: &Object) {
+fn render_panel(tcod: &mut Tcod, state: &GameState) {
+    let game = &state.game;
+    let player = state.entities.player();
+
     // Prepares the GUI panel.
     tcod.panel.set_default_background(BLACK);
     tcod.panel.clear();
 
+    let layout = tcod.layout;
+
     // Print the game messages, line by line.
-    let mut y = MSG_HEIGHT as i32;
+    let mut y = layout.msg_height as i32;
     // Goes through the list of messages in order of newest to oldest.
     for &(ref msg, color) in game.messages.iter().rev() {
         // Finds the height of each message.
-        let msg_height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+        let msg_height = tcod.panel.get_height_rect(layout.msg_x, y, layout.msg_width, 0, msg);
         // Subtracts that height from the total height of the panel in which the messages are rendered.
         y -= msg_height;
         // If height reaches 0 or less, the loop ends.
@@ -31,7 +39,7 @@ fn render_panel(tcod: &mut Tcod, game: &Game, characters: &[Character], items: &
         }
         // Otherwise, the message is printed to the panel, using the color attached to the message.
         tcod.panel.set_default_foreground(color);
-        tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+        tcod.panel.print_rect(layout.msg_x, y, layout.msg_width, 0, msg);
     }
 
     // Show the player's stats.
@@ -41,7 +49,7 @@ fn render_panel(tcod: &mut Tcod, game: &Game, characters: &[Character], items: &
         &mut tcod.panel,
         1,
         1,
-        BAR_WIDTH,
+        layout.bar_width,
         "HP",
         hp,
         max_hp,
@@ -65,16 +73,16 @@ fn render_panel(tcod: &mut Tcod, game: &Game, characters: &[Character], items: &
         2,
         BackgroundFlag::None,
         TextAlignment::Left,
-        get_names_under_mouse(tcod.mouse, player, characters, items, &tcod.fov),
+        get_names_under_mouse(mouse_tile(tcod, state), &state.entities, &state.fov),
     );
 
     // Blit the contents of 'panel' to the root console.
     blit(
         &tcod.panel,
         (0, 0),
-        (SCREEN_WIDTH, PANEL_HEIGHT),
+        (layout.screen_width, layout.panel_height),
         &mut tcod.root,
-        (0, PANEL_Y),
+        (0, layout.panel_y),
         1.0,
         1.0,
     );
@@ -103,39 +111,35 @@ impl Messages {
     }
 }
 
-fn get_names_under_mouse(
-    mouse: Mouse,
-    player: &Object,
-    characters: &[Character],
-    items: &HashMap<i32, Object>,
-    fov_map: &FovMap
-) -> String {
-    // Collects xy location of mouse pointer, and an empty variable to store possible names.
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
-    let mut names = Vec::new();
+// The map tile under the mouse pointer, taking into account how far the map has scrolled.
+pub fn mouse_tile(tcod: &Tcod, state: &GameState) -> (i32, i32) {
+    let (camera_x, camera_y) = tcod.layout.camera(&state.game.map, state.entities.player().pos());
+    (tcod.mouse.cx as i32 + camera_x, tcod.mouse.cy as i32 + camera_y)
+}
 
-    // Creates a list with the names of all characters at mouse's coordinates in FOV.
-    let character_names = characters
-        .iter()
-        .filter(|cha| cha.object.pos() == (x, y) && fov_map.is_in_fov(cha.object.x, cha.object.y))
-        .map(|cha| cha.object.name.clone())
-        .collect::<Vec<_>>();
+fn get_names_under_mouse((x, y): (i32, i32), entities: &Entities, fov_map: &FovMap) -> String {
+    // Collects an empty variable to store possible names.
+    let mut names = Vec::new();
 
     // If player is at those coordinates, it is added to the list first.
-    if player.pos() == (x, y) {
-        names.push(player.name.clone());
+    let player_id = entities.player_id();
+    if entities.player().pos() == (x, y) {
+        names.push(entities.player().name.clone());
     }
 
-    // Adds items to vector first so they always appear at the top of the list.
-    for (_, item) in items {
-        if item.pos() == (x, y) && fov_map.is_in_fov(item.x, item.y) {
-            names.push(item.name.clone());
-        }
-    }
+    // Creates a list of everything else at mouse's coordinates in FOV, leaving out traps that haven't been found.
+    let mut under_mouse = entities
+        .at(x, y)
+        .filter(|(id, object)| {
+            *id != player_id && fov_map.is_in_fov(object.x, object.y) && !object.trap.map_or(false, |trap| trap.hidden)
+        })
+        .map(|(_, object)| object)
+        .collect::<Vec<_>>();
 
-    // Adds characters into the same vector as above.
-    for character in character_names.iter() {
-        names.push(character.clone());
+    // Items are sorted to the front so they always appear at the top of the list.
+    under_mouse.sort_by_key(|object| object.item.is_none());
+    for object in under_mouse {
+        names.push(object.name.clone());
     }
 
     // Concatenates the vector items into a string separated by new lines
@@ -180,10 +184,7 @@ fn render_bar(
 
 pub fn target_tile(
     tcod: &mut Tcod,
-    game: &mut Game,
-    characters: &[Character],
-    items: &HashMap<i32, Object>,
-    player: &Object,
+    state: &GameState,
     max_range: Option<f32>
 ) -> Option<(i32, i32)> {
     use tcod::input::KeyCode::Escape;
@@ -200,14 +201,14 @@ pub fn target_tile(
         }
 
         // Continues rendering the map, and things within it.
-        render_all(tcod, game, characters, items, false, player);
+        render_all(tcod, state);
 
         // Assigns xy variables based on mouse xy location.
-        let (x, y) = (tcod.mouse.cx as i32, tcod.mouse.cy as i32);
+        let (x, y) = mouse_tile(tcod, state);
 
         // Accepts target if the click was in FOV and in range, if range was specified.
-        let in_fov = (x < MAP_WIDTH) && (y < MAP_HEIGHT) && tcod.fov.is_in_fov(x, y);
-        let in_range = max_range.map_or(true, |range| player.distance(x, y) <= range);
+        let in_fov = in_map(&state.game.map, x, y) && state.fov.is_in_fov(x, y);
+        let in_range = max_range.map_or(true, |range| state.entities.player().distance(x, y) <= range);
         if tcod.mouse.lbutton_pressed && in_fov && in_range {
             return Some((x, y));
         }
@@ -0,0 +1,70 @@
+use crate::environment::Map;
+use crate::environment::map_dimensions;
+
+// Default size of the window, in characters.
+pub const DEFAULT_SCREEN_WIDTH: i32 = 81;
+pub const DEFAULT_SCREEN_HEIGHT: i32 = 50;
+
+// Smallest window the GUI can fit into.
+pub const MIN_SCREEN_WIDTH: i32 = 60;
+pub const MIN_SCREEN_HEIGHT: i32 = 30;
+
+const BAR_WIDTH: i32 = 20;
+const PANEL_HEIGHT: i32 = 7;
+
+// Where each part of the GUI goes, worked out from the size of the window.
+// The map is shown in the space above the panel, and scrolls with the player if it doesn't fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub screen_width: i32,
+    pub screen_height: i32,
+    pub bar_width: i32,
+    pub panel_height: i32,
+    pub panel_y: i32,
+    pub msg_x: i32,
+    pub msg_width: i32,
+    pub msg_height: usize,
+    pub view_width: i32,
+    pub view_height: i32,
+}
+
+impl Layout {
+    // Lays out the GUI for a window of the given size.
+    pub fn new(screen_width: i32, screen_height: i32) -> Result<Layout, String> {
+        if screen_width < MIN_SCREEN_WIDTH || screen_height < MIN_SCREEN_HEIGHT {
+            return Err(format!(
+                "the window must be at least {}x{}, but {}x{} was given",
+                MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT, screen_width, screen_height,
+            ));
+        }
+
+        let panel_y = screen_height - PANEL_HEIGHT;
+        Ok(Layout {
+            screen_width,
+            screen_height,
+            bar_width: BAR_WIDTH,
+            panel_height: PANEL_HEIGHT,
+            panel_y,
+            msg_x: BAR_WIDTH + 4,
+            msg_width: screen_width - BAR_WIDTH - 2,
+            msg_height: PANEL_HEIGHT as usize - 1,
+            view_width: screen_width,
+            view_height: panel_y,
+        })
+    }
+
+    // The map tile shown in the top left corner of the screen.
+    // Keeps the player in the middle of the view, without scrolling past the edges of the map.
+    pub fn camera(&self, map: &Map, (player_x, player_y): (i32, i32)) -> (i32, i32) {
+        let (map_width, map_height) = map_dimensions(map);
+        let x = (player_x - self.view_width / 2).min(map_width - self.view_width).max(0);
+        let y = (player_y - self.view_height / 2).min(map_height - self.view_height).max(0);
+        (x, y)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::new(DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT).unwrap()
+    }
+}
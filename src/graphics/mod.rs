@@ -1,75 +1,68 @@
 pub mod gui;
+pub mod layout;
 
 use crate::*;
-use crate::objects::*;
 use crate::environment::*;
+use crate::state::GameState;
+use crate::rng::GameRng;
 use gui::render_gui;
 
 use rand::*;
 
-pub fn render_all(
-    tcod: &mut Tcod,
-    game: &mut Game,
-    characters: &[Character],
-    items: &HashMap<i32, Object>,
-    fov_recompute: bool,
-    player: &Object,
-) {
-    if fov_recompute {
-        //Recomputes FOV is needed, such as player movement
-        tcod.fov.compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
-    }
+pub fn render_all(tcod: &mut Tcod, state: &GameState) {
+    let (map_width, map_height) = map_dimensions(&state.game.map);
 
     // Scans the map
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
+    for y in 0..map_height {
+        for x in 0..map_width {
 
             // Checks to see if each tile is in the player's FOV.
-            let visible = tcod.fov.is_in_fov(x, y);
+            let visible = state.fov.is_in_fov(x, y);
 
             // Color of the tile at present moment is determined.
+            let tile = &state.game.map[x as usize][y as usize];
             let color = match visible {
                 // Outside of field of view:
-                false => game.map[x as usize][y as usize].color_dark,
+                false => tile.color_dark,
 
                 // Inside the field of view:
-                true => game.map[x as usize][y as usize].color_light,
+                true => tile.color_light,
             };
 
-            // If tile is currently in FOV, set the tiles "explored" variable to true.
-            let explored = &mut game.map[x as usize][y as usize].explored;
-            if visible {
-                //Since it's visible, explore it
-                *explored = true;
-            }
-
             // If a tiles "explored" variable is true, it will become visible.
-            if *explored {
+            if tile.explored {
                 tcod.con.set_char_background(x, y, color, BackgroundFlag::Set);
+
+                // Doors and terrain are drawn on top of their tile, so they stand out from the floor and walls.
+                if let Some((glyph, glyph_color)) = tile.glyph() {
+                    tcod.con.set_default_foreground(glyph_color);
+                    tcod.con.put_char(x, y, glyph, BackgroundFlag::None);
+                }
             }
         }
     }
 
     // Calls functions to render objects, and the GUI.
-    draw_objects(tcod, game, items, characters, player);
-    render_gui(tcod, game, characters, items, player);
+    draw_objects(tcod, state);
+    render_gui(tcod, state);
 }
 
-fn draw_objects(tcod: &mut Tcod, game: &mut Game, items: &HashMap<i32, Object>, characters: &[Character], player: &Object) {
-
-    // Draws items, then draws characters.
-    // Characters are second, so that they have visibility priority over items.
-    draw_items(tcod, game, items);
-    draw_chars(tcod, game, characters);
+fn draw_objects(tcod: &mut Tcod, state: &GameState) {
+    // Draws everything on the level apart from the player.
+    draw_entities(tcod, state);
 
     // Finally, it renders the player.
-    player.draw(&mut tcod.con);
+    state.entities.player().draw(&mut tcod.con);
 
     // Blit the contents (items + characters) of "con" to the root console and present it
+    // Only the part of the map around the player that fits above the panel is shown.
+    let layout = tcod.layout;
+    let camera = layout.camera(&state.game.map, state.entities.player().pos());
+    let size = (layout.view_width.min(tcod.con.width()), layout.view_height.min(tcod.con.height()));
     blit(
         &tcod.con,
-        (0, 0),
-        (SCREEN_WIDTH, SCREEN_HEIGHT),
+        camera,
+        size,
         &mut tcod.root,
         (0, 0),
         1.0,
@@ -77,56 +70,51 @@ fn draw_objects(tcod: &mut Tcod, game: &mut Game, items: &HashMap<i32, Object>,
     );
 }
 
-fn draw_items(tcod: &mut Tcod, game: &mut Game, items: &HashMap<i32, Object>) {
-    // Searches through items hashmap.
-    for item in items.values() {
-        // If item is in FOV, or "always_visible" variable is true in the location of an explored tile.
-        // Draw the item.
-        if tcod.fov.is_in_fov(item.x, item.y) ||
-            item.always_visible && game.map[item.x as usize][item.y as usize].explored {
-            item.draw(&mut tcod.con);
-        }
-    }
-}
+fn draw_entities(tcod: &mut Tcod, state: &GameState) {
+    let player_id = state.entities.player_id();
 
-fn draw_chars(tcod: &mut Tcod, game: &mut Game, characters: &[Character]) {
-    // Sorts character list to place non-blocking (corpses) first.
-    // This allows living characters to appear on top of them.
-    let mut to_draw: Vec<_> = characters
+    // Only draws entities in FOV, or "always_visible" entities in the location of an explored tile.
+    // Traps which haven't been found yet are never drawn.
+    let mut to_draw: Vec<_> = state.entities
         .iter()
-        .filter(|c| {
-            tcod.fov.is_in_fov(c.object.x, c.object.y) ||
-            (c.object.always_visible && game.map[c.object.x as usize][c.object.y as usize].explored)
+        .filter(|(id, object)| {
+            *id != player_id && !object.trap.map_or(false, |trap| trap.hidden) && (
+                state.fov.is_in_fov(object.x, object.y) ||
+                (object.always_visible && state.game.map[object.x as usize][object.y as usize].explored)
+            )
         })
+        .map(|(_, object)| object)
         .collect();
-    to_draw.sort_by(|c1, c2| c1.object.blocks.cmp(&c2.object.blocks));
 
-    // Draw all characters in the list
-    for character in &to_draw {
-        character.object.draw(&mut tcod.con);
+    // Sorts the list so that items are drawn first, then non-blocking objects (corpses), then living characters.
+    // This allows characters to appear on top of whatever they are standing on.
+    to_draw.sort_by_key(|object| (object.blocks, object.item.is_none()));
+
+    for object in &to_draw {
+        object.draw(&mut tcod.con);
     }
 }
 
-pub fn gen_colors() -> [Color; 7] {
+pub fn gen_colors(rng: &mut GameRng) -> [Color; 7] {
     // Light wall color is established.
     let light_wall_color: Color = Color {
-        r: ((rand::thread_rng().gen_range(80, 130))),
-        g: ((rand::thread_rng().gen_range(80, 130))),
-        b: ((rand::thread_rng().gen_range(80, 130)))
+        r: ((rng.gen_range(80, 130))),
+        g: ((rng.gen_range(80, 130))),
+        b: ((rng.gen_range(80, 130)))
     };
 
     // Light ground color is established.
     let light_ground_color: Color = Color {
-        r: ((rand::thread_rng().gen_range(65, 175))),
-        g: ((rand::thread_rng().gen_range(65, 175))),
-        b: ((rand::thread_rng().gen_range(65, 175)))
+        r: ((rng.gen_range(65, 175))),
+        g: ((rng.gen_range(65, 175))),
+        b: ((rng.gen_range(65, 175)))
     };
 
     // Variant is established, which just functions as a modifier to provide variation.
     let variant: Color = Color  {
-        r: ((rand::thread_rng().gen_range(0, 30))),
-        g: ((rand::thread_rng().gen_range(0, 30))),
-        b: ((rand::thread_rng().gen_range(0, 30)))
+        r: ((rng.gen_range(0, 30))),
+        g: ((rng.gen_range(0, 30))),
+        b: ((rng.gen_range(0, 30)))
     };
 
     // Creates higher, and lower variants based on the above colors.
@@ -137,9 +125,9 @@ pub fn gen_colors() -> [Color; 7] {
 
     // A darkness modifier is created, which gets subtracted from the base tile color whenever it is in darkness.
     let dark_modifier: Color = Color {
-        r: ((rand::thread_rng().gen_range(25, 35))),
-        g: ((rand::thread_rng().gen_range(25, 35))),
-        b: ((rand::thread_rng().gen_range(5, 15))),
+        r: ((rng.gen_range(25, 35))),
+        g: ((rng.gen_range(25, 35))),
+        b: ((rng.gen_range(5, 15))),
     };
 
     // Returns an array consisting of all of the color variants + darkness modifier.
@@ -158,33 +146,28 @@ pub fn gen_colors() -> [Color; 7] {
 
 pub fn render_map(
     tcod: &mut Tcod,
-    map: &mut Map,
+    map: &Map,
     frames: u32,
 ) {
     // Functions the same as the regular map rendering, although it has some differences.
-    // Always shows all tiles.
+    // Always shows all tiles, with walls shaded darker than the floor.
     // Also shows the map for a short period of time, determined by the "frames" variable.
-    // It should only be used during map gen, to visualize what the algorithm is doing.
-    tcod.fov.compute_fov(1, 1, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
-
+    // It should only be used with snapshots from the map recorder, to visualize what the algorithm is doing.
     for _ in 1..frames {
 
         tcod.root.clear();
 
-        for y in 0..MAP_HEIGHT {
-            for x in 0..MAP_WIDTH {
+        // Maps too big for the window are cut off, as there is no player to follow yet.
+        let (map_width, map_height) = map_dimensions(map);
+        let width = map_width.min(tcod.layout.view_width);
+        let height = map_height.min(tcod.layout.view_height);
+        for y in 0..height {
+            for x in 0..width {
 
-                let visible = tcod.fov.is_in_fov(x, y);
                 let wall = map[x as usize][y as usize].block_sight;
-                let color = match(visible, wall) {
-
-                    // Outside of field of view:
-                    (false, true) => map[x as usize][y as usize].color_dark,
-                    (false, false) => map[x as usize][y as usize].color_dark,
-
-                    // Inside the field of view:
-                    (true, true) => map[x as usize][y as usize].color_light,
-                    (true, false) => map[x as usize][y as usize].color_light,
+                let color = match wall {
+                    true => map[x as usize][y as usize].color_dark,
+                    false => map[x as usize][y as usize].color_light,
                 };
 
                 tcod.root.set_char_background(x, y, color, BackgroundFlag::Set);
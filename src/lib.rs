@@ -3,36 +3,40 @@ pub mod controls;
 pub mod environment;
 pub mod graphics;
 pub mod pathing;
-
-use objects::{ Object, Character };
+pub mod state;
+pub mod rng;
+pub mod replay;
+pub mod scheduler;
+pub mod events;
+pub mod config;
+pub mod simulate;
+pub mod save;
+pub mod morgue;
+pub mod visualise;
+
+use rng::GameRng;
+use replay::{ Replay, save_replay, load_replay, replay_step };
+use config::Config;
+use save::{ save_game, load_game, delete_save, emergency_path, run_saves, SaveError };
+use morgue::write_morgue;
+use save::slots::{ SaveSlot, list_slots, latest_slot, slot_path, unused_slot_name, duplicate_slot, delete_slot };
 use environment::*;
-use controls::{ handle_keys, PlayerAction };
-use graphics::render_all;
-use graphics::gui::menu::{ menu, msgbox };
+use controls::{ handle_keys, PlayerInput };
+use state::{ GameState, PlayerCommand };
+use events::GameEvent;
+use graphics::{ render_all, render_map };
+use graphics::layout::Layout;
+use graphics::gui::menu::{ menu, msgbox, level_up_menu };
 
-use std::collections::HashMap;
-use std::error::Error;
-use std::fs::File;
-use std::io::{ Read, Write };
+use std::panic::{ self, AssertUnwindSafe };
+use std::path::{ Path, PathBuf };
 
 use tcod::console::*;
 use tcod::colors::*;
-use tcod::map::Map as FovMap;
 use tcod::input::{ self, Event, Key, Mouse };
 
 const LIMIT_FPS: i32 = 60; // 20 frames-per-second maximum
 
-const SCREEN_WIDTH: i32 = 81;
-const SCREEN_HEIGHT: i32 = 50;
-
-const BAR_WIDTH: i32 = 20;
-const PANEL_HEIGHT: i32 = 7;
-const PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
-
-const MSG_X: i32 = BAR_WIDTH + 4;
-const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
-const MSG_HEIGHT: usize = PANEL_HEIGHT as usize - 1;
-
 const LIGHT_WALL_COLOR: usize = 0;
 const LIGHT_GROUND_COLOR: usize = 3;
 const V_ONE: usize = 1;
@@ -45,46 +49,176 @@ const LEVEL_SCREEN_WIDTH: i32 = 40;
 
 const CHARACTER_SCREEN_WIDTH: i32 = 30;
 
+const REPLAY_FRAMES_PER_STEP: u32 = 6;
+const REPLAY_SCREEN_WIDTH: i32 = 50;
+const SAVE_ERROR_WIDTH: i32 = 50;
+
+// The slot menu leaves room for one more option, to go back.
+const MAX_LISTED_SLOTS: usize = 25;
+
 pub type Point = (u32, u32); // (x, y)
 
-// Struct to contain the game.
+// Struct to contain the libtcod window, and the input coming from it.
+// The game itself lives in GameState, so that it can run without a window.
 pub struct Tcod {
     pub root: Root,
     pub con: Offscreen,
     pub panel: Offscreen,
-    pub fov: FovMap,
+    pub layout: Layout,
     pub key: Key,
     pub mouse: Mouse,
 }
 
 // Constructor for the Tcod struct
 impl Tcod {
-    pub fn new() -> Tcod {
+    // The window, and everything drawn in it, is sized by the layout.
+    // The map console is sized to each game's map once the game starts.
+    pub fn new(layout: Layout) -> Tcod {
         let root = Root::initializer()
             .font("arial10x10.png", FontLayout::Tcod)
             .font_type(FontType::Greyscale)
-            .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+            .size(layout.screen_width, layout.screen_height)
             .title("Rust/libtcod tutorial")
             .init();
 
-        let con = Offscreen::new(MAP_WIDTH, MAP_HEIGHT);
-        let panel = Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT);
-        let fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
+        let map_size = MapSize::default();
+        let con = Offscreen::new(map_size.width, map_size.height);
+        let panel = Offscreen::new(layout.screen_width, layout.panel_height);
         let key = Default::default();
         let mouse = Default::default();
 
-        Tcod { root, con, panel, fov, key, mouse }
+        Tcod { root, con, panel, layout, key, mouse }
+    }
+
+    // Remakes the map console at the size of a game's map.
+    // Every level in a run is the same size, but a loaded game may have been made at another size.
+    pub fn fit_map(&mut self, map: &Map) {
+        let (map_width, map_height) = map_dimensions(map);
+        self.con = Offscreen::new(map_width, map_height);
     }
 }
 
-pub fn run_game() {
-    let mut tcod = Tcod::new();
+// Starts the game window, set up by the config.
+// If the config has a seed, every new game will use it.
+pub fn run_game(config: &Config) {
+    let layout = config.layout().unwrap_or_default();
+    let mut tcod = Tcod::new(layout);
     tcod::system::set_fps(LIMIT_FPS);
+    install_panic_hook();
+
+    // The main menu can be skipped, to get straight into the saved game, or a new one.
+    if config.continue_game {
+        match continue_path(config).and_then(|path| load_game(&path).map(|state| (path, state))) {
+            Ok((path, mut state)) => play_game(&mut tcod, &mut state, config, &path),
+            Err(e) => msgbox(&format!("\n{}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root),
+        }
+        return;
+    }
+    if config.skip_menu {
+        new_game(&mut tcod, config);
+        return;
+    }
+
+    main_menu(&mut tcod, config);
+}
+
+// Lets the player know a crash isn't the end of their run, after the usual panic message is printed.
+// The save itself happens in play_game, once the crash has unwound and the game state is free to use.
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        eprintln!("The game has crashed. Attempting an emergency save...");
+    }));
+}
+
+// The save to pick up from when continuing without the menu: the configured save file, or the newest slot.
+pub fn continue_path(config: &Config) -> Result<PathBuf, SaveError> {
+    match &config.save_file {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => latest_slot(&config.save_dir()).map(|slot| slot.path),
+    }
+}
+
+// Starts, and plays, a brand new game.
+// Each new game gets its own save slot, unless the config names a single save file.
+fn new_game(tcod: &mut Tcod, config: &Config) {
+    let save_path = match &config.save_file {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = config.save_dir();
+            slot_path(&dir, &unused_slot_name(&dir, "run"))
+        },
+    };
+
+    let options = config.run_options(GameRng::random_seed());
+    let mut state = GameState::new(options, config.watch_map_generation);
+    play_game(tcod, &mut state, config, &save_path);
+}
+
+// Lets the player pick a save slot to continue, duplicate or delete.
+fn continue_menu(tcod: &mut Tcod, config: &Config) {
+    // A single configured save file doesn't need a menu.
+    if let Some(path) = &config.save_file {
+        let path = PathBuf::from(path);
+        match load_game(&path) {
+            Ok(mut state) => play_game(tcod, &mut state, config, &path),
+            // Tells the player why, whether the save is missing, damaged, or from a newer version.
+            Err(e) => msgbox(&format!("\n{}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root),
+        }
+        return;
+    }
+
+    let dir = config.save_dir();
+    let width = tcod.layout.screen_width - 6;
+    loop {
+        let slots: Vec<SaveSlot> = list_slots(&dir).into_iter().take(MAX_LISTED_SLOTS).collect();
+        if slots.is_empty() {
+            msgbox("\nNo saved games to load.\n", 24, &mut tcod.root);
+            return;
+        }
 
-    main_menu(&mut tcod);
+        // Picks a slot, with "Back" as the last option.
+        let mut options: Vec<String> = slots.iter().map(|slot| slot.describe()).collect();
+        options.push("Back".into());
+        let slot = match menu("Choose a saved game:\n", &options, width, &mut tcod.root) {
+            Some(index) if index < slots.len() => &slots[index],
+            _ => return,
+        };
+
+        // Then picks what to do with it.
+        let header = format!("{}\n", slot.describe());
+        let actions = &["Continue", "Duplicate", "Delete", "Back"];
+        match menu(&header, actions, width, &mut tcod.root) {
+            Some(0) => {
+                match load_game(&slot.path) {
+                    Ok(mut state) => {
+                        play_game(tcod, &mut state, config, &slot.path);
+                        return;
+                    },
+                    Err(e) => msgbox(&format!("\n{}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root),
+                }
+            },
+            Some(1) => {
+                if let Err(e) = duplicate_slot(&dir, slot) {
+                    msgbox(&format!("\n{}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root);
+                }
+            },
+            Some(2) => {
+                // Deleting can't be undone, so it has to be confirmed.
+                let confirm = format!("Delete {}? This can't be undone.\n", slot.name);
+                if menu(&confirm, &["Delete", "Keep"], width, &mut tcod.root) == Some(0) {
+                    if let Err(e) = delete_slot(slot) {
+                        msgbox(&format!("\n{}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
 }
 
-fn main_menu(mut tcod: &mut Tcod) {
+fn main_menu(mut tcod: &mut Tcod, config: &Config) {
     let img = tcod::image::Image::from_file("menu_background.png")
     .ok()
     .expect("Background image not found");
@@ -96,38 +230,37 @@ fn main_menu(mut tcod: &mut Tcod) {
         // Displays game title.
         tcod.root.set_default_foreground(LIGHT_YELLOW);
         tcod.root.print_ex(
-            SCREEN_WIDTH / 2,
-            SCREEN_HEIGHT / 2 - 4,
+            tcod.layout.screen_width / 2,
+            tcod.layout.screen_height / 2 - 4,
             BackgroundFlag::None,
             TextAlignment::Center,
             "TECHNICALLY A VIDEO GAME",
         );
 
         // Show options, and wait for the player's choice.
-        let choices = &["Play a new game", "Continue last game", "Quit"];
+        let choices = &["Play a new game", "Continue a saved game", "Watch replay", "Quit"];
         let choice = menu("", choices, 24, &mut tcod.root);
 
         match choice {
             Some(0) => {
                 // New game
-                let (mut game, mut characters, mut items, mut player) = new_game(&mut tcod);
-                play_game(&mut tcod, &mut game, &mut characters, &mut items, &mut player);
+                new_game(&mut tcod, config);
             },
             Some(1) => {
                 // Loads game
-                match load_game() {
-                    Ok((mut game, mut characters, mut items, mut player)) => {
-                        initialise_fov(tcod, &game.map);
-                        play_game(&mut tcod, &mut game, &mut characters, &mut items, &mut player);
-                    },
+                continue_menu(&mut tcod, config);
+            }
+            Some(2) => {
+                // Plays back the last recorded run.
+                match load_replay(&config.replay_file) {
+                    Ok(replay) => watch_replay(&mut tcod, &replay),
                     Err(_e) => {
-                        msgbox("\nNo saved game to load.\n", 24, &mut tcod.root);
+                        msgbox("\nNo replay to watch.\n", 24, &mut tcod.root);
                         continue;
                     }
                 }
-
-            }
-            Some(2) => {
+            },
+            Some(3) => {
                 // Quit
                 break;
             },
@@ -136,92 +269,63 @@ fn main_menu(mut tcod: &mut Tcod) {
     }
 }
 
-fn new_game(tcod: &mut Tcod) -> (Game, Vec<Character>, HashMap<i32, Object>, Character) {
-    // Creates game objects
-    let mut characters: Vec<Character> = vec![];
-    let mut items = HashMap::new();
-    let mut player = Object::new_player();
-
-    // Generate map to be rendered
-    let mut game = Game::new(&mut characters, &mut items, &mut player.object, tcod);
-
-    // Starts the FOV based on the new map.
-    initialise_fov(tcod, &game.map);
-
-    // Intro message
-    game.messages.add(
-        "Dive deep. Gain power. Try not to die in these ancient tombs...",
-        GOLD,
-    );
-
-    (game, characters, items, player)
-}
-
-fn initialise_fov(tcod: &mut Tcod, map: &Map) {
-    // Populates the FOV map, based on the generated map
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
-            tcod.fov.set(
-                x, y,
-                !map[x as usize][y as usize].block_sight,
-                !map[x as usize][y as usize].blocked,
-            );
+// Plays a game until the player quits.
+// If the game crashes, the run is saved next to its usual save before the crash carries on.
+fn play_game(tcod: &mut Tcod, state: &mut GameState, config: &Config, save_path: &Path) {
+    tcod.fit_map(&state.game.map);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| game_loop(tcod, state, config, save_path)));
+
+    if let Err(crash) = result {
+        // A run that has already ended in death is never saved, so it can't be carried on.
+        if state.entities.player().alive {
+            let path = emergency_path(save_path);
+            match save_game(state, &path, config.save_encoding) {
+                Ok(()) => eprintln!("Your run was saved to {}.", path.display()),
+                Err(e) => eprintln!("The emergency save failed: {}", e),
+            }
+        } else {
+            eprintln!("Your run had already ended, so it wasn't saved.");
         }
+        panic::resume_unwind(crash);
     }
-
-    // Unexplored areas start black (Default background color)
-    tcod.con.clear();
 }
 
-fn save_game(
-    game: &Game,
-    characters: &mut Vec<Character>,
-    items: &mut HashMap<i32, Object>,
-    player: &mut Character,
-) -> Result<(), Box<dyn Error>> {
-
-    // Converts game data to JSON.
-    let save_data = serde_json::to_string(&(game, characters, items, player))?;
-
-    // Creates, or overwrites, a file called "savegame".
-    let mut file = File::create("savegame")?;
-
-    // Writes the JSON data to the "savegame" file.
-    file.write_all(save_data.as_bytes())?;
-
-    // Returns an Ok() to ensure that no errors took place in this function.
-    Ok(())
+// Saves the game without interrupting play. Problems are reported in the message log.
+fn autosave(state: &mut GameState, save_path: &Path, config: &Config) {
+    if let Err(e) = save_game(state, save_path, config.save_encoding) {
+        state.game.messages.add(format!("Autosave failed. {}", e), RED);
+    }
 }
 
-fn load_game() -> Result<(Game, Vec<Character>, HashMap<i32, Object>, Character), Box<dyn Error>> {
-    // Creates an empty string.
-    let mut json_save_state = String::new();
-
-    // Loads "savegame" file into the system.
-    let mut file = File::open("savegame")?;
-
-    // Pours the data of the savegame file into the empty string
-    file.read_to_string(&mut json_save_state)?;
+// Death is permanent: the save is removed, and a record of the run is left in the morgue instead.
+fn end_run(state: &mut GameState, save_path: &Path, config: &Config) {
+    // Any save left behind by a crash goes too, so the run can't be carried on from it.
+    for path in run_saves(save_path) {
+        if let Err(e) = delete_save(&path) {
+            state.game.messages.add(format!("The save could not be removed. {}", e), RED);
+        }
+    }
+    match write_morgue(state, &config.morgue_dir()) {
+        Ok(path) => state.game.messages.add(format!("The tale of your run is kept in {}", path.display()), LIGHT_GREY),
+        Err(e) => state.game.messages.add(format!("The morgue file could not be written. {}", e), RED),
+    }
+}
 
-    // Loads the data imported to string to the different game elements.
-    let result = serde_json::from_str::<(Game, Vec<Character>, HashMap<i32, Object>, Character)>(&json_save_state)?;
+fn game_loop(mut tcod: &mut Tcod, state: &mut GameState, config: &Config, save_path: &Path) {
+    // Loaded games don't know whether the player wants to watch levels being made.
+    state.recorder.enabled = config.watch_map_generation;
 
-    // Returns those elements in an Ok() to be unpacked into the game.
-    Ok(result)
-}
+    // Unexplored areas start black (Default background color)
+    tcod.con.clear();
 
-fn play_game(
-    mut tcod: &mut Tcod,
-    mut game: &mut Game,
-    mut characters: &mut Vec<Character>,
-    mut items: &mut HashMap<i32, Object>,
-    mut player: &mut Character,
-) {
-    // Force FOV "recompute" first time through the game loop
-    let mut previous_player_position = (-1, -1);
+    // Turn count at the last autosave.
+    let mut last_autosave = state.stats.turns;
 
     // Keeps the core game loop happening so long as the window remains open.
     while !tcod.root.window_closed() {
+        // Plays back any map generation that was recorded.
+        show_map_generation(tcod, state);
+
         // Clears the screen of the previous frame
         tcod.con.clear();
 
@@ -232,30 +336,113 @@ fn play_game(
         }
 
         // Renders the screen
-        let fov_recompute = previous_player_position != (player.object.pos());
-        render_all(&mut tcod, &mut game, &characters[..], &items, fov_recompute, &mut player.object);
+        render_all(&mut tcod, state);
 
         // Moves everything to render onto the main console.
         tcod.root.flush();
 
         // Level up if needed.
-        Object::level_up(tcod, game, &mut player.object);
+        if state.entities.player().alive && state.level_up_ready() {
+            let stat = level_up_menu(state.entities.player(), &mut tcod.root);
+            state.step(PlayerCommand::LevelUp { stat });
+        }
 
         // Handles keys, and exits game if prompted
-        previous_player_position = player.object.pos();
-        let player_action = handle_keys(&mut tcod, &mut game, &mut characters, &mut items, &mut player);
-        if player_action == PlayerAction::Exit {
-            save_game(game, characters, items, player).unwrap();
-            break;
-        }
+        match handle_keys(&mut tcod, state) {
+            PlayerInput::Command(command) => {
+                // The game state handles the command, and the monster turns that follow.
+                for event in state.step(command) {
+                    match event {
+                        GameEvent::LevelEntered { .. } | GameEvent::LevelReturned { .. } => {
+                            // Unexplored areas of the new level start black.
+                            tcod.con.clear();
+
+                            // The game is saved whenever the player changes level.
+                            autosave(state, save_path, config);
+                            last_autosave = state.stats.turns;
+                        },
+                        GameEvent::Died { is_player: true, .. } => {
+                            // Keeps the replay of the run, so it can be watched back.
+                            if let Err(e) = save_replay(&state.replay, &config.replay_file) {
+                                state.game.messages.add(format!("The replay could not be saved. {}", e), RED);
+                            }
+                            end_run(state, save_path, config);
+                        },
+                        _ => {},
+                    }
+                }
 
-        // Lets monsters take their turn
-        if player.object.alive && player_action != PlayerAction::DidntTakeTurn {
-            for id in 0..characters.len() {
-                if characters[id].object.ai.is_some() {
-                    Object::ai_take_turn(id, &tcod, &mut game, &mut characters, &mut player.object);
+                // Saves every so often, in case the game is closed without saving.
+                let interval = config.autosave_turns;
+                if interval > 0 && state.entities.player().alive && state.stats.turns >= last_autosave + interval {
+                    autosave(state, save_path, config);
+                    last_autosave = state.stats.turns;
                 }
-            }
+            },
+            PlayerInput::Exit => {
+                // A finished run has nothing left to save.
+                if !state.entities.player().alive {
+                    break;
+                }
+
+                // The game stays open if it couldn't be saved, so the run isn't lost by accident.
+                if let Err(e) = save_game(state, save_path, config.save_encoding) {
+                    state.game.messages.add(format!("The game could not be saved. {}", e), RED);
+                    continue;
+                }
+                if let Err(e) = save_replay(&state.replay, &config.replay_file) {
+                    msgbox(&format!("\nThe replay could not be saved. {}\n", e), SAVE_ERROR_WIDTH, &mut tcod.root);
+                }
+                break;
+            },
+            PlayerInput::Nothing => {},
+        }
+    }
+}
+
+// Renders each of the snapshots taken while the current map was being generated.
+fn show_map_generation(tcod: &mut Tcod, state: &mut GameState) {
+    for (map, frames) in state.recorder.drain() {
+        render_map(tcod, &map, frames);
+    }
+}
+
+// Plays a recorded run back in the window, one command at a time.
+// Press escape to stop watching early.
+fn watch_replay(tcod: &mut Tcod, replay: &Replay) {
+    use tcod::input::KeyCode::Escape;
+
+    let mut state = GameState::new(replay.options, false);
+    tcod.fit_map(&state.game.map);
+    tcod.con.clear();
+
+    for step in 0..replay.steps.len() {
+        if tcod.root.window_closed() {
+            return;
+        }
+
+        // Shows the current state of the replay for a few frames.
+        for _ in 0..REPLAY_FRAMES_PER_STEP {
+            tcod.con.clear();
+            render_all(tcod, &state);
+            tcod.root.flush();
+        }
+
+        match input::check_for_event(input::KEY_PRESS) {
+            Some((_, Event::Key(k))) if k.code == Escape => return,
+            _ => {},
+        }
+
+        // Carries out the recorded command, and stops if the game no longer matches the recording.
+        if let Err(divergence) = replay_step(&mut state, replay, step) {
+            let msg = format!(
+                "\nThe replay diverged at step {} of {}.\n\nExpected: {:?}\n\nFound: {:?}\n",
+                divergence.step + 1, replay.steps.len(), divergence.expected, divergence.actual,
+            );
+            msgbox(&msg, REPLAY_SCREEN_WIDTH, &mut tcod.root);
+            return;
         }
     }
+
+    msgbox("\nThe replay has finished.\n", 24, &mut tcod.root);
 }
@@ -0,0 +1,107 @@
+use crate::save::GAME_VERSION;
+use crate::save::slots::format_timestamp;
+use crate::state::GameState;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+// How many of the last messages are copied into a morgue file.
+const MORGUE_MESSAGES: usize = 20;
+
+// Writes a description of a finished run to a new file in the morgue directory.
+// Returns where the file was written.
+pub fn write_morgue(state: &GameState, dir: &Path) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    fs::create_dir_all(dir)?;
+
+    // Named after the seed and the time of death, so no two runs share a file.
+    let path = dir.join(format!("morgue-{}-{}.txt", state.game.seed, timestamp));
+    fs::write(&path, morgue_text(state, timestamp))?;
+    Ok(path)
+}
+
+// Everything worth remembering about a run, as plain text.
+pub fn morgue_text(state: &GameState, timestamp: u64) -> String {
+    let player = state.entities.player();
+    let stats = &state.stats;
+    let mut text = String::new();
+
+    // Writing to a String can't fail, so the results are ignored throughout.
+    let _ = writeln!(text, "Roguelike {} morgue file", GAME_VERSION);
+    let _ = writeln!(text, "Seed: {}", state.game.seed);
+    let _ = writeln!(text, "Date: {} UTC", format_timestamp(timestamp));
+    let _ = writeln!(text);
+    let _ = writeln!(
+        text,
+        "{} on dungeon level {}, at character level {}, after {} turns.",
+        stats.cause_of_death.as_ref().map_or("Died", |cause| cause.as_str()),
+        state.game.dungeon_level,
+        player.level,
+        stats.turns,
+    );
+
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Character");
+    if let Some(fighter) = player.fighter {
+        let _ = writeln!(text, "    Max HP:     {}", fighter.max_hp);
+        let _ = writeln!(text, "    Power:      {}", fighter.power);
+        let _ = writeln!(text, "    Defense:    {}", fighter.defense);
+        let _ = writeln!(text, "    Perception: {}", fighter.perception);
+        let _ = writeln!(text, "    Speed:      {}", fighter.speed);
+        let _ = writeln!(text, "    Exp:        {}", fighter.exp);
+    }
+
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Stats");
+    let _ = writeln!(text, "    Deepest level:   {}", stats.deepest_level);
+    let _ = writeln!(text, "    Monsters slain:  {}", stats.kills);
+    let _ = writeln!(text, "    Damage dealt:    {}", stats.damage_dealt);
+    let _ = writeln!(text, "    Damage taken:    {}", stats.damage_taken);
+    let _ = writeln!(text, "    Items picked up: {}", stats.items_picked_up);
+    let _ = writeln!(text, "    Items used:      {}", stats.items_used);
+    let _ = writeln!(text, "    Levels gained:   {}", stats.levels_gained);
+
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Inventory");
+    let inventory = player.inventory.as_deref().unwrap_or(&[]);
+    if inventory.is_empty() {
+        let _ = writeln!(text, "    (empty)");
+    }
+    for (index, item) in inventory.iter().enumerate() {
+        let _ = writeln!(text, "    {}) {}", (b'a' + index as u8) as char, item.name);
+    }
+
+    // Most killed monsters first.
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Kills");
+    let mut kills: Vec<_> = stats.kills_by_type.iter().collect();
+    kills.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    if kills.is_empty() {
+        let _ = writeln!(text, "    (none)");
+    }
+    for (name, count) in kills {
+        let _ = writeln!(text, "    {:>4} {}", count, name);
+    }
+
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Achievements");
+    let mut unlocked = state.achievements.unlocked().peekable();
+    if unlocked.peek().is_none() {
+        let _ = writeln!(text, "    (none)");
+    }
+    for achievement in unlocked {
+        let _ = writeln!(text, "    {}: {}", achievement.name, achievement.description);
+    }
+
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Last messages");
+    let messages: Vec<_> = state.game.messages.iter().rev().take(MORGUE_MESSAGES).collect();
+    for (message, _) in messages.into_iter().rev() {
+        let _ = writeln!(text, "    {}", message);
+    }
+
+    text
+}
@@ -0,0 +1,259 @@
+use super::Object;
+
+use std::ops::{ Index, IndexMut };
+
+use serde::{ Serialize, Deserialize };
+
+// Stable handle to an entity in the store.
+// The generation changes whenever a slot is reused, so an old id can never point at a new entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    entity: Option<Object>,
+}
+
+// Holds every entity on the current level: the player, monsters, corpses, items and stairs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entities {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    player: EntityId,
+}
+
+impl Entities {
+    // Creates a new store, with the player as its first entity.
+    pub fn new(player: Object) -> Entities {
+        let mut entities = Entities {
+            slots: Vec::new(),
+            free: Vec::new(),
+            player: EntityId { index: 0, generation: 0 },
+        };
+        entities.player = entities.insert(player);
+        entities
+    }
+
+    // Adds an entity to the store, reusing an empty slot if there is one.
+    pub fn insert(&mut self, object: Object) -> EntityId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.generation += 1;
+                slot.entity = Some(object);
+                EntityId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, entity: Some(object) });
+                EntityId { index: (self.slots.len() - 1) as u32, generation: 0 }
+            },
+        }
+    }
+
+    // Takes an entity out of the store. Its id will no longer find anything.
+    pub fn remove(&mut self, id: EntityId) -> Option<Object> {
+        if id == self.player {
+            return None; // The player can never be removed.
+        }
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let removed = slot.entity.take();
+        if removed.is_some() {
+            self.free.push(id.index);
+        }
+        removed
+    }
+
+    // Takes out everything except for the player, so that the level can be put away and come back to.
+    // Entities are returned in id order, so putting them back keeps them acting in the same order.
+    pub fn take_level(&mut self) -> Vec<Object> {
+        let player = self.player;
+        self.ids()
+            .into_iter()
+            .filter(|id| *id != player)
+            .filter_map(|id| self.remove(id))
+            .collect()
+    }
+
+    // Removes everything except for the player, such as when moving to a new level.
+    pub fn clear_level(&mut self) {
+        let player = self.player;
+        for id in self.ids() {
+            if id != player {
+                self.remove(id);
+            }
+        }
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Object> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Object> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_mut())
+    }
+
+    // Borrows two different entities at once, such as an attacker and its target.
+    pub fn pair_mut(&mut self, a: EntityId, b: EntityId) -> Option<(&mut Object, &mut Object)> {
+        if a.index == b.index || self.get(a).is_none() || self.get(b).is_none() {
+            return None;
+        }
+        let (first, second) = if a.index < b.index { (a, b) } else { (b, a) };
+        let (left, right) = self.slots.split_at_mut(second.index as usize);
+        let first_obj = left[first.index as usize].entity.as_mut()?;
+        let second_obj = right[0].entity.as_mut()?;
+        if a.index < b.index {
+            Some((first_obj, second_obj))
+        } else {
+            Some((second_obj, first_obj))
+        }
+    }
+
+    pub fn player_id(&self) -> EntityId {
+        self.player
+    }
+
+    pub fn player(&self) -> &Object {
+        &self[self.player]
+    }
+
+    pub fn player_mut(&mut self) -> &mut Object {
+        let player = self.player;
+        &mut self[player]
+    }
+
+    // Iterates through every entity, alongside its id.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.entity.as_ref().map(|object| (EntityId { index: index as u32, generation: slot.generation }, object))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut Object)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            let generation = slot.generation;
+            slot.entity.as_mut().map(|object| (EntityId { index: index as u32, generation }, object))
+        })
+    }
+
+    // Collects the ids of every entity, so that the store can be changed while going through them.
+    pub fn ids(&self) -> Vec<EntityId> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+
+    // Everything standing on a tile.
+    pub fn at(&self, x: i32, y: i32) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.iter().filter(move |(_, object)| object.pos() == (x, y))
+    }
+
+    // Entities which can fight (the player, and living monsters).
+    pub fn with_fighter(&self) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.iter().filter(|(_, object)| object.fighter.is_some())
+    }
+
+    // Entities which act on their own each turn. Corpses lose their AI, so they are never included.
+    pub fn with_ai(&self) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.iter().filter(|(_, object)| object.ai.is_some())
+    }
+
+    // Entities which can be picked up.
+    pub fn with_item(&self) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.iter().filter(|(_, object)| object.item.is_some())
+    }
+
+    // Traps, whether or not the player has found them yet.
+    pub fn with_trap(&self) -> impl Iterator<Item = (EntityId, &Object)> {
+        self.iter().filter(|(_, object)| object.trap.is_some())
+    }
+}
+
+impl Index<EntityId> for Entities {
+    type Output = Object;
+
+    fn index(&self, id: EntityId) -> &Object {
+        self.get(id).expect("Entity id no longer exists")
+    }
+}
+
+impl IndexMut<EntityId> for Entities {
+    fn index_mut(&mut self, id: EntityId) -> &mut Object {
+        self.get_mut(id).expect("Entity id no longer exists")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Object {
+        let mut object = Object::new_player();
+        object.name = name.into();
+        object
+    }
+
+    #[test]
+    fn removed_ids_no_longer_find_anything() {
+        let mut entities = Entities::new(Object::new_player());
+        let id = entities.insert(named("Orc"));
+        assert_eq!(entities.remove(id).map(|object| object.name), Some("Orc".to_string()));
+        assert!(entities.get(id).is_none());
+        assert!(entities.remove(id).is_none());
+    }
+
+    #[test]
+    fn reused_slots_get_a_new_generation() {
+        let mut entities = Entities::new(Object::new_player());
+        let old = entities.insert(named("Orc"));
+        entities.remove(old);
+        let new = entities.insert(named("Troll"));
+
+        // The slot is reused, but the old id can't reach the new entity.
+        assert_eq!(new.index, old.index);
+        assert_ne!(new.generation, old.generation);
+        assert!(entities.get(old).is_none());
+        assert_eq!(entities[new].name, "Troll");
+    }
+
+    #[test]
+    fn player_is_never_removed() {
+        let mut entities = Entities::new(Object::new_player());
+        let player = entities.player_id();
+        assert!(entities.remove(player).is_none());
+        entities.insert(named("Orc"));
+        entities.clear_level();
+        assert_eq!(entities.ids(), vec![player]);
+    }
+
+    #[test]
+    fn take_level_keeps_id_order() {
+        let mut entities = Entities::new(Object::new_player());
+        for name in &["First", "Second", "Third"] {
+            entities.insert(named(name));
+        }
+        let names: Vec<_> = entities.take_level().into_iter().map(|object| object.name).collect();
+        assert_eq!(names, vec!["First", "Second", "Third"]);
+        assert_eq!(entities.ids(), vec![entities.player_id()]);
+    }
+
+    #[test]
+    fn pair_mut_needs_two_different_entities() {
+        let mut entities = Entities::new(Object::new_player());
+        let player = entities.player_id();
+        let orc = entities.insert(named("Orc"));
+        assert!(entities.pair_mut(player, player).is_none());
+        let (first, second) = entities.pair_mut(orc, player).unwrap();
+        assert_eq!((first.name.as_str(), second.name.as_str()), ("Orc", "Player"));
+    }
+}
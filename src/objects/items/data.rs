@@ -0,0 +1,246 @@
+use crate::environment::spawner::{ Transition, from_dungeon_level };
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::Color;
+
+// File the item data is read from. If it doesn't exist, the defaults built into the game are used.
+pub const ITEM_DATA_FILE: &str = "data/items.json";
+
+// The items shipped with the game.
+const DEFAULT_ITEM_DATA: &str = include_str!("../../../data/items.json");
+
+static ITEM_DATA: OnceLock<ItemData> = OnceLock::new();
+
+// How the player chooses what an item is used on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TargetMode {
+    // The item affects the player.
+    Player,
+    // The item picks the closest visible monster within range.
+    ClosestMonster { max_range: i32 },
+    // The player clicks on a tile, optionally within a range.
+    Tile { max_range: Option<f32>, prompt: String },
+}
+
+// Statuses that an item can put on a fighter.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StatusKind {
+    Confused,
+    Afraid,
+    Hasted,
+    Slowed,
+}
+
+// Stats that an item can permanently raise (or lower).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stat {
+    MaxHp,
+    Power,
+    Defense,
+}
+
+// A single thing that happens when an item is used. Items can have as many of these as they like.
+// Messages can contain "{target}" and "{amount}", which are filled in when the effect happens.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Effect {
+    // Heals each target.
+    Heal { amount: i32, message: Option<String> },
+    // Damages each target.
+    Damage { amount: i32, message: Option<String> },
+    // Damages every fighter within a radius of the target tile.
+    DamageInRadius {
+        amount: i32,
+        radius: i32,
+        #[serde(default)]
+        hits_player: bool,
+        message: Option<String>,
+        player_message: Option<String>,
+    },
+    // Puts a status on each target for a number of turns.
+    ApplyStatus { status: StatusKind, turns: i32, message: Option<String> },
+    // Changes a stat of each target.
+    // If "per_depth" is set, the amount is given once for every that many dungeon levels.
+    StatChange { stat: Stat, amount: i32, per_depth: Option<u32>, message: Option<String> },
+    // Unlocks every locked door next to the target tile. Items with this are also used up by walking into a locked door.
+    Unlock { message: Option<String> },
+}
+
+// Everything about an item: how it looks, where it spawns, how it is aimed and what it does.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemDef {
+    pub id: String,
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    pub spawn_chance: Vec<Transition>,
+    pub targeting: TargetMode,
+    pub use_message: Option<String>,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemData {
+    pub items: Vec<ItemDef>,
+}
+
+impl ItemDef {
+    // Whether walking into a locked door with this item will open it.
+    pub fn unlocks(&self) -> bool {
+        self.effects.iter().any(|effect| match effect {
+            Effect::Unlock { .. } => true,
+            _ => false,
+        })
+    }
+}
+
+impl ItemData {
+    // Reads item data from JSON, and checks that it can be used.
+    pub fn from_json(json: &str) -> Result<ItemData, Box<dyn Error>> {
+        let data = serde_json::from_str::<ItemData>(json)?;
+        data.validate()?;
+        Ok(data)
+    }
+
+    // Catches mistakes in the data up front, rather than when an item is used.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.items.is_empty() {
+            return Err("there must be at least one item".into());
+        }
+
+        let mut ids = HashSet::new();
+        for item in &self.items {
+            if !ids.insert(item.id.as_str()) {
+                return Err(format!("the item id \"{}\" is used more than once", item.id).into());
+            }
+            if item.name.trim().is_empty() {
+                return Err(format!("item \"{}\" needs a name", item.id).into());
+            }
+            if item.effects.is_empty() {
+                return Err(format!("item \"{}\" doesn't do anything", item.id).into());
+            }
+
+            match &item.targeting {
+                TargetMode::ClosestMonster { max_range } if *max_range <= 0 => {
+                    return Err(format!("item \"{}\" needs a range above 0", item.id).into());
+                },
+                TargetMode::Tile { max_range: Some(max_range), .. } if *max_range <= 0.0 => {
+                    return Err(format!("item \"{}\" needs a range above 0", item.id).into());
+                },
+                _ => {},
+            }
+
+            for effect in &item.effects {
+                match effect {
+                    Effect::DamageInRadius { radius, .. } if *radius < 0 => {
+                        return Err(format!("item \"{}\" has a negative radius", item.id).into());
+                    },
+                    Effect::ApplyStatus { turns, .. } if *turns <= 0 => {
+                        return Err(format!("item \"{}\" applies a status for no turns", item.id).into());
+                    },
+                    Effect::StatChange { per_depth: Some(0), .. } => {
+                        return Err(format!("item \"{}\" can't change a stat every 0 levels", item.id).into());
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        // Only the first floor has to have an item that can spawn, as rooms can go without items deeper down.
+        let first_floor: u32 = self.items.iter().map(|item| {
+            from_dungeon_level(&item.spawn_chance, 1)
+        }).sum();
+        if first_floor == 0 {
+            return Err("no item can spawn on the first dungeon level".into());
+        }
+
+        Ok(())
+    }
+
+    // Finds the definition for an item id.
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.items.iter().find(|item| item.id == id)
+    }
+}
+
+// Loads the item data at startup.
+// The file is used if it exists, otherwise the built in defaults are used. Either way, it is validated first.
+//
+// The file holds { "items": [...] }, where each item has:
+//   "id" (unique), "name", "glyph", "color" ({ "r", "g", "b" }),
+//   "spawn_chance": a list of { "level", "value" }, each weight applying from that dungeon level down,
+//   "targeting": { "type": "Player" }, { "type": "ClosestMonster", "max_range" },
+//     or { "type": "Tile", "max_range" (optional), "prompt" },
+//   "use_message" (optional), shown when the item is used,
+//   "effects": a list of effects, each with a "type" of Heal, Damage, DamageInRadius, ApplyStatus, StatChange or Unlock
+//     and the fields listed on Effect above. Effect messages can use {target} and {amount}.
+pub fn load_item_data(path: &str) -> Result<(), Box<dyn Error>> {
+    let data = if Path::new(path).exists() {
+        let json = fs::read_to_string(path)?;
+        ItemData::from_json(&json).map_err(|e| format!("{}: {}", path, e))?
+    } else {
+        ItemData::from_json(DEFAULT_ITEM_DATA)?
+    };
+
+    // If the data was somehow already loaded, the first copy is kept, so a run never changes part way through.
+    let _ = ITEM_DATA.set(data);
+    Ok(())
+}
+
+// The item data in use. Falls back to the built in defaults if nothing was loaded.
+pub fn item_data() -> &'static ItemData {
+    ITEM_DATA.get_or_init(|| {
+        ItemData::from_json(DEFAULT_ITEM_DATA).expect("The built in item data is invalid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The default items, changed in some way and turned back into JSON.
+    fn changed_defaults<F: FnOnce(&mut ItemData)>(change: F) -> String {
+        let mut data = ItemData::from_json(DEFAULT_ITEM_DATA).unwrap();
+        change(&mut data);
+        serde_json::to_string(&data).unwrap()
+    }
+
+    #[test]
+    fn default_items_are_valid() {
+        let data = ItemData::from_json(DEFAULT_ITEM_DATA).unwrap();
+        assert!(data.items.iter().any(|item| item.unlocks()));
+    }
+
+    #[test]
+    fn repeated_ids_are_rejected() {
+        let json = changed_defaults(|data| {
+            let copy = data.items[0].clone();
+            data.items.push(copy);
+        });
+        assert!(ItemData::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn items_that_do_nothing_are_rejected() {
+        let json = changed_defaults(|data| data.items[0].effects.clear());
+        assert!(ItemData::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn first_level_needs_an_item() {
+        let json = changed_defaults(|data| {
+            for item in data.items.iter_mut() {
+                item.spawn_chance = vec![Transition { level: 2, value: 10 }];
+            }
+        });
+        assert!(ItemData::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::environment::{ Game, in_map };
+use crate::environment::map::tiles::TileType;
+use crate::events::{ DamageSource, GameEvent };
+use crate::objects::Object;
+use crate::objects::entities::{ Entities, EntityId };
+use crate::objects::npc::SpeedStatus;
+use crate::objects::npc::ai::Ai;
+use super::data::{ ItemDef, TargetMode, Effect, StatusKind, Stat };
+use super::{ valid_target, UseResult };
+
+use tcod::colors::*;
+use tcod::map::Map as FovMap;
+
+// Fills in the "{target}" and "{amount}" parts of an effect message.
+fn fill_message(message: &str, target: &str, amount: i32) -> String {
+    message
+        .replace("{target}", target)
+        .replace("{amount}", &amount.to_string())
+}
+
+// Damages an entity, giving the player experience if they killed a monster.
+pub fn damage_entity(id: EntityId, amount: i32, source: DamageSource, game: &mut Game, entities: &mut Entities) {
+    if id == entities.player_id() {
+        Object::player_damage(amount, source, game, entities.player_mut());
+    } else {
+        let by_player = source == DamageSource::Player;
+        if let Some(exp) = entities[id].take_damage(amount, source, game) {
+            if by_player {
+                entities.player_mut().fighter.as_mut().unwrap().exp += exp;
+            }
+        }
+    }
+}
+
+// Uses an item, based entirely on its definition.
+// First works out what the item is aimed at, then runs each of its effects in order.
+pub fn apply_item(
+    def: &ItemDef,
+    target: Option<(i32, i32)>,
+    fov: &FovMap,
+    game: &mut Game,
+    entities: &mut Entities,
+) -> UseResult {
+    let player_id = entities.player_id();
+
+    // The tile the item is centred on, and the fighters it affects directly.
+    let (tile, targets) = match &def.targeting {
+        TargetMode::Player => (entities.player().pos(), vec![player_id]),
+        TargetMode::ClosestMonster { max_range } => {
+            match Object::closest_monster(fov, entities, *max_range) {
+                Some(monster_id) => (entities[monster_id].pos(), vec![monster_id]),
+                None => {
+                    game.messages.add("No enemy is close enough to strike.", RED);
+                    return UseResult::Cancelled;
+                },
+            }
+        },
+        TargetMode::Tile { max_range, .. } => {
+            match valid_target(target, fov, &game.map, entities.player(), *max_range) {
+                Some((x, y)) => {
+                    let on_tile = entities
+                        .at(x, y)
+                        .filter(|(_, object)| object.fighter.is_some())
+                        .map(|(id, _)| id)
+                        .collect();
+                    ((x, y), on_tile)
+                },
+                None => {
+                    game.messages.add("Nothing happens...", RED);
+                    return UseResult::Cancelled;
+                },
+            }
+        },
+    };
+
+    if let Some(message) = &def.use_message {
+        game.messages.add(message.clone(), def.color);
+    }
+
+    // The item is only used up if at least one of its effects actually did something.
+    let mut took_effect = false;
+    for effect in &def.effects {
+        took_effect |= apply_effect(effect, tile, &targets, def.color, game, entities);
+    }
+
+    if took_effect {
+        UseResult::UsedUp
+    } else {
+        UseResult::Cancelled
+    }
+}
+
+// Carries out a single effect. Returns whether it had any effect at all.
+fn apply_effect(
+    effect: &Effect,
+    (x, y): (i32, i32),
+    targets: &[EntityId],
+    color: Color,
+    game: &mut Game,
+    entities: &mut Entities,
+) -> bool {
+    let player_id = entities.player_id();
+    let mut took_effect = false;
+
+    match effect {
+        Effect::Heal { amount, message } => {
+            for &id in targets {
+                let at_full_health = entities[id].fighter.map_or(true, |f| f.hp >= f.max_hp);
+                if at_full_health {
+                    if id == player_id {
+                        game.messages.add("You are already at full health.", RED);
+                    }
+                    continue;
+                }
+                if let Some(message) = message {
+                    game.messages.add(fill_message(message, &entities[id].name, *amount), color);
+                }
+                entities[id].heal(*amount);
+                took_effect = true;
+            }
+        },
+
+        Effect::Damage { amount, message } => {
+            for &id in targets {
+                if let Some(message) = message {
+                    game.messages.add(fill_message(message, &entities[id].name, *amount), color);
+                }
+                damage_entity(id, *amount, DamageSource::Player, game, entities);
+                took_effect = true;
+            }
+        },
+
+        Effect::DamageInRadius { amount, radius, hits_player, message, player_message } => {
+            // Everything other than the player within the radius is hit.
+            let in_radius: Vec<_> = entities
+                .with_fighter()
+                .filter(|(id, object)| *id != player_id && object.distance(x, y) <= *radius as f32)
+                .map(|(id, _)| id)
+                .collect();
+            for id in in_radius {
+                if let Some(message) = message {
+                    game.messages.add(fill_message(message, &entities[id].name, *amount), color);
+                }
+                damage_entity(id, *amount, DamageSource::Player, game, entities);
+            }
+
+            // Then the player, if the effect can hurt them.
+            if *hits_player && entities.player().distance(x, y) <= *radius as f32 {
+                if let Some(message) = player_message {
+                    game.messages.add(fill_message(message, &entities.player().name, *amount), DARK_FLAME);
+                }
+                damage_entity(player_id, *amount, DamageSource::Player, game, entities);
+            }
+
+            // An explosion always goes off, even if nothing was caught in it.
+            took_effect = true;
+        },
+
+        Effect::ApplyStatus { status, turns, message } => {
+            for &id in targets {
+                let object = &mut entities[id];
+                match status {
+                    // Confusion and fear replace the AI, so only monsters can be affected.
+                    // The old AI is kept, so that the monster can go back to it when the status wears off.
+                    StatusKind::Confused | StatusKind::Afraid => {
+                        let old_ai = match object.ai.take() {
+                            Some(ai) => ai,
+                            None => continue,
+                        };
+                        let previous_ai = Box::new(old_ai);
+                        object.ai = Some(match status {
+                            StatusKind::Confused => Ai::Confused { previous_ai, num_turns: *turns },
+                            _ => Ai::Fear { previous_ai, num_turns: *turns },
+                        });
+                    },
+                    StatusKind::Hasted | StatusKind::Slowed => {
+                        let fighter = match object.fighter.as_mut() {
+                            Some(fighter) => fighter,
+                            None => continue,
+                        };
+                        fighter.status = Some(match status {
+                            StatusKind::Hasted => SpeedStatus::Hasted { num_turns: *turns },
+                            _ => SpeedStatus::Slowed { num_turns: *turns },
+                        });
+                    },
+                }
+                if let Some(message) = message {
+                    game.messages.add(fill_message(message, &entities[id].name, *turns), color);
+                }
+                took_effect = true;
+            }
+        },
+
+        Effect::StatChange { stat, amount, per_depth, message } => {
+            // Some upgrades get stronger the deeper they are used.
+            let value = amount * per_depth.map_or(1, |depth| (game.dungeon_level / depth) as i32);
+            for &id in targets {
+                let fighter = match entities[id].fighter.as_mut() {
+                    Some(fighter) => fighter,
+                    None => continue,
+                };
+                match stat {
+                    Stat::MaxHp => fighter.max_hp += value,
+                    Stat::Power => fighter.power += value,
+                    Stat::Defense => fighter.defense += value,
+                }
+                if let Some(message) = message {
+                    game.messages.add(fill_message(message, &entities[id].name, value), color);
+                }
+                took_effect = true;
+            }
+        },
+
+        Effect::Unlock { message } => {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let (door_x, door_y) = (x + dx, y + dy);
+                    if in_map(&game.map, door_x, door_y) &&
+                        game.map[door_x as usize][door_y as usize].tiletype == TileType::LockedDoor
+                    {
+                        game.map[door_x as usize][door_y as usize].set_door(TileType::OpenDoor);
+                        game.events.emit(GameEvent::DoorUnlocked { x: door_x, y: door_y });
+                        took_effect = true;
+                    }
+                }
+            }
+            match (took_effect, message) {
+                (true, Some(message)) => game.messages.add(message.clone(), color),
+                (false, _) => game.messages.add("There is no locked door nearby.", RED),
+                _ => {},
+            }
+        },
+    }
+
+    took_effect
+}
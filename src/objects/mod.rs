@@ -1,4 +1,5 @@
 use crate::environment::{ Game, Map };
+use crate::events::{ DamageSource, GameEvent };
 
 pub mod player;
 pub mod npc;
@@ -8,10 +9,19 @@ use npc::ai::*;
 pub mod items;
 use items::*;
 
+pub mod traps;
+use traps::Trap;
+
+pub mod terrain;
+
+pub mod entities;
+use entities::{ Entities, EntityId };
+
 use serde::{ Serialize, Deserialize };
 
 use tcod::colors::*;
 use tcod::console::*;
+use tcod::map::Map as FovMap;
 
 // Object struct definition.
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,15 +37,10 @@ pub struct Object {
     pub fighter: Option<Fighter>,
     pub ai: Option<Ai>,
     pub item: Option<Item>,
+    pub inventory: Option<Vec<Object>>,
     pub level: i32,
     pub always_visible: bool,
-}
-
-// Character definition
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Character {
-    pub object: Object,
-    pub inventory: Option<Vec<Object>>
+    pub trap: Option<Trap>,
 }
 
 // Item definition
@@ -47,13 +52,13 @@ impl Object {
     }
 
     // Checks to see if an object is meant to block other objects.
-    pub fn is_blocked(x: i32, y: i32, map: &Map, characters: &[Character]) -> bool {
+    pub fn is_blocked(x: i32, y: i32, map: &Map, entities: &Entities) -> bool {
         // First test the map tile
         if map[x as usize][y as usize].blocked {
             return true;
         }
         // Checks for any blocking objects
-        characters.iter().any(|character| character.object.blocks && character.object.pos() == (x, y))
+        entities.at(x, y).any(|(_, object)| object.blocks)
     }
 
     // Returns the x/y coordinates of the object.
@@ -67,20 +72,24 @@ impl Object {
         self.y = y;
     }
 
-    // Moves unit in a direction if the tile isn't blocked
-    pub fn move_by(id: usize, dx: i32, dy: i32, map: &Map, objects: &mut [Character]) {
-        let (x, y) = objects[id].object.pos();
-        if !Object::is_blocked(x + dx, y + dy, &map, objects) {
-            objects[id].object.set_pos(x + dx, y + dy);
+    // Moves unit in a direction if the tile isn't blocked, setting off any trap or terrain it steps on.
+    pub fn move_by(id: EntityId, dx: i32, dy: i32, fov: &FovMap, game: &mut Game, entities: &mut Entities) {
+        let (x, y) = entities[id].pos();
+        if !Object::is_blocked(x + dx, y + dy, &game.map, entities) {
+            entities[id].set_pos(x + dx, y + dy);
+            let seen = fov.is_in_fov(x + dx, y + dy);
+            Object::trigger_traps(id, seen, game, entities);
+            Object::enter_terrain(id, seen, game, entities);
         }
     }
 
     // Function to allow fighter-enabled objects to take damage
-    fn take_damage(&mut self, damage: i32, game: &mut Game) -> Option<i32> {
+    fn take_damage(&mut self, damage: i32, source: DamageSource, game: &mut Game) -> Option<i32> {
         // Apply damage if possible.
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
                 fighter.hp -= damage;
+                game.events.emit(GameEvent::Damaged { target: self.name.clone(), damage, is_player: false, source: source.clone() });
             }
         }
 
@@ -88,7 +97,7 @@ impl Object {
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.alive = false;
-                fighter.on_death.callback(self, game);
+                fighter.on_death.callback(self, source, game);
                 return Some(fighter.exp);
             }
         }
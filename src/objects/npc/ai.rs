@@ -1,12 +1,15 @@
-use crate::Tcod;
-use crate::environment::{ Game, Map };
-use super::{ Object, super::Character };
+use crate::environment::Game;
+use super::Object;
+use crate::objects::entities::{ Entities, EntityId };
+use crate::events::{ DamageSource, GameEvent };
+use crate::scheduler::{ MOVE_COST, ATTACK_COST, WAIT_COST };
 
 use rand::Rng;
 
 use serde::{ Serialize, Deserialize };
 
 use tcod::colors::*;
+use tcod::map::Map as FovMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Ai {
@@ -19,21 +22,34 @@ pub enum Ai {
         previous_ai: Box<Ai>,
         num_turns: i32,
     },
+    // Heading for where an alarm went off.
+    Alerted {
+        previous_ai: Box<Ai>,
+        x: i32,
+        y: i32,
+        num_turns: i32,
+    },
 }
 
 impl Object {
     // Moves object towards another object.
-    fn move_towards(id: usize, target_x: i32, target_y: i32, map: &Map, characters: &mut [Character]) {
+    fn move_towards(id: EntityId, target_x: i32, target_y: i32, fov: &FovMap, game: &mut Game, entities: &mut Entities) {
         // Vector from this object to the target, and the distance.
-        let dx = target_x - characters[id].object.x;
-        let dy = target_y - characters[id].object.y;
+        let dx = target_x - entities[id].x;
+        let dy = target_y - entities[id].y;
         let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
 
         // Normalize to length 1 while keeping direction.
         // Then round, and convert to an integer so movement stays to map grid.
         let dx = (dx as f32 / distance).round() as i32;
         let dy = (dy as f32 / distance).round() as i32;
-        Object::move_by(id, dx, dy, map, characters);
+
+        // Monsters won't walk into anything that would hurt them on purpose.
+        let (next_x, next_y) = (entities[id].x + dx, entities[id].y + dy);
+        if game.map[next_x as usize][next_y as usize].harmful() {
+            return;
+        }
+        Object::move_by(id, dx, dy, fov, game, entities);
     }
 
     // Calculates distance between object, and another object.
@@ -44,122 +60,144 @@ impl Object {
     }
 
     // Depending on the current AI status of the object, activates the relevant AI function.
-    pub fn ai_take_turn(monster_id: usize, tcod: &Tcod, game: &mut Game, characters: &mut Vec<Character>, player: &mut Object) {
+    // Returns the energy spent by whatever the monster chose to do.
+    pub fn ai_take_turn(monster_id: EntityId, fov: &FovMap, game: &mut Game, entities: &mut Entities) -> i32 {
         use Ai::*;
-        if let Some(ai) = characters[monster_id].object.ai.take() {
-            let new_ai = match ai {
-                Basic => Object::ai_basic(monster_id, tcod, game, characters, player),
-                Confused{previous_ai, num_turns} => Object::ai_confused(monster_id, tcod, game, characters, previous_ai, num_turns),
-                Fear{previous_ai, num_turns} => Object::ai_fear(monster_id, tcod, game, characters, previous_ai, num_turns),
-            };
-            characters[monster_id].object.ai = Some(new_ai);
+        match entities[monster_id].ai.take() {
+            Some(ai) => {
+                let (new_ai, cost) = match ai {
+                    Basic => Object::ai_basic(monster_id, fov, game, entities),
+                    Confused{previous_ai, num_turns} => Object::ai_confused(monster_id, fov, game, entities, previous_ai, num_turns),
+                    Fear{previous_ai, num_turns} => Object::ai_fear(monster_id, fov, game, entities, previous_ai, num_turns),
+                    Alerted{previous_ai, x, y, num_turns} => Object::ai_alerted(monster_id, fov, game, entities, previous_ai, (x, y), num_turns),
+                };
+                entities[monster_id].ai = Some(new_ai);
+                cost
+            },
+            None => WAIT_COST,
         }
     }
 
     // Because the AI state can change, the different AI types return an AI to insert into the object.
-    fn ai_basic(monster_id: usize, tcod: &Tcod, game: &mut Game, characters: &mut [Character], player: &mut Object) -> Ai {
+    // Alongside it, they return the energy cost of the action taken.
+    fn ai_basic(monster_id: EntityId, fov: &FovMap, game: &mut Game, entities: &mut Entities) -> (Ai, i32) {
         // A basic monster taking its turn normally.
         // If you can see it, it can see you too.
-        let (monster_x, monster_y) = characters[monster_id].object.pos();
-        if tcod.fov.is_in_fov(monster_x, monster_y) {
-            if characters[monster_id].object.distance_to(player) >= 2.0 {
+        let (monster_x, monster_y) = entities[monster_id].pos();
+        if fov.is_in_fov(monster_x, monster_y) {
+            let player_id = entities.player_id();
+            if entities[monster_id].distance_to(entities.player()) >= 2.0 {
                 // Moves towards player if far away.
-                let (player_x, player_y) = player.pos();
-                Object::move_towards(monster_id, player_x, player_y, &game.map, characters);
-            } else if player.fighter.map_or(false, |f| f.hp > 0) {
+                let (player_x, player_y) = entities.player().pos();
+                Object::move_towards(monster_id, player_x, player_y, fov, game, entities);
+                return (Ai::Basic, MOVE_COST);
+            } else if entities.player().fighter.map_or(false, |f| f.hp > 0) {
                 // Close enough to attack.
-                characters[monster_id].object.monster_attack(game, player);
+                if let Some((monster, player)) = entities.pair_mut(monster_id, player_id) {
+                    monster.monster_attack(game, player);
+                }
+                return (Ai::Basic, ATTACK_COST);
             }
         }
-        Ai::Basic
+        (Ai::Basic, WAIT_COST)
     }
 
     // Returns AI confused, until the confusion wears off, then it returns its previous AI.
     fn ai_confused(
-        monster_id: usize,
-        _tcod: &Tcod,
+        monster_id: EntityId,
+        fov: &FovMap,
         game: &mut Game,
-        characters: &mut [Character],
+        entities: &mut Entities,
         previous_ai: Box<Ai>,
         num_turns: i32
-    ) -> Ai {
+    ) -> (Ai, i32) {
         if num_turns >= 0 {
             // Still confused ...
             // Move in a random direction, and decrease the number of turns confused.
-            Object::move_by(
-                monster_id,
-                rand::thread_rng().gen_range(-1, 2),
-                rand::thread_rng().gen_range(-1, 2),
-                &game.map,
-                characters,
-            );
-            Ai::Confused {
+            let dx = game.rng.gen_range(-1, 2);
+            let dy = game.rng.gen_range(-1, 2);
+            Object::move_by(monster_id, dx, dy, fov, game, entities);
+            (Ai::Confused {
                 previous_ai: previous_ai,
                 num_turns: num_turns - 1,
-            }
+            }, MOVE_COST)
         } else {
             // Restore the previous AI, and delete this one.
             game.messages.add(
-                format!("The {} is no longer confused!", characters[monster_id].object.name),
+                format!("The {} is no longer confused!", entities[monster_id].name),
                 RED,
             );
-            *previous_ai
+            (*previous_ai, WAIT_COST)
         }
     }
 
     // Returns AI confused, until the confusion wears off, then it returns its previous AI.
     fn ai_fear(
-        monster_id: usize,
-        _tcod: &Tcod,
+        monster_id: EntityId,
+        _fov: &FovMap,
         game: &mut Game,
-        characters: &mut [Character],
+        entities: &mut Entities,
         previous_ai: Box<Ai>,
         num_turns: i32,
-    ) -> Ai {
+    ) -> (Ai, i32) {
         if num_turns >= 0 {
             // Still scared ...
             // Stay frozen
-            Ai::Fear {
+            (Ai::Fear {
                 previous_ai: previous_ai,
                 num_turns: num_turns - 1,
-            }
+            }, WAIT_COST)
         } else {
             // Restore the previous AI, and delete this one.
             game.messages.add(
-                format!("The {} is no longer scared!", characters[monster_id].object.name),
+                format!("The {} is no longer scared!", entities[monster_id].name),
                 RED,
             );
-            *previous_ai
+            (*previous_ai, WAIT_COST)
         }
     }
 
+    // Walks towards an alarm, until it gets there, gives up, or spots the player on the way.
+    fn ai_alerted(
+        monster_id: EntityId,
+        fov: &FovMap,
+        game: &mut Game,
+        entities: &mut Entities,
+        previous_ai: Box<Ai>,
+        (x, y): (i32, i32),
+        num_turns: i32,
+    ) -> (Ai, i32) {
+        let (monster_x, monster_y) = entities[monster_id].pos();
+        if fov.is_in_fov(monster_x, monster_y) || num_turns <= 0 || (monster_x, monster_y) == (x, y) {
+            return (*previous_ai, WAIT_COST);
+        }
+        Object::move_towards(monster_id, x, y, fov, game, entities);
+        (Ai::Alerted {
+            previous_ai: previous_ai,
+            x: x,
+            y: y,
+            num_turns: num_turns - 1,
+        }, MOVE_COST)
+    }
+
     // Just a simple attack on another object
     fn monster_attack(&self, game: &mut Game, mut other: &mut Object) {
-        let mut rng = rand::thread_rng();
-        let attack = (self.fighter.map_or(1, |f| f.power)) as f32 + rng.gen_range(-1.0, 1.0);
-        let defense = (other.fighter.map_or(1, |f| f.defense)) as f32 + rng.gen_range(-1.0, 1.0);
+        let attack = (self.fighter.map_or(1, |f| f.power)) as f32 + game.rng.gen_range(-1.0, 1.0);
+        let defense = (other.fighter.map_or(1, |f| f.defense)) as f32 + game.rng.gen_range(-1.0, 1.0);
         let mut level_mod = ((self.level - other.level) / 3) as f32;
         if level_mod <= 0.0 { level_mod = 1.0; }
 
         let damage = ((attack * level_mod) - defense).round() as i32;
+        game.events.emit(GameEvent::Attacked {
+            attacker: self.name.clone(),
+            target: other.name.clone(),
+            damage,
+            by_player: false,
+            color: self.color,
+        });
         if damage > 0 {
             // Target takes damage.
-            game.messages.add(
-                format!(
-                    "{} attacks {} dealing {} damage.",
-                    self.name, other.name, damage
-                ),
-                self.color,
-            );
-            Object::player_damage(damage, game, &mut other);
-        } else {
-            game.messages.add(
-                format!(
-                    "{} attacks {} but it has no effect!",
-                    self.name, other.name
-                ),
-                WHITE,
-            );
+            Object::player_damage(damage, DamageSource::Monster { name: self.name.clone() }, game, &mut other);
         }
     }
 
@@ -0,0 +1,247 @@
+use crate::environment::MapTheme;
+use crate::environment::spawner::{ Transition, from_dungeon_level };
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::Color;
+
+// File the monster data is read from. If it doesn't exist, the defaults built into the game are used.
+pub const MONSTER_DATA_FILE: &str = "data/monsters.json";
+
+// The monsters shipped with the game.
+const DEFAULT_MONSTER_DATA: &str = include_str!("../../../../data/monsters.json");
+
+// Every base monster and trait has one set of stats per tier (weak, medium, powerful).
+pub const TIERS: usize = 3;
+
+// Spawn chances are checked down to this depth, to make sure there is always a monster to pick.
+const MAX_CHECKED_DEPTH: u32 = 100;
+
+const THEMES: [MapTheme; 7] = [
+    MapTheme::Fire,
+    MapTheme::Nature,
+    MapTheme::Water,
+    MapTheme::Light,
+    MapTheme::Death,
+    MapTheme::Crystal,
+    MapTheme::Earth,
+];
+
+static MONSTER_DATA: OnceLock<MonsterData> = OnceLock::new();
+
+// Stats of a base monster at one tier.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterTier {
+    pub exp: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    pub speed: i32,
+}
+
+// A base monster, such as a lizard, before a trait is added onto it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterDef {
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    pub corpse_type: String,
+    pub spawn_chance: Vec<Transition>,
+    pub tiers: Vec<MonsterTier>,
+}
+
+// Bonuses given by a trait at one tier, along with the name prefix it gives the monster.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraitTier {
+    pub name: String,
+    pub exp: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+}
+
+// A trait, such as "fire", which changes a monster's stats, color and corpse.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraitDef {
+    pub theme: MapTheme,
+    pub color: Color,
+    pub corpse_type: String,
+    pub tiers: Vec<TraitTier>,
+}
+
+// How likely each trait is to show up on a map of a given theme.
+// Any trait not listed for a theme uses the default weight.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Affinity {
+    pub default_weight: u32,
+    pub themes: BTreeMap<MapTheme, BTreeMap<MapTheme, u32>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterData {
+    pub monsters: Vec<MonsterDef>,
+    pub traits: Vec<TraitDef>,
+    pub affinity: Affinity,
+}
+
+impl Affinity {
+    // Weight of a trait appearing on a map with the given theme.
+    pub fn weight(&self, map_theme: MapTheme, trait_theme: MapTheme) -> u32 {
+        self.themes
+            .get(&map_theme)
+            .and_then(|weights| weights.get(&trait_theme))
+            .cloned()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+impl MonsterData {
+    // Reads monster data from JSON, and checks that it can be used.
+    pub fn from_json(json: &str) -> Result<MonsterData, Box<dyn Error>> {
+        let data = serde_json::from_str::<MonsterData>(json)?;
+        data.validate()?;
+        Ok(data)
+    }
+
+    // Catches mistakes in the data up front, rather than part way through a run.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.monsters.is_empty() {
+            return Err("there must be at least one monster".into());
+        }
+
+        for monster in &self.monsters {
+            if monster.name.trim().is_empty() {
+                return Err("every monster needs a name".into());
+            }
+            if monster.tiers.len() != TIERS {
+                return Err(format!("monster \"{}\" has {} tiers, but needs {}", monster.name, monster.tiers.len(), TIERS).into());
+            }
+            for tier in &monster.tiers {
+                if tier.hp <= 0 || tier.speed <= 0 || tier.exp < 0 {
+                    return Err(format!("monster \"{}\" needs positive hp and speed, and exp of at least 0", monster.name).into());
+                }
+            }
+        }
+
+        // Every depth needs a monster with a chance to spawn, or the weighted choice has nothing to pick from.
+        for level in 1..=MAX_CHECKED_DEPTH {
+            let total: u32 = self.monsters.iter().map(|m| from_dungeon_level(&m.spawn_chance, level)).sum();
+            if total == 0 {
+                return Err(format!("no monster can spawn on dungeon level {}", level).into());
+            }
+        }
+
+        // Each theme needs exactly one trait.
+        for theme in THEMES.iter() {
+            let count = self.traits.iter().filter(|t| t.theme == *theme).count();
+            if count != 1 {
+                return Err(format!("there must be exactly one {:?} trait, but {} were found", theme, count).into());
+            }
+        }
+        for trait_def in &self.traits {
+            if trait_def.tiers.len() != TIERS {
+                return Err(format!("the {:?} trait has {} tiers, but needs {}", trait_def.theme, trait_def.tiers.len(), TIERS).into());
+            }
+        }
+
+        // Every map theme needs at least one trait with a chance to appear.
+        for map_theme in THEMES.iter() {
+            let total: u32 = self.traits.iter().map(|t| self.affinity.weight(*map_theme, t.theme)).sum();
+            if total == 0 {
+                return Err(format!("no trait can appear on a {:?} map", map_theme).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn trait_for(&self, theme: MapTheme) -> &TraitDef {
+        self.traits
+            .iter()
+            .find(|t| t.theme == theme)
+            .expect("Monster data was validated to have every trait")
+    }
+}
+
+// Loads the monster data at startup.
+// The file is used if it exists, otherwise the built in defaults are used. Either way, it is validated first.
+//
+// The file holds three things:
+//   "monsters": base monsters, each with a "name", "glyph", "color" ({ "r", "g", "b" }), "corpse_type",
+//     "spawn_chance" (a list of { "level", "value" }, each weight applying from that dungeon level down),
+//     and "tiers": three sets of { "exp", "hp", "defense", "power", "speed" }, from weakest to strongest.
+//   "traits": exactly one per map theme, each with a "theme", "color", "corpse_type",
+//     and three "tiers" of { "name", "exp", "hp", "defense", "power" } added onto the base monster.
+//   "affinity": a "default_weight" for each trait, and "themes", which maps a map theme to the weights
+//     of the traits that are more or less likely on it.
+pub fn load_monster_data(path: &str) -> Result<(), Box<dyn Error>> {
+    let data = if Path::new(path).exists() {
+        let json = fs::read_to_string(path)?;
+        MonsterData::from_json(&json).map_err(|e| format!("{}: {}", path, e))?
+    } else {
+        MonsterData::from_json(DEFAULT_MONSTER_DATA)?
+    };
+
+    // If the data was somehow already loaded, the first copy is kept, so a run never changes part way through.
+    let _ = MONSTER_DATA.set(data);
+    Ok(())
+}
+
+// The monster data in use. Falls back to the built in defaults if nothing was loaded.
+pub fn monster_data() -> &'static MonsterData {
+    MONSTER_DATA.get_or_init(|| {
+        MonsterData::from_json(DEFAULT_MONSTER_DATA).expect("The built in monster data is invalid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The default monsters, changed in some way and turned back into JSON.
+    fn changed_defaults<F: FnOnce(&mut MonsterData)>(change: F) -> String {
+        let mut data = MonsterData::from_json(DEFAULT_MONSTER_DATA).unwrap();
+        change(&mut data);
+        serde_json::to_string(&data).unwrap()
+    }
+
+    #[test]
+    fn default_monsters_are_valid() {
+        let data = MonsterData::from_json(DEFAULT_MONSTER_DATA).unwrap();
+        for theme in THEMES.iter() {
+            assert_eq!(data.trait_for(*theme).theme, *theme);
+        }
+    }
+
+    #[test]
+    fn monsters_need_every_tier() {
+        let json = changed_defaults(|data| {
+            data.monsters[0].tiers.pop();
+        });
+        assert!(MonsterData::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn traits_are_needed_for_every_theme() {
+        let json = changed_defaults(|data| {
+            data.traits.pop();
+        });
+        assert!(MonsterData::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn every_level_needs_a_monster() {
+        let json = changed_defaults(|data| {
+            for monster in data.monsters.iter_mut() {
+                monster.spawn_chance = vec![Transition { level: 3, value: 10 }];
+            }
+        });
+        assert!(MonsterData::from_json(&json).is_err());
+    }
+}
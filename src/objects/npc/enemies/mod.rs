@@ -1,21 +1,32 @@
-pub mod enemy_list;
-pub mod traits;
+pub mod data;
 
-use traits::get_trait;
-use enemy_list::get_monster;
+use data::{ monster_data, TIERS };
 
 use crate::environment::MapTheme;
-use crate::objects::Character;
+use crate::environment::spawner::from_dungeon_level;
+use crate::rng::GameRng;
 use super::ai::*;
 use super::*;
 
 use rand::Rng;
+use rand::distributions::{ IndependentSample, Weighted, WeightedChoice };
+
+// Trait definition.
+#[derive(Debug)]
+pub struct Trait {
+    pub name: String,
+    pub exp: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    pub color: Color,
+    pub corpse_type: String,
+}
 
 // Creates a new, empty, generic enemy
 impl Object {
-    fn new_enemy(x: i32, y: i32, char: char, color: Color, name: &str, blocks: bool, corpse_type: &str) -> Character {
-        Character {
-            object: Object {
+    fn new_enemy(x: i32, y: i32, char: char, color: Color, name: &str, blocks: bool, corpse_type: &str) -> Object {
+        Object {
             x: x,
             y: y,
             char: char,
@@ -27,18 +38,83 @@ impl Object {
             fighter: None,
             ai: Some(Ai::Basic),
             item: None,
+            inventory: None,
             level: 1,
             always_visible: false,
-            },
-            inventory: None,
+            trap: None,
         }
     }
 }
 
+// Turns a tier (1 to 3) into an index into the tier stats, treating anything higher as the strongest tier.
+fn tier_index(tier: i32) -> usize {
+    ((tier.max(1) - 1) as usize).min(TIERS - 1)
+}
+
+// Selects, generates, and returns a random base monster based on the depth level.
+// Which monsters can appear, and how often, comes from the monster data.
+pub fn get_monster(x: i32, y: i32, level: u32, tier: i32, rng: &mut GameRng) -> Object {
+    let data = monster_data();
+
+    let mut monster_chances: Vec<_> = data.monsters
+        .iter()
+        .enumerate()
+        .map(|(index, monster)| Weighted {
+            weight: from_dungeon_level(&monster.spawn_chance, level),
+            item: index,
+        })
+        .collect();
+    let monster_choice = WeightedChoice::new(&mut monster_chances);
+    let def = &data.monsters[monster_choice.ind_sample(rng)];
+
+    // The stats for the chosen tier are added into the monster.
+    let stats = &def.tiers[tier_index(tier)];
+    let mut monster = Object::new_enemy(x, y, def.glyph, def.color, &def.name, true, &def.corpse_type);
+    monster.fighter = Some(Fighter {
+        exp: stats.exp,
+        max_hp: stats.hp,
+        hp: stats.hp,
+        defense: stats.defense,
+        power: stats.power,
+        perception: 0,
+        speed: stats.speed,
+        energy: 0,
+        status: None,
+        on_death: DeathCallback::Monster,
+    });
+    monster
+}
+
+// Randomly selects, and returns a trait type.
+// Traits matching the map theme are far more likely, as set out in the affinity table of the monster data.
+pub fn get_trait(theme: MapTheme, tier: i32, rng: &mut GameRng) -> Trait {
+    let data = monster_data();
+
+    let mut trait_chances: Vec<_> = data.traits
+        .iter()
+        .map(|trait_def| Weighted {
+            weight: data.affinity.weight(theme, trait_def.theme),
+            item: trait_def.theme,
+        })
+        .collect();
+    let trait_choice = WeightedChoice::new(&mut trait_chances);
+    let trait_def = data.trait_for(trait_choice.ind_sample(rng));
+
+    let stats = &trait_def.tiers[tier_index(tier)];
+    Trait {
+        name: stats.name.clone(),
+        exp: stats.exp,
+        hp: stats.hp,
+        defense: stats.defense,
+        power: stats.power,
+        color: trait_def.color,
+        corpse_type: trait_def.corpse_type.clone(),
+    }
+}
+
 // Function to randomly assign attribute bonuses as a monster levels up.
-pub fn monster_level_up(mut fighter: &mut Fighter) {
-    let rng = rand::thread_rng().gen_range(0, 3);
-    match rng {
+pub fn monster_level_up(fighter: &mut Fighter, rng: &mut GameRng) {
+    match rng.gen_range(0, 3) {
         0 => {
             fighter.max_hp += 5;
             fighter.hp += 5;
@@ -50,19 +126,19 @@ pub fn monster_level_up(mut fighter: &mut Fighter) {
 }
 
 // Creates a new monster by pulling from random characteristics.
-pub fn generate_monster(x: i32, y: i32, tier: i32, level: u32, theme: MapTheme) -> Character {
+pub fn generate_monster(x: i32, y: i32, tier: i32, level: u32, theme: MapTheme, rng: &mut GameRng) -> Object {
 
     // Selects random base monster and trait.
-    let enemy_trait = get_trait(theme, tier);
-    let mut monster = get_monster(x, y, level, tier);
+    let enemy_trait = get_trait(theme, tier, rng);
+    let mut monster = get_monster(x, y, level, tier, rng);
 
     // Changes base monster variables to reflect the trait.
-    monster.object.name = format!("{}{}", enemy_trait.name, monster.object.name);
-    monster.object.corpse_type.push_str(&enemy_trait.corpse_type);
-    monster.object.color = enemy_trait.color;
+    monster.name = format!("{}{}", enemy_trait.name, monster.name);
+    monster.corpse_type.push_str(&enemy_trait.corpse_type);
+    monster.color = enemy_trait.color;
 
     // Adjust combat capabilities of the monster to reflect the trait.
-    monster.object.fighter.as_mut().map(|f| {
+    monster.fighter.as_mut().map(|f| {
         f.exp += enemy_trait.exp;
         f.max_hp += enemy_trait.hp;
         f.hp += enemy_trait.hp;
@@ -2,6 +2,7 @@ pub mod enemies;
 pub mod ai;
 
 use crate::environment::Game;
+use crate::events::{ DamageSource, GameEvent };
 use super::Object;
 
 use serde::{ Serialize, Deserialize };
@@ -16,9 +17,54 @@ pub struct Fighter {
     pub hp: i32,
     pub defense: i32,
     pub power: i32,
+    // Makes secret passages easier to notice.
+    pub perception: i32,
+    pub speed: i32,
+    pub energy: i32,
+    pub status: Option<SpeedStatus>,
     pub on_death: DeathCallback,
 }
 
+// Temporary effects which change how quickly a fighter gains energy.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpeedStatus {
+    Hasted { num_turns: i32 },
+    Slowed { num_turns: i32 },
+}
+
+impl Fighter {
+    // Speed after statuses are taken into account.
+    // Never drops below 1, so that a fighter can always act eventually.
+    pub fn effective_speed(&self) -> i32 {
+        let speed = match self.status {
+            Some(SpeedStatus::Hasted { .. }) => self.speed * 2,
+            Some(SpeedStatus::Slowed { .. }) => self.speed / 2,
+            None => self.speed,
+        };
+        speed.max(1)
+    }
+
+    // Counts down the current status by one turn.
+    // Returns the status if it has just worn off.
+    pub fn tick_status(&mut self) -> Option<SpeedStatus> {
+        let status = self.status?;
+        let remaining = match status {
+            SpeedStatus::Hasted { num_turns } => SpeedStatus::Hasted { num_turns: num_turns - 1 },
+            SpeedStatus::Slowed { num_turns } => SpeedStatus::Slowed { num_turns: num_turns - 1 },
+        };
+        match remaining {
+            SpeedStatus::Hasted { num_turns } | SpeedStatus::Slowed { num_turns } if num_turns <= 0 => {
+                self.status = None;
+                Some(status)
+            },
+            _ => {
+                self.status = Some(remaining);
+                None
+            },
+        }
+    }
+}
+
 // Allows for different death effects based on the enemy killed.
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DeathCallback {
@@ -29,30 +75,29 @@ pub enum DeathCallback {
 // Connects to the specific callback function of the object
 // Calls the relevant death function for said object.
 impl DeathCallback {
-    pub fn callback(self, object: &mut Object, game: &mut Game) {
-        let callback: fn(&mut Object, &mut Game) = match self {
+    pub fn callback(self, object: &mut Object, source: DamageSource, game: &mut Game) {
+        let callback: fn(&mut Object, DamageSource, &mut Game) = match self {
             DeathCallback::Player => Object::fake_player_death,
             DeathCallback::Monster => Object::monster_death,
         };
-        callback(object, game);
+        callback(object, source, game);
     }
 }
 
 impl Object {
     // Player death functions quite differently from other objects.
     // However, the player is still an object, so a dummy-function is used to fill that gap.
-    fn fake_player_death(_object: &mut Object, _game: &mut Game) {}
+    fn fake_player_death(_object: &mut Object, _source: DamageSource, _game: &mut Game) {}
 
-    fn monster_death(monster: &mut Object, game: &mut Game) {
+    fn monster_death(monster: &mut Object, source: DamageSource, game: &mut Game) {
         // Turns monster into a corpse.
         // No longer blocks, attacks, or moves.
-        game.messages.add(
-            format!(
-                "{} is dead! You gain {} experience points.",
-                monster.name,
-                monster.fighter.unwrap().exp
-            ),
-            DARK_RED);
+        game.events.emit(GameEvent::Died {
+            name: monster.name.clone(),
+            exp: monster.fighter.map_or(0, |f| f.exp),
+            is_player: false,
+            source,
+        });
         monster.color = DARK_RED;
         monster.blocks = false;
         monster.fighter = None;
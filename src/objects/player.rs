@@ -1,97 +1,224 @@
-use crate::{ LEVEL_UP_FACTOR, LEVEL_UP_BASE, LEVEL_SCREEN_WIDTH };
-use crate::Tcod;
-use crate::environment::{ Game };
+use crate::environment::{ Game, in_map };
+use crate::environment::map::tiles::TileType;
+use crate::state::LevelUpStat;
+use crate::rng::GameRng;
+use crate::events::{ DamageSource, GameEvent };
+use crate::scheduler::{ ACTION_THRESHOLD, NORMAL_SPEED, MOVE_COST, ATTACK_COST, DOOR_COST };
 
-use super::{ Object, Character };
+use super::Object;
+use super::entities::{ Entities, EntityId };
 use super::npc::{ Fighter, DeathCallback };
 use super::items::*;
-use crate::graphics::gui::menu::menu;
+use super::items::effects::apply_item;
 
-use std::collections::HashMap;
 use rand::Rng;
 
 use tcod::colors::*;
+use tcod::map::Map as FovMap;
+
+// Chance, out of 100, of bashing open a locked door, along with how much each point of power adds to it.
+const BASH_CHANCE: i32 = 10;
+const BASH_CHANCE_PER_POWER: i32 = 3;
+
+// Chance, out of 100, of noticing each secret passage next to the player, when searching or just walking by.
+// Each point of perception adds to it.
+const SEARCH_CHANCE: i32 = 30;
+const SEARCH_CHANCE_PER_PERCEPTION: i32 = 10;
+const PASSIVE_SEARCH_CHANCE: i32 = 5;
+const PASSIVE_SEARCH_CHANCE_PER_PERCEPTION: i32 = 3;
 
 impl Object {
     // Player constructor
-    pub fn new_player() -> Character {
-        Character {
-            object: Object {
-                x: 0,
-                y: 0,
-                char: '@',
-                color: WHITE,
-                name: "Player".into(),
-                blocks: true,
-                alive: true,
-                corpse_type: "'s bloody corpse".into(),
-                fighter: Some(Fighter {
-                    exp: 0,
-                    max_hp: 100,
-                    hp: 100,
-                    defense: 1,
-                    power: 4,
-                    on_death: DeathCallback::Player,
-                }),
-                ai: None,
-                item: None,
-                level: 1,
-                always_visible: false,
-            },
+    pub fn new_player() -> Object {
+        Object {
+            x: 0,
+            y: 0,
+            char: '@',
+            color: WHITE,
+            name: "Player".into(),
+            blocks: true,
+            alive: true,
+            corpse_type: "'s bloody corpse".into(),
+            fighter: Some(Fighter {
+                exp: 0,
+                max_hp: 100,
+                hp: 100,
+                defense: 1,
+                power: 4,
+                perception: 1,
+                speed: NORMAL_SPEED,
+                energy: ACTION_THRESHOLD, // The player always gets the first move.
+                status: None,
+                on_death: DeathCallback::Player,
+            }),
+            ai: None,
+            item: None,
             inventory: Some(Vec::new()),
+            level: 1,
+            always_visible: false,
+            trap: None,
         }
     }
 
     // Decides if the player object should move, or attack when inputs are entered.
-    pub fn player_move_or_attack(dx: i32, dy: i32, game: &mut Game, characters: &mut [Character], player: &mut Object) {
+    // Returns the energy the player spent doing so.
+    pub fn player_move_or_attack(dx: i32, dy: i32, game: &mut Game, entities: &mut Entities) -> i32 {
         // The coordinates the player is moving to / attacking
-        let x = player.x + dx;
-        let y = player.y + dy;
+        let player_id = entities.player_id();
+        let x = entities.player().x + dx;
+        let y = entities.player().y + dy;
 
         // Try to find an attackable object there
-        let target_id = characters.iter().position(|cha| cha.object.fighter.is_some() && cha.object.pos() == (x, y));
+        let target_id = entities
+            .at(x, y)
+            .find(|(id, object)| *id != player_id && object.fighter.is_some())
+            .map(|(id, _)| id);
 
         // Attack target if found, otherwise move
-        match target_id {
-            Some(target_id) => {
-                let damage = Object::player_attack(&mut characters[target_id].object, player);
+        match target_id.and_then(|target_id| entities.pair_mut(player_id, target_id)) {
+            Some((player, target)) => {
+                let damage = Object::player_attack(target, player, &mut game.rng);
+                game.events.emit(GameEvent::Attacked {
+                    attacker: player.name.clone(),
+                    target: target.name.clone(),
+                    damage,
+                    by_player: true,
+                    color: player.color,
+                });
+                // Target takes damage, and applies exp to player, if needed.
                 if damage > 0 {
-                    // Target takes damage.
-                    game.messages.add(
-                        format!(
-                            "{} attacks {} dealing {} damage.",
-                            player.name, characters[target_id].object.name, damage
-                        ),
-                        player.color,
-                    );
-                    // Applies exp to player, if needed.
-                    if let Some(exp) = characters[target_id].object.take_damage(damage, game) {
+                    if let Some(exp) = target.take_damage(damage, DamageSource::Player, game) {
                         player.fighter.as_mut().unwrap().exp += exp;
                     }
-                } else {
-                    // Attack was not powerful enough to do damage.
-                    game.messages.add(
-                        format!(
-                            "{} attacks {} but it has no effect!",
-                            player.name, characters[target_id].object.name
-                        ),
-                        WHITE,
-                    );
                 }
+                ATTACK_COST
             },
             // Moves player
             None => {
-                if !Object::is_blocked(x, y, &game.map, characters) {
-                    player.set_pos(x, y);
+                // Walking into a door tries to get through it instead.
+                if in_map(&game.map, x, y) {
+                    match game.map[x as usize][y as usize].tiletype {
+                        TileType::ClosedDoor => {
+                            game.map[x as usize][y as usize].set_door(TileType::OpenDoor);
+                            game.events.emit(GameEvent::DoorOpened { x, y });
+                            return DOOR_COST;
+                        },
+                        TileType::LockedDoor => return Object::player_force_door(x, y, game, entities),
+                        _ => {},
+                    }
+                }
+                if !Object::is_blocked(x, y, &game.map, entities) {
+                    entities.player_mut().set_pos(x, y);
+                    Object::trigger_traps(player_id, true, game, entities);
+                    Object::enter_terrain(player_id, true, game, entities);
+                }
+                MOVE_COST
+            }
+        }
+    }
+
+    // Gets through a locked door, with a key if the player has one, or by bashing it otherwise.
+    fn player_force_door(x: i32, y: i32, game: &mut Game, entities: &mut Entities) -> i32 {
+        let key_id = entities.player().inventory.as_ref().and_then(|inventory| {
+            inventory.iter().position(|object| {
+                object.item.as_ref().and_then(|item| item.def()).map_or(false, |def| def.unlocks())
+            })
+        });
+
+        match key_id {
+            // The key is used up in the lock.
+            Some(key_id) => {
+                let key = entities.player_mut().inventory.as_mut().unwrap().remove(key_id);
+                game.map[x as usize][y as usize].set_door(TileType::OpenDoor);
+                game.events.emit(GameEvent::DoorUnlocked { x, y });
+                if let Some(item) = key.item {
+                    game.events.emit(GameEvent::ItemUsed { item });
+                }
+                DOOR_COST
+            },
+            // Stronger players are more likely to break the lock.
+            None => {
+                let power = entities.player().fighter.map_or(0, |f| f.power);
+                let broke = game.rng.gen_range(0, 100) < BASH_CHANCE + power * BASH_CHANCE_PER_POWER;
+                if broke {
+                    game.map[x as usize][y as usize].set_door(TileType::OpenDoor);
+                }
+                game.events.emit(GameEvent::DoorBashed { x, y, broke });
+                ATTACK_COST
+            },
+        }
+    }
+
+    // Closes the first open door next to the player, as long as nothing is standing in it.
+    // Returns whether a door was closed.
+    pub fn player_close_door(game: &mut Game, entities: &Entities) -> bool {
+        let (player_x, player_y) = entities.player().pos();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (x, y) = (player_x + dx, player_y + dy);
+                if in_map(&game.map, x, y) &&
+                    game.map[x as usize][y as usize].tiletype == TileType::OpenDoor &&
+                    entities.at(x, y).next().is_none()
+                {
+                    game.map[x as usize][y as usize].set_door(TileType::ClosedDoor);
+                    game.events.emit(GameEvent::DoorClosed { x, y });
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Looks for secret passages and traps next to the player. Searching on purpose is much more likely to find them
+    // than the check made every turn. Returns how many were found.
+    pub fn player_search(searching: bool, game: &mut Game, entities: &mut Entities) -> usize {
+        let perception = entities.player().fighter.map_or(0, |f| f.perception);
+        let chance = match searching {
+            true => SEARCH_CHANCE + perception * SEARCH_CHANCE_PER_PERCEPTION,
+            false => PASSIVE_SEARCH_CHANCE + perception * PASSIVE_SEARCH_CHANCE_PER_PERCEPTION,
+        };
+
+        let (player_x, player_y) = entities.player().pos();
+        let mut found = 0;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (x, y) = (player_x + dx, player_y + dy);
+                if !in_map(&game.map, x, y) {
+                    continue;
+                }
+                let tile = game.map[x as usize][y as usize];
+                if tile.tiletype == TileType::SecretPath && !tile.found && game.rng.gen_range(0, 100) < chance {
+                    game.map[x as usize][y as usize].reveal(&game.palette);
+                    game.events.emit(GameEvent::SecretFound { x, y });
+                    found += 1;
                 }
             }
         }
+
+        // Hidden traps are spotted in the same way.
+        let hidden_traps: Vec<_> = entities
+            .with_trap()
+            .filter(|(_, object)| {
+                object.trap.map_or(false, |trap| trap.hidden) &&
+                    (object.x - player_x).abs() <= 1 && (object.y - player_y).abs() <= 1
+            })
+            .map(|(id, _)| id)
+            .collect();
+        for id in hidden_traps {
+            if game.rng.gen_range(0, 100) < chance {
+                if let Some(trap) = entities[id].trap.as_mut() {
+                    trap.hidden = false;
+                }
+                game.events.emit(GameEvent::TrapFound { trap: entities[id].name.clone() });
+                found += 1;
+            }
+        }
+        found
     }
 
     // Function to allow fighter-enabled objects to attack other fighter-enabled objects.
-    fn player_attack(target: &mut Object, player: &Object) -> i32{
+    fn player_attack(target: &mut Object, player: &Object, rng: &mut GameRng) -> i32{
         // Damage formula.
-        let mut rng = rand::thread_rng();
         let attack = (player.fighter.map_or(0, |f| f.power)) as f32 + rng.gen_range(-1.0, 1.0);
         let defense = (target.fighter.map_or(0, |f| f.defense)) as f32 + rng.gen_range(-1.0, 1.0);
         let mut level_mod = ((player.level - target.level) / 3) as f32;
@@ -101,11 +228,12 @@ impl Object {
         damage
     }
 
-    pub fn player_damage(damage: i32, game: &mut Game, player: &mut Object) {
+    pub fn player_damage(damage: i32, source: DamageSource, game: &mut Game, player: &mut Object) {
         // Apply damage if possible.
         if let Some(fighter) = player.fighter.as_mut() {
             if damage > 0 {
                 fighter.hp -= damage;
+                game.events.emit(GameEvent::Damaged { target: player.name.clone(), damage, is_player: true, source: source.clone() });
             }
         }
 
@@ -113,86 +241,65 @@ impl Object {
         if let Some(fighter) = player.fighter {
             if fighter.hp <= 0 {
                 player.alive = false;
-                Object::player_death(player, game)
+                Object::player_death(player, source, game)
             }
         }
     }
 
-    fn player_death(player: &mut Object, game: &mut Game) {
+    fn player_death(player: &mut Object, source: DamageSource, game: &mut Game) {
         // The game ended!
-        game.messages.add("You died, lmao!", RED);
+        game.events.emit(GameEvent::Died { name: player.name.clone(), exp: 0, is_player: true, source });
         player.char = '%';
         player.color = DARK_RED;
         player.name = format!("{}{}", player.name, player.corpse_type);
     }
 
-    pub fn level_up(tcod: &mut Tcod, game: &mut Game, player: &mut Object) {
-        let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
-
-        // Check if the player has enough exp to level up.
-        if player.fighter.as_ref().map_or(0, |f| f.exp) >= level_up_xp {
-            // Success - Level up!
-            player.level += 1;
-            game.messages.add(
-                format!(
-                    "Your power grows - You have reached level {}!",
-                    player.level
-                ),
-                GOLD,
-            );
-            let fighter = player.fighter.as_mut().unwrap();
-            let mut choice = None;
-            while choice.is_none() {
-                // Continuously requests for a choice to be made, until it is made.
-                choice = menu(
-                    "Level up! Choose a state to raise:\n",
-                    &[
-                        format!("Constitution (+20 HP, from {})", fighter.max_hp),
-                        format!("Strength (+1 Attack, from {})", fighter.power),
-                        format!("Agility (+1 Defense, from {})", fighter.defense),
-                    ],
-                    LEVEL_SCREEN_WIDTH,
-                    &mut tcod.root,
-                );
-            }
-            // Applies the level up bonus.
-            fighter.exp -= level_up_xp;
-            match choice.unwrap() {
-                0 => {
-                    fighter.max_hp += 20;
-                    fighter.hp += 20;
-                },
-                1 => {
-                    fighter.power += 1;
-                },
-                2 => {
-                    fighter.defense += 1;
-                },
-                _ => unreachable!(),
-            }
+    // Applies the level up bonus for the chosen stat.
+    // The front-end is responsible for asking the player which stat they want.
+    pub fn level_up(stat: LevelUpStat, level_up_xp: i32, game: &mut Game, player: &mut Object) {
+        // Success - Level up!
+        player.level += 1;
+        game.events.emit(GameEvent::LevelUp { level: player.level, stat });
+        let fighter = player.fighter.as_mut().unwrap();
+        fighter.exp -= level_up_xp;
+        match stat {
+            LevelUpStat::Constitution => {
+                fighter.max_hp += 20;
+                fighter.hp += 20;
+            },
+            LevelUpStat::Strength => {
+                fighter.power += 1;
+            },
+            LevelUpStat::Agility => {
+                fighter.defense += 1;
+            },
+            LevelUpStat::Perception => {
+                fighter.perception += 1;
+            },
         }
     }
 
     // Adds item to player's inventory, and removes from the map.
-    pub fn pick_item_up(object_id: i32, game: &mut Game, items: &mut HashMap<i32, Object>, player: &mut Character) {
-        match &mut player.inventory {
+    pub fn pick_item_up(item_id: EntityId, game: &mut Game, entities: &mut Entities) {
+        let inventory_len = entities.player().inventory.as_ref().map(|inventory| inventory.len());
+        match inventory_len {
             // Inventory is found on player
-            Some(inventory) => if inventory.len() >= 26 { // Inventory is at capacity, nothing happens.
+            Some(len) => if len >= 26 { // Inventory is at capacity, nothing happens.
                 game.messages.add(
                     format!("Your inventory is full!"),
                     RED,
                 );
             } else {
                 // Inventory has space. Item is pulled from floor.
-                let wrapped = items.remove(&object_id);
+                let wrapped = entities.remove(item_id);
                 match wrapped {
                     // If item is pulled successfully, it is inserted into the players inventory
                     Some(pick_up_item) => {
-                        game.messages.add(
-                            format!("You picked found a {}", pick_up_item.name), // Message is displayed as such.
-                            pick_up_item.color,
-                        );
-                        inventory.push(pick_up_item);
+                        game.events.emit(GameEvent::ItemPickedUp {
+                            name: pick_up_item.name.clone(),
+                            color: pick_up_item.color,
+                        });
+                        entities.player_mut().inventory.as_mut().unwrap().push(pick_up_item);
                     },
                     _ => (), // Otherwise, nothing happens.
                 }
@@ -207,39 +314,29 @@ impl Object {
 
     pub fn use_item(
         inventory_id: usize,
-        tcod: &mut Tcod,
+        target: Option<(i32, i32)>,
+        fov: &FovMap,
         game: &mut Game,
-        characters: &mut Vec<Character>,
-        player: &mut Character,
-        items: &mut HashMap<i32, Object>
+        entities: &mut Entities,
     ) {
-        match &mut player.inventory {
-            // Inventory is found on player.
-            Some(inventory) => {
-                // Finds item within the inventory
-                if let Some(item) = inventory[inventory_id].item {
-                    // Depending on the type of item, a variable is assigned a function.
-                    let on_use = match item {
-                        Item::Heal => Object::use_health_potion,
-                        Item::LightningBoltScroll => Object::use_lightning_bolt_scroll,
-                        Item::ConfusionScroll => Object::use_confusion_scroll,
-                        Item::FireballScroll => Object::use_fireball_scroll,
-                        Item::FearScroll => Object::use_fear_scroll,
-                        Item::HpUp => Object::use_health_up,
-                        Item::PowUp => Object::use_power_up,
-                        Item::DefUp => Object::use_defense_up,
-                    };
-                    // Triggers the relevant item usage function, and decides what to do depending on how the item works.
-                    match on_use(inventory_id, tcod, game, &mut player.object, characters, items) {
-                        // If the item is used successfully, and is to be erased afterwards.
-                        UseResult::UsedUp => {
-                            // Destroy after use, unless it was cancelled for some reason.
-                            inventory.remove(inventory_id);
-                        },
-                        // Item fails to be used.
-                        UseResult::Cancelled => {
-                            game.messages.add("Cancelled", WHITE);
-                        }
+        // Finds item within the inventory
+        let item = entities.player().inventory.as_ref().and_then(|inventory| inventory[inventory_id].item.clone());
+        // The item's definition says how it is aimed, and what it does.
+        let def = item.as_ref().and_then(|item| item.def());
+        match (item, def) {
+            // Inventory is found on player, and holds a usable item.
+            (Some(item), Some(def)) => {
+                // Applies the item's effects, and decides what to do depending on how the item works.
+                match apply_item(def, target, fov, game, entities) {
+                    // If the item is used successfully, and is to be erased afterwards.
+                    UseResult::UsedUp => {
+                        // Destroy after use, unless it was cancelled for some reason.
+                        entities.player_mut().inventory.as_mut().unwrap().remove(inventory_id);
+                        game.events.emit(GameEvent::ItemUsed { item });
+                    },
+                    // Item fails to be used.
+                    UseResult::Cancelled => {
+                        game.messages.add("Cancelled", WHITE);
                     }
                 }
             },
@@ -251,33 +348,23 @@ impl Object {
     pub fn drop_item(
         inventory_id: usize,
         game: &mut Game,
-        items: &mut HashMap<i32, Object>,
-        player: &mut Character
+        entities: &mut Entities,
     ) {
         // Finds player location so that the item appears on the same tile.
-        let (x, y) = player.object.pos();
+        let (x, y) = entities.player().pos();
 
         // Pull the inventory from the "Some" allowing access to the item.
-        match &mut player.inventory {
+        match entities.player_mut().inventory.as_mut() {
             Some(inventory) => {
                 // Removes item from inventory.
                 let mut item = inventory.remove(inventory_id);
 
                 // Sets item position to the player position.
                 item.set_pos(x, y);
-                game.messages.add(format!("You dropped a {}.", item.name), YELLOW);
+                game.events.emit(GameEvent::ItemDropped { name: item.name.clone() });
 
-                // Loops to find an empty key in the item hashmap.
-                let mut new_id = 1;
-                for _ in 0..items.len() {
-                    if items.contains_key(&new_id) {
-                        new_id += 1;
-                    } else {
-                        break;
-                    }
-                }
-                // Inserts the item into the hashmap with its new id.
-                items.insert(new_id, item);
+                // Puts the item back into the world.
+                entities.insert(item);
             },
             // Do nothing if the inventory is inaccessible.
             _ => (()),
@@ -285,15 +372,14 @@ impl Object {
     }
 
     // Find closest enemy, up to a max range, within the player FOV.
-    pub fn closest_monster(player: &Object, tcod: &Tcod, objects: &[Character], max_range: i32) -> Option<usize> {
+    pub fn closest_monster(fov: &FovMap, entities: &Entities, max_range: i32) -> Option<EntityId> {
         let mut closest_enemy = None;
         let mut closest_dist = (max_range + 1) as f32; // Start with slightly more than max range.
+        let player = entities.player();
 
-        for (id, character) in objects.iter().enumerate() {
-            let obj_ref = &character.object;
+        for (id, obj_ref) in entities.with_ai() {
             if obj_ref.fighter.is_some() &&
-            obj_ref.ai.is_some() &&
-            tcod.fov.is_in_fov(obj_ref.x, obj_ref.y) {
+            fov.is_in_fov(obj_ref.x, obj_ref.y) {
                 // Calculates distance between this object and player.
                 let dist = player.distance_to(obj_ref);
                 if dist < closest_dist {
@@ -0,0 +1,82 @@
+use crate::environment::Game;
+use crate::environment::map::tiles::TileType;
+use crate::events::DamageSource;
+use crate::objects::Object;
+use crate::objects::entities::{ Entities, EntityId };
+use crate::objects::items::effects::damage_entity;
+
+use tcod::colors::*;
+use tcod::map::Map as FovMap;
+
+// Base damage from lava or miasma, each time it hurts.
+const LAVA_DAMAGE: i32 = 6;
+const MIASMA_DAMAGE: i32 = 2;
+
+// Extra energy spent wading through deep water, or pushing through bramble.
+const WADE_COST: i32 = 100;
+const BRAMBLE_COST: i32 = 50;
+
+impl Object {
+    // Applies the effects of whatever terrain a fighter has just stepped onto.
+    // Only terrain the player can see is mentioned, and monsters it kills aren't put down to the player.
+    pub fn enter_terrain(id: EntityId, seen: bool, game: &mut Game, entities: &mut Entities) {
+        if !entities[id].alive {
+            return;
+        }
+        let (x, y) = entities[id].pos();
+        match game.map[x as usize][y as usize].tiletype {
+            TileType::Lava | TileType::Miasma => Object::burn_in_terrain(id, seen, game, entities),
+            // Slow going terrain costs extra energy, so whoever is in it gets fewer turns.
+            TileType::DeepWater => spend_energy(id, WADE_COST, entities),
+            TileType::Bramble => spend_energy(id, BRAMBLE_COST, entities),
+            _ => {},
+        }
+    }
+
+    // Hurts every fighter still standing in lava or miasma as a turn ends, so standing still in it is no escape.
+    pub fn terrain_turn(fov: &FovMap, game: &mut Game, entities: &mut Entities) {
+        let standing: Vec<_> = entities
+            .with_fighter()
+            .filter(|(_, object)| object.alive && game.map[object.x as usize][object.y as usize].harmful())
+            .map(|(id, object)| (id, fov.is_in_fov(object.x, object.y)))
+            .collect();
+        for (id, seen) in standing {
+            Object::burn_in_terrain(id, seen, game, entities);
+        }
+    }
+
+    // Damages a fighter standing in lava or miasma. Both get worse deeper down.
+    fn burn_in_terrain(id: EntityId, seen: bool, game: &mut Game, entities: &mut Entities) {
+        let (x, y) = entities[id].pos();
+        let is_player = id == entities.player_id();
+        let seen = seen || is_player;
+        let victim = match is_player {
+            true => "You are".to_string(),
+            false => format!("The {} is", entities[id].name),
+        };
+
+        match game.map[x as usize][y as usize].tiletype {
+            TileType::Lava => {
+                if seen {
+                    game.messages.add(format!("{} burned by the lava!", victim), ORANGE);
+                }
+                let source = DamageSource::Hazard { cause: "Burned to death in lava".into() };
+                damage_entity(id, LAVA_DAMAGE + game.dungeon_level as i32 / 2, source, game, entities);
+            },
+            TileType::Miasma => {
+                if seen {
+                    game.messages.add(format!("{} choked by the miasma!", victim), LIGHT_PURPLE);
+                }
+                let source = DamageSource::Hazard { cause: "Choked to death on miasma".into() };
+                damage_entity(id, MIASMA_DAMAGE + game.dungeon_level as i32 / 4, source, game, entities);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn spend_energy(id: EntityId, energy: i32, entities: &mut Entities) {
+    if let Some(fighter) = entities[id].fighter.as_mut() {
+        fighter.energy -= energy;
+    }
+}
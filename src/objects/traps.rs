@@ -0,0 +1,258 @@
+use crate::environment::{ Game, MapTheme, map_dimensions };
+use crate::events::{ DamageSource, GameEvent };
+use crate::objects::Object;
+use crate::objects::entities::{ Entities, EntityId };
+use crate::objects::items::effects::damage_entity;
+use crate::objects::npc::SpeedStatus;
+use crate::objects::npc::ai::Ai;
+
+use rand::Rng;
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::*;
+
+// Monsters within this distance of an alarm come to see what set it off.
+const ALARM_RADIUS: f32 = 15.0;
+
+// Turns an alarmed monster spends looking for the cause, before giving up.
+const ALARM_TURNS: i32 = 20;
+
+// Random tiles tried when looking for somewhere to teleport to.
+const TELEPORT_ATTEMPTS: u32 = 100;
+
+// Chance, out of 100, of disarming a trap, along with how much each point of perception adds to it.
+// Of the attempts that fail, the worst few set the trap off.
+const DISARM_CHANCE: i32 = 50;
+const DISARM_CHANCE_PER_PERCEPTION: i32 = 10;
+const SET_OFF_CHANCE: i32 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrapKind {
+    Pit,
+    Dart,
+    Alarm,
+    Teleport,
+    // Takes its form from the theme of the level it was made on.
+    Elemental { theme: MapTheme },
+}
+
+// A trap sits on a tile, and goes off whenever something walks onto it.
+// Hidden traps aren't drawn until the player finds them, or sees them go off.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub hidden: bool,
+}
+
+// How each elemental trap looks, and what it does. Some also slow down whatever they hit.
+struct Element {
+    name: &'static str,
+    color: Color,
+    message: &'static str,
+    damage: i32,
+    slows: bool,
+}
+
+fn element(theme: MapTheme) -> Element {
+    match theme {
+        MapTheme::Fire => Element { name: "Flame trap", color: FLAME, message: "A jet of fire engulfs {target}!", damage: 8, slows: false },
+        MapTheme::Nature => Element { name: "Thorn trap", color: DARK_GREEN, message: "Thorny vines lash at {target}!", damage: 4, slows: true },
+        MapTheme::Water => Element { name: "Frost trap", color: LIGHT_BLUE, message: "A freezing spray coats {target} in ice!", damage: 3, slows: true },
+        MapTheme::Light => Element { name: "Flash trap", color: LIGHT_YELLOW, message: "A searing flash of light burns {target}!", damage: 6, slows: false },
+        MapTheme::Death => Element { name: "Poison trap", color: PURPLE, message: "A cloud of poison chokes {target}!", damage: 6, slows: false },
+        MapTheme::Crystal => Element { name: "Shard trap", color: CYAN, message: "Shards of crystal burst out at {target}!", damage: 7, slows: false },
+        MapTheme::Earth => Element { name: "Rockfall trap", color: DARK_ORANGE, message: "Rocks tumble down onto {target}!", damage: 6, slows: false },
+    }
+}
+
+impl TrapKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TrapKind::Pit => "Pit trap",
+            TrapKind::Dart => "Dart trap",
+            TrapKind::Alarm => "Alarm trap",
+            TrapKind::Teleport => "Teleport trap",
+            TrapKind::Elemental { theme } => element(theme).name,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            TrapKind::Pit => DARKER_SEPIA,
+            TrapKind::Dart => LIGHT_GREY,
+            TrapKind::Alarm => LIGHT_RED,
+            TrapKind::Teleport => LIGHT_MAGENTA,
+            TrapKind::Elemental { theme } => element(theme).color,
+        }
+    }
+}
+
+impl Object {
+    // Trap constructor. Traps start out hidden.
+    pub fn new_trap(x: i32, y: i32, kind: TrapKind) -> Object {
+        Object {
+            x: x,
+            y: y,
+            char: '^',
+            color: kind.color(),
+            name: kind.name().into(),
+            blocks: false,
+            alive: false,
+            corpse_type: kind.name().into(),
+            fighter: None,
+            ai: None,
+            item: None,
+            inventory: None,
+            level: 1,
+            always_visible: true,
+            trap: Some(Trap { kind, hidden: true }),
+        }
+    }
+
+    // Sets off any trap on the tile a fighter has just stepped onto.
+    // Traps the player can't see going off stay hidden, and aren't mentioned.
+    pub fn trigger_traps(victim_id: EntityId, seen: bool, game: &mut Game, entities: &mut Entities) {
+        let (x, y) = entities[victim_id].pos();
+        let trap_id = entities.at(x, y).find(|(_, object)| object.trap.is_some()).map(|(id, _)| id);
+        if let Some(trap_id) = trap_id {
+            Object::spring_trap(trap_id, victim_id, seen, game, entities);
+        }
+    }
+
+    // Tries to disarm a trap the player knows about, on or next to them. Disarmed traps are removed.
+    // Returns false if there wasn't a trap to try.
+    pub fn player_disarm(game: &mut Game, entities: &mut Entities) -> bool {
+        let player_id = entities.player_id();
+        let (player_x, player_y) = entities.player().pos();
+        let trap_id = entities
+            .with_trap()
+            .filter(|(_, object)| {
+                object.trap.map_or(false, |trap| !trap.hidden) &&
+                    (object.x - player_x).abs() <= 1 && (object.y - player_y).abs() <= 1
+            })
+            .map(|(id, _)| id)
+            .min();
+        let trap_id = match trap_id {
+            Some(id) => id,
+            None => return false,
+        };
+
+        let perception = entities.player().fighter.map_or(0, |f| f.perception);
+        let roll = game.rng.gen_range(0, 100);
+        let trap = entities[trap_id].name.clone();
+        if roll < DISARM_CHANCE + perception * DISARM_CHANCE_PER_PERCEPTION {
+            entities.remove(trap_id);
+            game.events.emit(GameEvent::TrapDisarmed { trap });
+        } else if roll >= 100 - SET_OFF_CHANCE {
+            game.events.emit(GameEvent::TrapDisarmFailed { trap, set_off: true });
+            Object::spring_trap(trap_id, player_id, true, game, entities);
+        } else {
+            game.events.emit(GameEvent::TrapDisarmFailed { trap, set_off: false });
+        }
+        true
+    }
+
+    // Makes a trap go off on whatever is standing on it.
+    pub fn spring_trap(trap_id: EntityId, victim_id: EntityId, seen: bool, game: &mut Game, entities: &mut Entities) {
+        let kind = match entities[trap_id].trap {
+            Some(trap) => trap.kind,
+            None => return,
+        };
+        let is_player = victim_id == entities.player_id();
+        let seen = seen || is_player;
+        let victim = match is_player {
+            true => "you".to_string(),
+            false => format!("the {}", entities[victim_id].name),
+        };
+
+        if seen {
+            if let Some(trap) = entities[trap_id].trap.as_mut() {
+                trap.hidden = false;
+            }
+            game.events.emit(GameEvent::TrapTriggered { trap: kind.name().into(), target: entities[victim_id].name.clone(), is_player });
+        }
+
+        // Traps hit harder deeper down. Anything they kill isn't put down to the player.
+        let depth_bonus = game.dungeon_level as i32 / 2;
+        let source = || DamageSource::Hazard { cause: format!("Killed by a {}", kind.name().to_lowercase()) };
+        match kind {
+            TrapKind::Pit => {
+                if seen {
+                    game.messages.add(format!("The floor gives way beneath {}!", victim), ORANGE);
+                }
+                damage_entity(victim_id, 5 + depth_bonus, source(), game, entities);
+            },
+
+            TrapKind::Dart => {
+                if seen {
+                    game.messages.add(format!("A poisoned dart strikes {}!", victim), ORANGE);
+                }
+                slow(victim_id, 5, entities);
+                damage_entity(victim_id, 3 + depth_bonus / 2, source(), game, entities);
+            },
+
+            TrapKind::Alarm => {
+                match seen {
+                    true => game.messages.add("A loud alarm rings out!", LIGHT_RED),
+                    false => game.messages.add("You hear an alarm ringing in the distance.", LIGHT_RED),
+                }
+                alert_monsters(entities[trap_id].pos(), victim_id, entities);
+            },
+
+            TrapKind::Teleport => {
+                if seen {
+                    game.messages.add(format!("A flash of light whisks {} away!", victim), LIGHT_MAGENTA);
+                }
+                teleport(victim_id, game, entities);
+            },
+
+            TrapKind::Elemental { theme } => {
+                let element = element(theme);
+                if seen {
+                    game.messages.add(element.message.replace("{target}", &victim), element.color);
+                }
+                if element.slows {
+                    slow(victim_id, 8, entities);
+                }
+                damage_entity(victim_id, element.damage + depth_bonus, source(), game, entities);
+            },
+        }
+    }
+}
+
+fn slow(id: EntityId, turns: i32, entities: &mut Entities) {
+    if let Some(fighter) = entities[id].fighter.as_mut() {
+        fighter.status = Some(SpeedStatus::Slowed { num_turns: turns });
+    }
+}
+
+// Sends every calm monster in earshot towards the alarm. Confused or scared monsters are too busy to notice.
+fn alert_monsters((x, y): (i32, i32), victim_id: EntityId, entities: &mut Entities) {
+    let in_earshot: Vec<_> = entities
+        .with_ai()
+        .filter(|(id, object)| *id != victim_id && object.distance(x, y) <= ALARM_RADIUS)
+        .map(|(id, _)| id)
+        .collect();
+    for id in in_earshot {
+        let monster = &mut entities[id];
+        if let Some(Ai::Basic) = monster.ai {
+            monster.ai = Some(Ai::Alerted { previous_ai: Box::new(Ai::Basic), x, y, num_turns: ALARM_TURNS });
+        }
+    }
+}
+
+// Moves a fighter to a random open tile on the level.
+fn teleport(id: EntityId, game: &mut Game, entities: &mut Entities) {
+    let (map_width, map_height) = map_dimensions(&game.map);
+    for _ in 0..TELEPORT_ATTEMPTS {
+        let x = game.rng.gen_range(1, map_width - 1);
+        let y = game.rng.gen_range(1, map_height - 1);
+        if !Object::is_blocked(x, y, &game.map, entities) && !game.map[x as usize][y as usize].block_sight {
+            // Whatever it lands on takes effect, just as if it had walked there.
+            entities[id].set_pos(x, y);
+            Object::enter_terrain(id, false, game, entities);
+            return;
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::environment::{ Map, MAP_WIDTH, MAP_HEIGHT };
+use crate::environment::{ Map, map_dimensions };
 use crate::environment::map::tiles::{ Tile };
 
 #[derive(Debug)]
@@ -9,14 +9,16 @@ pub struct Broadfs {
 }
 
 impl Broadfs {
-    pub fn new() -> Broadfs {
+    pub fn new(map: &Map) -> Broadfs {
+        let (map_width, map_height) = map_dimensions(map);
+
         let mut bfs = Broadfs {
             nodes: Vec::new(),
             frontier: Vec::new(),
             path: Vec::new(),
         };
-        for x in 0..MAP_WIDTH {
-            for y in 0..MAP_HEIGHT {
+        for x in 0..map_width {
+            for y in 0..map_height {
                 bfs.nodes.push(Node::new(x as u32, y as u32));
             }
         }
@@ -113,6 +115,8 @@ impl Broadfs {
     }
 
     fn get_neighbors(&self, node: Node, map: &Map) -> Vec<(u32, u32)> {
+        let (map_width, map_height) = map_dimensions(map);
+
         let (x, y) = (node.x, node.y);
         let mut neighbors: Vec<(u32, u32)> = vec![];
 
@@ -126,7 +130,7 @@ impl Broadfs {
             }
         }
 
-        if (x + 1) < (MAP_WIDTH - 1) as u32 &&
+        if (x + 1) < (map_width - 1) as u32 &&
         !map[(x + 1) as usize][y as usize].wall {
             match self.get_node(node.x + 1, node.y) {
                 Some(neighbor) => if !neighbor.visited {
@@ -146,7 +150,7 @@ impl Broadfs {
             }
         }
 
-        if (y + 1) < (MAP_HEIGHT - 1) as u32 &&
+        if (y + 1) < (map_height - 1) as u32 &&
         !map[x as usize][(y + 1) as usize].wall {
             match self.get_node(node.x, node.y + 1) {
                 Some(neighbor) => if !neighbor.visited {
@@ -1,20 +1,32 @@
 pub mod bfs;
 
-use bfs::Broadfs;
+use crate::environment::{ Map, map_dimensions, in_map };
 
-use crate::objects::Object;
-use crate::environment::Map;
-use crate::environment::map::tiles::Tile;
-
-use tcod::colors::*;
+// Finds every tile that can be walked to from the start, moving in any of the eight directions.
+// Doors count as open ground, as even locked ones can be got through.
+// Returns a grid the same shape as the map, marking the reachable tiles.
+pub fn reachable_tiles(map: &Map, start: (i32, i32)) -> Vec<Vec<bool>> {
+    let (map_width, map_height) = map_dimensions(map);
+    let mut reached = vec![vec![false; map_height as usize]; map_width as usize];
+    if !in_map(map, start.0, start.1) {
+        return reached;
+    }
 
-pub fn remove_inaccessible_tiles(map: &mut Map, player: &Object, colors: &[Color; 7]) {
-    let mut bfs = Broadfs::new();
-    let (px, py) = player.pos();
-    bfs.search(map, (px as u32, py as u32) , None);
-    for node in &bfs.nodes {
-        if !node.visited && map[node.x as usize][node.y as usize].empty {
-            map[node.x as usize][node.y as usize] = Tile::wall(colors);
+    let mut frontier = vec![start];
+    reached[start.0 as usize][start.1 as usize] = true;
+    while let Some((x, y)) = frontier.pop() {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (next_x, next_y) = (x + dx, y + dy);
+                if in_map(map, next_x, next_y) &&
+                    !reached[next_x as usize][next_y as usize] &&
+                    (!map[next_x as usize][next_y as usize].blocked || map[next_x as usize][next_y as usize].is_door())
+                {
+                    reached[next_x as usize][next_y as usize] = true;
+                    frontier.push((next_x, next_y));
+                }
+            }
         }
     }
+    reached
 }
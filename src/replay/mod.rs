@@ -0,0 +1,126 @@
+use crate::state::{ GameState, PlayerCommand };
+use crate::events::{ GameEvent, Subscriber };
+use crate::graphics::gui::Messages;
+use crate::environment::RunOptions;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{ Read, Write };
+
+use serde::{ Serialize, Deserialize };
+
+// File the most recent run's replay is written to.
+pub const REPLAY_FILE: &str = "replay";
+
+// A small summary of the game state, taken after every command.
+// If a replay gives a different checkpoint than the one recorded, the run has diverged.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub dungeon_level: u32,
+    pub player_pos: (i32, i32),
+    pub player_hp: i32,
+    pub player_exp: i32,
+    pub monsters_alive: usize,
+    pub rng_state: [u32; 4],
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+}
+
+// A single recorded command, and what the game looked like after it was carried out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayStep {
+    pub command: PlayerCommand,
+    pub checkpoint: Checkpoint,
+}
+
+// Everything needed to play a run back: how it was started, and every command made since.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    // How the run was set up. Stored alongside the steps, so older replays with only a seed still load.
+    #[serde(flatten)]
+    pub options: RunOptions,
+    pub steps: Vec<ReplayStep>,
+    // Events seen since the last recorded step. They are stored with the next checkpoint.
+    #[serde(skip)]
+    pending_events: Vec<GameEvent>,
+}
+
+// Describes the first point at which a replay stopped matching its recording.
+#[derive(Debug)]
+pub struct Divergence {
+    pub step: usize,
+    pub expected: Checkpoint,
+    pub actual: Checkpoint,
+}
+
+impl Replay {
+    // Creates an empty replay for a run started with the given options.
+    pub fn new(options: RunOptions) -> Replay {
+        Replay {
+            options,
+            steps: Vec::new(),
+            pending_events: Vec::new(),
+        }
+    }
+
+    // Adds a command to the end of the replay, alongside the events it caused.
+    pub fn record(&mut self, command: PlayerCommand, mut checkpoint: Checkpoint) {
+        checkpoint.events = self.pending_events.drain(..).collect();
+        self.steps.push(ReplayStep { command, checkpoint });
+    }
+}
+
+// The replay listens to every event, so that a replayed run can be checked against what actually happened.
+impl Subscriber for Replay {
+    fn notify(&mut self, event: &GameEvent, _messages: &mut Messages) {
+        self.pending_events.push(event.clone());
+    }
+}
+
+// Writes a replay to a file as JSON.
+pub fn save_replay(replay: &Replay, path: &str) -> Result<(), Box<dyn Error>> {
+    let replay_data = serde_json::to_string(replay)?;
+    let mut file = File::create(path)?;
+    file.write_all(replay_data.as_bytes())?;
+    Ok(())
+}
+
+// Reads a replay back from a file.
+pub fn load_replay(path: &str) -> Result<Replay, Box<dyn Error>> {
+    let mut replay_data = String::new();
+    let mut file = File::open(path)?;
+    file.read_to_string(&mut replay_data)?;
+    let replay = serde_json::from_str::<Replay>(&replay_data)?;
+    Ok(replay)
+}
+
+// Plays a single step of a replay, and checks that the game ended up where the recording says it should.
+pub fn replay_step(state: &mut GameState, replay: &Replay, step: usize) -> Result<(), Divergence> {
+    let recorded = &replay.steps[step];
+    state.step(recorded.command);
+
+    // The re-run records its own replay as it goes, so its latest checkpoint is what actually happened.
+    let actual = match state.replay.steps.get(step) {
+        Some(replayed) => replayed.checkpoint.clone(),
+        None => state.checkpoint(),
+    };
+    if actual == recorded.checkpoint {
+        Ok(())
+    } else {
+        Err(Divergence {
+            step,
+            expected: recorded.checkpoint.clone(),
+            actual,
+        })
+    }
+}
+
+// Re-runs a whole replay without a window, stopping at the first divergence.
+// Returns the finished game state, so the caller can inspect how the run ended.
+pub fn run_replay(replay: &Replay) -> Result<GameState, Divergence> {
+    let mut state = GameState::new(replay.options, false);
+    for step in 0..replay.steps.len() {
+        replay_step(&mut state, replay, step)?;
+    }
+    Ok(state)
+}
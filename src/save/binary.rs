@@ -0,0 +1,284 @@
+use super::SaveError;
+
+use crate::environment::{ Map, map_dimensions };
+use crate::environment::map::tiles::{ Tile, TileType };
+
+use tcod::colors::Color;
+
+// Marks the start of a binary save body.
+const MAGIC: &[u8; 4] = b"RLSB";
+
+// Most colors the palette of one map can hold, as tiles refer to them with a single byte.
+const MAX_PALETTE_COLORS: usize = 256;
+
+// A binary save body is laid out as:
+//   the magic bytes, then the number of maps (2 bytes), with the current level first and any kept levels after it,
+//   then for each map:
+//     the map width and height (2 bytes each),
+//     the number of palette colors (2 bytes), then 3 bytes (red, green, blue) for each color,
+//     3 bytes for each tile, column by column: its type, then the palette index of its light and dark colors,
+//     one bit for each tile saying whether it has been explored, then one bit each for whether it has been found,
+//   and finally the length of the rest of the game (4 bytes), followed by the rest of the game as JSON.
+// Numbers are little endian.
+pub fn encode(maps: &[(&Map, &[Color; 7])], rest: &[u8]) -> Result<Vec<u8>, SaveError> {
+    let mut body = Vec::with_capacity(rest.len() + 64);
+    body.extend_from_slice(MAGIC);
+    body.extend_from_slice(&(maps.len() as u16).to_le_bytes());
+    for (map, palette) in maps {
+        encode_map(map, palette, &mut body)?;
+    }
+    body.extend_from_slice(&(rest.len() as u32).to_le_bytes());
+    body.extend_from_slice(rest);
+    Ok(body)
+}
+
+fn encode_map(map: &Map, palette: &[Color; 7], body: &mut Vec<u8>) -> Result<(), SaveError> {
+    let (map_width, map_height) = map_dimensions(map);
+    let tiles = map.iter().flat_map(|column| column.iter());
+
+    // The palette starts with the colors the level was generated from.
+    // Anything else on the map (such as darkened tiles) is added to the end as it is found.
+    let mut colors: Vec<Color> = palette.to_vec();
+    let mut packed_tiles = Vec::with_capacity((map_width * map_height * 3) as usize);
+    for tile in tiles.clone() {
+        packed_tiles.push(type_code(tile.tiletype));
+        packed_tiles.push(palette_index(&mut colors, tile.color_light)?);
+        packed_tiles.push(palette_index(&mut colors, tile.color_dark)?);
+    }
+
+    body.extend_from_slice(&(map_width as u16).to_le_bytes());
+    body.extend_from_slice(&(map_height as u16).to_le_bytes());
+    body.extend_from_slice(&(colors.len() as u16).to_le_bytes());
+    for color in &colors {
+        body.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+    body.extend_from_slice(&packed_tiles);
+    body.extend_from_slice(&pack_bits(tiles.clone().map(|tile| tile.explored)));
+    body.extend_from_slice(&pack_bits(tiles.map(|tile| tile.found)));
+    Ok(())
+}
+
+// Unpacks a binary save body into its maps, and the JSON holding the rest of the game.
+pub fn decode(body: &[u8]) -> Result<(Vec<Map>, &[u8]), SaveError> {
+    let mut reader = Reader { bytes: body };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(SaveError::Corrupt("the binary save doesn't start with the expected marker".into()));
+    }
+
+    let map_count = reader.u16()? as usize;
+    let mut maps = Vec::with_capacity(map_count);
+    for _ in 0..map_count {
+        maps.push(decode_map(&mut reader)?);
+    }
+
+    let rest_length = reader.u32()? as usize;
+    let rest = reader.take(rest_length)?;
+    Ok((maps, rest))
+}
+
+fn decode_map(reader: &mut Reader) -> Result<Map, SaveError> {
+    let map_width = reader.u16()? as usize;
+    let map_height = reader.u16()? as usize;
+    let color_count = reader.u16()? as usize;
+    let colors: Vec<Color> = reader.take(color_count * 3)?
+        .chunks(3)
+        .map(|rgb| Color { r: rgb[0], g: rgb[1], b: rgb[2] })
+        .collect();
+    let color = |index: u8| {
+        colors.get(index as usize).copied().ok_or_else(|| SaveError::Corrupt("a tile has a color outside the palette".into()))
+    };
+
+    let tile_count = map_width * map_height;
+    let packed_tiles = reader.take(tile_count * 3)?;
+    let bit_bytes = (tile_count + 7) / 8;
+    let explored = reader.take(bit_bytes)?;
+    let found = reader.take(bit_bytes)?;
+
+    let mut map = Vec::with_capacity(map_width);
+    for x in 0..map_width {
+        let mut column = Vec::with_capacity(map_height);
+        for y in 0..map_height {
+            let index = x * map_height + y;
+            let packed = &packed_tiles[index * 3..index * 3 + 3];
+            let mut tile = Tile::from_type(tile_type(packed[0])?, color(packed[1])?, color(packed[2])?);
+            tile.explored = bit(explored, index);
+            tile.found = bit(found, index);
+            // Secret passages that have been found can be seen through.
+            if tile.found && tile.tiletype == TileType::SecretPath {
+                tile.block_sight = false;
+            }
+            column.push(tile);
+        }
+        map.push(column);
+    }
+    Ok(map)
+}
+
+// Finds a color in the palette, adding it if it isn't there yet.
+fn palette_index(colors: &mut Vec<Color>, color: Color) -> Result<u8, SaveError> {
+    let index = match colors.iter().position(|known| *known == color) {
+        Some(index) => index,
+        None => {
+            colors.push(color);
+            colors.len() - 1
+        },
+    };
+    if index >= MAX_PALETTE_COLORS {
+        return Err(SaveError::Encoding(format!("the map uses more than {} colors", MAX_PALETTE_COLORS)));
+    }
+    Ok(index as u8)
+}
+
+fn type_code(tiletype: TileType) -> u8 {
+    match tiletype {
+        TileType::Empty => 0,
+        TileType::Wall => 1,
+        TileType::SecretPath => 2,
+        TileType::Debug => 3,
+        TileType::OpenDoor => 4,
+        TileType::ClosedDoor => 5,
+        TileType::LockedDoor => 6,
+        TileType::Lava => 7,
+        TileType::DeepWater => 8,
+        TileType::Bramble => 9,
+        TileType::Crystal => 10,
+        TileType::Miasma => 11,
+    }
+}
+
+fn tile_type(code: u8) -> Result<TileType, SaveError> {
+    match code {
+        0 => Ok(TileType::Empty),
+        1 => Ok(TileType::Wall),
+        2 => Ok(TileType::SecretPath),
+        3 => Ok(TileType::Debug),
+        4 => Ok(TileType::OpenDoor),
+        5 => Ok(TileType::ClosedDoor),
+        6 => Ok(TileType::LockedDoor),
+        7 => Ok(TileType::Lava),
+        8 => Ok(TileType::DeepWater),
+        9 => Ok(TileType::Bramble),
+        10 => Ok(TileType::Crystal),
+        11 => Ok(TileType::Miasma),
+        _ => Err(SaveError::Corrupt(format!("unknown tile type {}", code))),
+    }
+}
+
+// Packs a list of flags into bytes, eight to a byte.
+fn pack_bits<I: Iterator<Item = bool>>(flags: I) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (index, flag) in flags.enumerate() {
+        if index % 8 == 0 {
+            bytes.push(0);
+        }
+        if flag {
+            *bytes.last_mut().unwrap() |= 1 << (index % 8);
+        }
+    }
+    bytes
+}
+
+fn bit(bytes: &[u8], index: usize) -> bool {
+    bytes[index / 8] & (1 << (index % 8)) != 0
+}
+
+// Reads through a binary body a piece at a time, reporting a save that ends too soon.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], SaveError> {
+        if count > self.bytes.len() {
+            return Err(SaveError::Corrupt("the binary save ends too soon".into()));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, SaveError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SaveError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tcod::colors::*;
+
+    const PALETTE: [Color; 7] = [BLACK, WHITE, GREY, DARK_GREY, RED, GREEN, BLUE];
+
+    // Everything about a tile that a binary save keeps.
+    fn summary(map: &Map) -> Vec<(TileType, Color, Color, bool, bool, bool)> {
+        map.iter()
+            .flat_map(|column| column.iter())
+            .map(|tile| (tile.tiletype, tile.color_light, tile.color_dark, tile.explored, tile.found, tile.block_sight))
+            .collect()
+    }
+
+    fn test_map(width: usize, height: usize) -> Map {
+        let mut map = vec![vec![Tile::from_type(TileType::Empty, GREY, DARK_GREY); height]; width];
+        map[0][0] = Tile::from_type(TileType::Wall, WHITE, BLACK);
+        map[1][2] = Tile::from_type(TileType::SecretPath, WHITE, BLACK);
+        map[1][2].found = true;
+        map[1][2].block_sight = false;
+        map[2][1] = Tile::from_type(TileType::Lava, RED, ORANGE); // Orange isn't in the palette.
+        map[2][2].explored = true;
+        map
+    }
+
+    #[test]
+    fn maps_survive_a_round_trip() {
+        let (first, second) = (test_map(3, 4), test_map(5, 3));
+        let body = encode(&[(&first, &PALETTE), (&second, &PALETTE)], b"{\"rest\":true}").unwrap();
+        let (maps, rest) = decode(&body).unwrap();
+        assert_eq!(maps.len(), 2);
+        assert_eq!(summary(&maps[0]), summary(&first));
+        assert_eq!(summary(&maps[1]), summary(&second));
+        assert_eq!(map_dimensions(&maps[1]), (5, 3));
+        assert_eq!(rest, b"{\"rest\":true}");
+    }
+
+    #[test]
+    fn truncated_bodies_are_corrupt() {
+        let map = test_map(3, 4);
+        let body = encode(&[(&map, &PALETTE)], b"{}").unwrap();
+        for length in 0..body.len() {
+            match decode(&body[..length]) {
+                Err(SaveError::Corrupt(_)) => {},
+                _ => panic!("a body cut off after {} bytes was accepted", length),
+            }
+        }
+    }
+
+    #[test]
+    fn bodies_need_the_marker() {
+        let mut body = encode(&[], b"{}").unwrap();
+        body[0] = b'X';
+        match decode(&body) {
+            Err(SaveError::Corrupt(_)) => {},
+            _ => panic!("a body without the marker was accepted"),
+        }
+    }
+
+    #[test]
+    fn unknown_palette_indexes_are_corrupt() {
+        let map = vec![vec![Tile::from_type(TileType::Wall, WHITE, BLACK)]];
+        let mut body = encode(&[(&map, &PALETTE)], b"{}").unwrap();
+        // Header, map count, size and palette, then the tile's type and its light color index.
+        let light_index = MAGIC.len() + 2 + 6 + PALETTE.len() * 3 + 1;
+        body[light_index] = 200;
+        match decode(&body) {
+            Err(SaveError::Corrupt(_)) => {},
+            _ => panic!("a color outside the palette was accepted"),
+        }
+    }
+}
@@ -0,0 +1,470 @@
+pub mod slots;
+pub mod binary;
+
+use crate::environment::Game;
+use crate::environment::map::recorder::MapRecorder;
+use crate::objects::entities::Entities;
+use crate::replay::Replay;
+use crate::events::stats::RunStats;
+use crate::events::achievements::Achievements;
+use crate::state::GameState;
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+
+// Version of the save layout written by this build.
+// Bump it whenever the saved data changes shape, and add a migration from the previous version below.
+// Saves from before save slots (the single headerless "savegame" file) are too different to upgrade, and aren't read.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+// Version of the game itself, kept in the header so it is clear which build wrote a save.
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Each migration turns a save body of one version into the next. The first one will upgrade version 1 to 2, and so on.
+const MIGRATIONS: [fn(Value) -> Result<Value, SaveError>; 0] = [];
+
+// How the body of a save is written.
+// JSON is easy to read while debugging. Binary packs the map tightly, which makes for much smaller saves.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaveEncoding {
+    Json,
+    Binary,
+}
+
+impl Default for SaveEncoding {
+    fn default() -> SaveEncoding {
+        SaveEncoding::Json
+    }
+}
+
+// Written on the first line of every save file. The saved game follows on the next line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveHeader {
+    pub format_version: u32,
+    pub game_version: String,
+    // Checksum of everything after the header, to catch files that were cut short or edited.
+    pub checksum: String,
+    pub encoding: SaveEncoding,
+    // A summary of the run, so save slots can be listed without loading every game.
+    pub metadata: Option<SaveMetadata>,
+}
+
+// What the slot menu shows about a save.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    pub character_level: i32,
+    pub dungeon_level: u32,
+    pub turns: u32,
+    // Seconds since 1970, when the game was saved.
+    pub timestamp: u64,
+    pub seed: u64,
+}
+
+impl SaveMetadata {
+    // Summarises a game that is about to be saved.
+    pub fn from_state(state: &GameState) -> SaveMetadata {
+        SaveMetadata {
+            character_level: state.entities.player().level,
+            dungeon_level: state.game.dungeon_level,
+            turns: state.stats.turns,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            seed: state.game.seed,
+        }
+    }
+}
+
+// Everything kept in a save.
+// The replay is saved alongside the game, so that a continued run can still be replayed from the start.
+// Run stats and achievement progress are also kept, so they carry on where they left off.
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    pub game: Game,
+    pub entities: Entities,
+    pub replay: Replay,
+    pub stats: RunStats,
+    pub achievements: Achievements,
+}
+
+impl SaveData {
+    fn borrowed(&self) -> SaveDataRef<'_> {
+        SaveDataRef {
+            game: &self.game,
+            entities: &self.entities,
+            replay: &self.replay,
+            stats: &self.stats,
+            achievements: &self.achievements,
+        }
+    }
+}
+
+// The same as SaveData, but borrowed from the game state, so saving doesn't need a copy of everything.
+#[derive(Serialize)]
+struct SaveDataRef<'a> {
+    game: &'a Game,
+    entities: &'a Entities,
+    replay: &'a Replay,
+    stats: &'a RunStats,
+    achievements: &'a Achievements,
+}
+
+// Everything that can go wrong with a save, so the player can be told what actually happened.
+#[derive(Debug)]
+pub enum SaveError {
+    Missing(String),
+    Io(io::Error),
+    Corrupt(String),
+    ChecksumMismatch,
+    TooNew { found: u32, game_version: String },
+    // Saves from before save slots, which can't be turned into anything this version uses.
+    Unsupported,
+    Encoding(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Missing(path) => write!(f, "There is no saved game in {}.", path),
+            SaveError::Io(e) => write!(f, "The save could not be read or written: {}", e),
+            SaveError::Corrupt(reason) => write!(f, "The save is corrupt: {}", reason),
+            SaveError::ChecksumMismatch => write!(f, "The save is corrupt: it has been cut short or changed."),
+            SaveError::TooNew { found, game_version } => write!(
+                f,
+                "The save was made by a newer version of the game ({}, save format {}). This version reads up to format {}.",
+                game_version, found, SAVE_FORMAT_VERSION,
+            ),
+            SaveError::Unsupported => write!(
+                f,
+                "The save was made before save slots were added, and can't be loaded by this version. Start a new game instead.",
+            ),
+            SaveError::Encoding(reason) => write!(f, "The save could not be written: {}", reason),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> SaveError {
+        SaveError::Corrupt(e.to_string())
+    }
+}
+
+// 64 bit FNV-1a hash. Not secure, but plenty to spot a damaged file.
+fn checksum(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// Writes the game to a save file, with a header in front of it.
+// The save directory is created if needed, so the first save into a new slot works.
+// The file is replaced in one step, so a crash while saving leaves the previous save as it was.
+pub fn save_game<P: AsRef<Path>>(state: &GameState, path: P, encoding: SaveEncoding) -> Result<(), SaveError> {
+    let data = SaveDataRef {
+        game: &state.game,
+        entities: &state.entities,
+        replay: &state.replay,
+        stats: &state.stats,
+        achievements: &state.achievements,
+    };
+    write_save(&data, Some(SaveMetadata::from_state(state)), path.as_ref(), encoding)
+}
+
+fn write_save(
+    data: &SaveDataRef,
+    metadata: Option<SaveMetadata>,
+    path: &Path,
+    encoding: SaveEncoding,
+) -> Result<(), SaveError> {
+    let save_data = encode_save(data, metadata, encoding)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    write_atomic(path, &save_data)?;
+    Ok(())
+}
+
+// Turns the game into the contents of a save file: the header line, followed by the body.
+fn encode_save(data: &SaveDataRef, metadata: Option<SaveMetadata>, encoding: SaveEncoding) -> Result<Vec<u8>, SaveError> {
+    let body = match encoding {
+        SaveEncoding::Json => serde_json::to_vec(data)?,
+        SaveEncoding::Binary => {
+            // The maps are packed on their own, and the rest of the game follows them as JSON.
+            // The current level comes first, then every kept level in order of depth.
+            let mut rest = serde_json::to_value(data)?;
+            rest["game"]["map"] = Value::Array(Vec::new());
+            let mut maps = vec![(&data.game.map, &data.game.palette)];
+            for (level, stored) in &data.game.levels {
+                rest["game"]["levels"][level.to_string()]["map"] = Value::Array(Vec::new());
+                maps.push((&stored.map, &stored.palette));
+            }
+            binary::encode(&maps, &serde_json::to_vec(&rest)?)?
+        },
+    };
+    let header = SaveHeader {
+        format_version: SAVE_FORMAT_VERSION,
+        game_version: GAME_VERSION.into(),
+        checksum: checksum(&body),
+        encoding: encoding,
+        metadata: metadata,
+    };
+
+    let mut save_data = serde_json::to_vec(&header)?;
+    save_data.push(b'\n');
+    save_data.extend_from_slice(&body);
+    Ok(save_data)
+}
+
+// Rewrites a save in another encoding, such as turning a binary save into JSON to look through it.
+// The summary of the run is kept, so a converted slot is listed the same way as the original.
+pub fn convert_save(from: &Path, to: &Path, encoding: SaveEncoding) -> Result<(), SaveError> {
+    let (metadata, data) = read_save(&read_file(from)?)?;
+    write_save(&data.borrowed(), metadata, to, encoding)
+}
+
+// Writes to a temporary file next to the target, then renames it over the target.
+// A rename within one directory either happens completely or not at all, so there is never a half written save.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents)?;
+    // Makes sure the data has reached the disk before the old save is replaced.
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        e
+    })
+}
+
+// Removes a save, such as when the run in it has ended. A save that was never written is fine.
+pub fn delete_save(path: &Path) -> Result<(), SaveError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Where a game is saved if it crashes, such as "run-1-crash.sav" for "run-1.sav".
+// It is kept apart from the normal save, as a game that crashed part way through a turn may not be sound.
+pub fn emergency_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!("{}-crash.{}", stem, extension.to_string_lossy())),
+        None => path.with_file_name(format!("{}-crash", stem)),
+    }
+}
+
+// Every save that can hold the same run: the save itself, the emergency save made next to it,
+// and, when it is an emergency save, the usual save it was made next to.
+pub fn run_saves(path: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![path.to_path_buf(), emergency_path(path)];
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if let Some(usual) = stem.strip_suffix("-crash") {
+        paths.push(match path.extension() {
+            Some(extension) => path.with_file_name(format!("{}.{}", usual, extension.to_string_lossy())),
+            None => path.with_file_name(usual),
+        });
+    }
+    paths
+}
+
+// Reads a save file, upgrading it first if it was written by an older version of the game.
+pub fn load_game<P: AsRef<Path>>(path: P) -> Result<GameState, SaveError> {
+    let (_, data) = read_save(&read_file(path.as_ref())?)?;
+    Ok(GameState::from_parts(
+        data.game,
+        data.entities,
+        MapRecorder::new(false), // The front-end turns this on if it wants to watch new levels being made.
+        data.replay,
+        data.stats,
+        data.achievements,
+    ))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, SaveError> {
+    match fs::read(path) {
+        Ok(contents) => Ok(contents),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Err(SaveError::Missing(path.display().to_string())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Checks the header of a save, and turns the rest of it into game data.
+// Also returns the summary of the run from the header, if it has one.
+pub fn read_save(contents: &[u8]) -> Result<(Option<SaveMetadata>, SaveData), SaveError> {
+    // Saves from before save slots have no header, and are a single JSON array.
+    let first = contents.iter().find(|byte| !byte.is_ascii_whitespace());
+    if first == Some(&b'[') {
+        return Err(SaveError::Unsupported);
+    }
+
+    let (header_line, body) = contents.split_at(contents.iter().position(|byte| *byte == b'\n').ok_or_else(|| {
+        SaveError::Corrupt("the header is missing".into())
+    })?);
+    let body = &body[1..];
+    let header = serde_json::from_slice::<SaveHeader>(header_line)
+        .map_err(|e| SaveError::Corrupt(format!("the header can't be read ({})", e)))?;
+
+    if header.format_version > SAVE_FORMAT_VERSION {
+        return Err(SaveError::TooNew { found: header.format_version, game_version: header.game_version });
+    }
+    if header.format_version == 0 {
+        return Err(SaveError::Corrupt("the header has no format version".into()));
+    }
+    if checksum(body) != header.checksum {
+        return Err(SaveError::ChecksumMismatch);
+    }
+
+    // A binary save keeps the maps packed at the front, and the rest of the game as JSON.
+    let (maps, body) = match header.encoding {
+        SaveEncoding::Json => (Vec::new(), body),
+        SaveEncoding::Binary => binary::decode(body)?,
+    };
+
+    let body = serde_json::from_slice::<Value>(body)?;
+    let body = migrate(header.format_version, body)?;
+    let mut data = serde_json::from_value::<SaveData>(body)?;
+    // The current level is packed first, then every kept level in order of depth.
+    let mut maps = maps.into_iter();
+    if let Some(map) = maps.next() {
+        data.game.map = map;
+    }
+    for (stored, map) in data.game.levels.values_mut().zip(maps) {
+        stored.map = map;
+    }
+    Ok((header.metadata, data))
+}
+
+// Runs a save body through every migration between its version and the current one.
+fn migrate(version: u32, mut body: Value) -> Result<Value, SaveError> {
+    for migration in MIGRATIONS.iter().skip(version as usize - 1) {
+        body = migration(body)?;
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{ Map, RunOptions, next_level };
+    use crate::environment::map::tiles::TileType;
+
+    fn test_state() -> GameState {
+        let mut state = GameState::new(RunOptions::new(3), false);
+        // Going down a floor gives the save a kept level as well as the current one.
+        next_level(&mut state.game, &mut state.entities, &mut state.recorder);
+        state.initialise_fov();
+        state
+    }
+
+    fn encode_state(state: &GameState, encoding: SaveEncoding) -> Vec<u8> {
+        let data = SaveDataRef {
+            game: &state.game,
+            entities: &state.entities,
+            replay: &state.replay,
+            stats: &state.stats,
+            achievements: &state.achievements,
+        };
+        encode_save(&data, Some(SaveMetadata::from_state(state)), encoding).unwrap()
+    }
+
+    fn tile_types(map: &Map) -> Vec<TileType> {
+        map.iter().flat_map(|column| column.iter().map(|tile| tile.tiletype)).collect()
+    }
+
+    fn assert_same_game(loaded: &SaveData, state: &GameState) {
+        assert_eq!(loaded.game.dungeon_level, state.game.dungeon_level);
+        assert_eq!(loaded.game.rng.state(), state.game.rng.state());
+        assert_eq!(loaded.entities.player().pos(), state.entities.player().pos());
+        assert_eq!(tile_types(&loaded.game.map), tile_types(&state.game.map));
+        assert_eq!(loaded.game.levels.len(), 1);
+        assert_eq!(tile_types(&loaded.game.levels[&1].map), tile_types(&state.game.levels[&1].map));
+    }
+
+    #[test]
+    fn json_saves_load_back() {
+        let state = test_state();
+        let (metadata, loaded) = read_save(&encode_state(&state, SaveEncoding::Json)).unwrap();
+        assert_same_game(&loaded, &state);
+        assert_eq!(metadata.map(|metadata| metadata.dungeon_level), Some(2));
+    }
+
+    #[test]
+    fn binary_saves_load_back() {
+        let state = test_state();
+        let (_, loaded) = read_save(&encode_state(&state, SaveEncoding::Binary)).unwrap();
+        assert_same_game(&loaded, &state);
+    }
+
+    #[test]
+    fn changed_saves_fail_the_checksum() {
+        for encoding in &[SaveEncoding::Json, SaveEncoding::Binary] {
+            let mut contents = encode_state(&test_state(), *encoding);
+            let last = contents.len() - 2;
+            contents[last] ^= 1;
+            match read_save(&contents) {
+                Err(SaveError::ChecksumMismatch) => {},
+                _ => panic!("a changed {:?} save was accepted", encoding),
+            }
+        }
+    }
+
+    #[test]
+    fn saves_from_before_slots_are_unsupported() {
+        match read_save(b"[{\"map\": []}, {}]") {
+            Err(SaveError::Unsupported) => {},
+            _ => panic!("a save without a header was accepted"),
+        }
+    }
+
+    #[test]
+    fn saves_from_newer_versions_are_refused() {
+        let contents = encode_state(&test_state(), SaveEncoding::Json);
+        let newline = contents.iter().position(|byte| *byte == b'\n').unwrap();
+        let mut header: Value = serde_json::from_slice(&contents[..newline]).unwrap();
+        header["format_version"] = (SAVE_FORMAT_VERSION + 1).into();
+
+        let mut newer = serde_json::to_vec(&header).unwrap();
+        newer.extend_from_slice(&contents[newline..]);
+        match read_save(&newer) {
+            Err(SaveError::TooNew { found, .. }) => assert_eq!(found, SAVE_FORMAT_VERSION + 1),
+            _ => panic!("a save from a newer version was accepted"),
+        }
+    }
+
+    #[test]
+    fn current_saves_need_no_migrating() {
+        let body = serde_json::json!({ "game": { "dungeon_level": 4 } });
+        assert_eq!(migrate(SAVE_FORMAT_VERSION, body.clone()).unwrap(), body);
+    }
+
+    #[test]
+    fn crash_saves_are_cleaned_up_with_the_run() {
+        let saves = run_saves(Path::new("saves/slot-1-crash.sav"));
+        assert!(saves.contains(&PathBuf::from("saves/slot-1-crash.sav")));
+        assert!(saves.contains(&PathBuf::from("saves/slot-1.sav")));
+    }
+}
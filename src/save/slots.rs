@@ -0,0 +1,146 @@
+use super::{ SaveHeader, SaveMetadata, SaveError };
+
+use std::env;
+use std::fs::{ self, File };
+use std::io::{ BufRead, BufReader };
+use std::path::{ Path, PathBuf };
+
+// Name of the folder the game keeps its files in, inside the user's data directory.
+const APP_DIR: &str = "roguelike";
+
+// Save slots are files with this extension in the save directory. The slot name is the rest of the file name.
+pub const SLOT_EXTENSION: &str = "sav";
+
+// A save slot on disk, and what its header says about the run inside it.
+#[derive(Clone, Debug)]
+pub struct SaveSlot {
+    pub name: String,
+    pub path: PathBuf,
+    // Missing if the header couldn't be read. The slot is still listed, so it can be deleted.
+    pub metadata: Option<SaveMetadata>,
+}
+
+impl SaveSlot {
+    // One line describing the slot, for the slot menu.
+    pub fn describe(&self) -> String {
+        match &self.metadata {
+            Some(meta) => format!(
+                "{}: level {}, depth {}, {} turns, seed {}, {}",
+                self.name, meta.character_level, meta.dungeon_level, meta.turns, meta.seed,
+                format_timestamp(meta.timestamp),
+            ),
+            None => format!("{}: (unreadable)", self.name),
+        }
+    }
+}
+
+// The directory saves are kept in.
+pub fn default_save_dir() -> PathBuf {
+    default_data_dir().join("saves")
+}
+
+// The directory the game keeps its files in, following each platform's convention for per-user data.
+// Falls back to the folder the game is run from if no home directory can be found.
+pub fn default_data_dir() -> PathBuf {
+    let data_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+    };
+
+    match data_dir {
+        Some(dir) => dir.join(APP_DIR),
+        None => PathBuf::new(),
+    }
+}
+
+// Where the slot with the given name is stored.
+pub fn slot_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, SLOT_EXTENSION))
+}
+
+// Lists every save slot in the directory, most recently saved first.
+pub fn list_slots(dir: &Path) -> Vec<SaveSlot> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(), // No directory yet just means nothing has been saved.
+    };
+
+    let mut slots: Vec<SaveSlot> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == SLOT_EXTENSION))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let metadata = read_metadata(&path).ok();
+            Some(SaveSlot { name, path, metadata })
+        })
+        .collect();
+
+    // Newest first. Ties (and unreadable slots) are sorted by name, so the order is always the same.
+    slots.sort_by(|a, b| {
+        let time = |slot: &SaveSlot| slot.metadata.as_ref().map_or(0, |meta| meta.timestamp);
+        time(b).cmp(&time(a)).then_with(|| a.name.cmp(&b.name))
+    });
+    slots
+}
+
+// The most recently saved slot, for continuing straight from the command line.
+pub fn latest_slot(dir: &Path) -> Result<SaveSlot, SaveError> {
+    list_slots(dir)
+        .into_iter()
+        .find(|slot| slot.metadata.is_some())
+        .ok_or_else(|| SaveError::Missing(dir.display().to_string()))
+}
+
+// Reads just the header of a save, which is enough to describe it without loading the whole game.
+pub fn read_metadata(path: &Path) -> Result<SaveMetadata, SaveError> {
+    let mut header_line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut header_line)?;
+    let header = serde_json::from_str::<SaveHeader>(&header_line)
+        .map_err(|e| SaveError::Corrupt(format!("the header can't be read ({})", e)))?;
+    header.metadata.ok_or_else(|| SaveError::Corrupt("the header has no details about the run".into()))
+}
+
+// Picks a name for a new slot that isn't taken yet, such as "run-3".
+pub fn unused_slot_name(dir: &Path, base: &str) -> String {
+    (1..)
+        .map(|number| format!("{}-{}", base, number))
+        .find(|name| !slot_path(dir, name).exists())
+        .unwrap()
+}
+
+// Copies a slot into a new one. Returns the name of the copy.
+pub fn duplicate_slot(dir: &Path, slot: &SaveSlot) -> Result<String, SaveError> {
+    let name = unused_slot_name(dir, &format!("{}-copy", slot.name));
+    fs::copy(&slot.path, slot_path(dir, &name))?;
+    Ok(name)
+}
+
+pub fn delete_slot(slot: &SaveSlot) -> Result<(), SaveError> {
+    fs::remove_file(&slot.path)?;
+    Ok(())
+}
+
+// Turns a unix timestamp into a "YYYY-MM-DD HH:MM" date, in UTC.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Converts days since 1970 into a calendar date (Howard Hinnant's civil_from_days).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3600, (seconds % 3600) / 60)
+}
@@ -0,0 +1,92 @@
+use crate::environment::Game;
+use crate::objects::entities::Entities;
+use crate::objects::npc::SpeedStatus;
+
+use tcod::colors::*;
+
+// Energy an entity needs before it is allowed to act.
+pub const ACTION_THRESHOLD: i32 = 100;
+
+// Speed of a regular entity. At this speed, an entity gains enough energy to act once per turn.
+pub const NORMAL_SPEED: i32 = 100;
+
+// Energy spent by each kind of action.
+pub const MOVE_COST: i32 = 100;
+pub const ATTACK_COST: i32 = 100;
+pub const WAIT_COST: i32 = 100;
+pub const PICK_UP_COST: i32 = 50;
+pub const USE_ITEM_COST: i32 = 100;
+pub const DESCEND_COST: i32 = 100;
+pub const DOOR_COST: i32 = 100;
+pub const SEARCH_COST: i32 = 100;
+pub const DISARM_COST: i32 = 100;
+
+// Passes a single turn of game time.
+// Every fighter gains energy based on its speed, and their statuses count down.
+pub fn pass_turn(game: &mut Game, entities: &mut Entities) {
+    let player_id = entities.player_id();
+
+    for (id, object) in entities.iter_mut() {
+        if let Some(fighter) = object.fighter.as_mut() {
+            fighter.energy += fighter.effective_speed();
+
+            // Lets the player know when one of their statuses wears off.
+            // Monster statuses wear off silently, as they may not even be in view.
+            match fighter.tick_status() {
+                Some(SpeedStatus::Hasted { .. }) if id == player_id => {
+                    game.messages.add("The world around you speeds back up.", LIGHT_GREY);
+                },
+                Some(SpeedStatus::Slowed { .. }) if id == player_id => {
+                    game.messages.add("Your limbs no longer feel heavy.", LIGHT_GREY);
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{ Game, RunOptions };
+    use crate::environment::map::recorder::MapRecorder;
+    use crate::objects::Object;
+
+    fn with_speed(speed: i32, status: Option<SpeedStatus>) -> Object {
+        let mut object = Object::new_player();
+        let fighter = object.fighter.as_mut().unwrap();
+        fighter.speed = speed;
+        fighter.energy = 0;
+        fighter.status = status;
+        object
+    }
+
+    #[test]
+    fn fighters_gain_energy_by_speed() {
+        let mut entities = Entities::new(with_speed(NORMAL_SPEED, None));
+        let mut game = Game::new(RunOptions::new(1), &mut entities, &mut MapRecorder::new(false));
+        let fast = entities.insert(with_speed(200, None));
+        let slow = entities.insert(with_speed(50, None));
+        let hasted = entities.insert(with_speed(NORMAL_SPEED, Some(SpeedStatus::Hasted { num_turns: 1 })));
+        let slowed = entities.insert(with_speed(NORMAL_SPEED, Some(SpeedStatus::Slowed { num_turns: 5 })));
+        entities.player_mut().fighter.as_mut().unwrap().energy = 0;
+
+        pass_turn(&mut game, &mut entities);
+        let energy = |id| entities[id].fighter.unwrap().energy;
+        assert_eq!(energy(entities.player_id()), NORMAL_SPEED);
+        assert_eq!(energy(fast), 200);
+        assert_eq!(energy(slow), 50);
+        assert_eq!(energy(hasted), 200);
+        assert_eq!(energy(slowed), 50);
+
+        // Statuses count down each turn, and are gone once they run out.
+        assert_eq!(entities[hasted].fighter.unwrap().status, None);
+        assert_eq!(entities[slowed].fighter.unwrap().status, Some(SpeedStatus::Slowed { num_turns: 4 }));
+    }
+
+    #[test]
+    fn speed_never_drops_to_zero() {
+        let fighter = with_speed(1, Some(SpeedStatus::Slowed { num_turns: 3 })).fighter.unwrap();
+        assert_eq!(fighter.effective_speed(), 1);
+    }
+}
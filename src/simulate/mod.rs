@@ -0,0 +1,115 @@
+use crate::environment::{ RunOptions, in_map };
+use crate::objects::Object;
+use crate::objects::items::data::{ Effect, TargetMode };
+use crate::rng::GameRng;
+use crate::state::{ GameState, PlayerCommand, LevelUpStat };
+
+use rand::Rng;
+
+// Most items the player can carry. Picking up more than this does nothing, so the bot doesn't try.
+const INVENTORY_LIMIT: usize = 26;
+
+// Plays a game without a window, using a simple bot in place of the player.
+// The bot's choices come from its own generator, seeded from the run, so a simulation can be repeated exactly.
+// Stops when the player dies, or after the given number of commands.
+pub fn simulate(options: RunOptions, max_steps: usize) -> GameState {
+    let mut state = GameState::new(options, false);
+    let mut bot_rng = GameRng::from_seed(options.seed ^ 0x5EED_B075);
+
+    for _ in 0..max_steps {
+        if !state.entities.player().alive {
+            break;
+        }
+        let command = choose_command(&state, &mut bot_rng);
+        state.step(command);
+    }
+
+    state
+}
+
+// Picks the bot's next command.
+// In order: level up, heal when low, take the stairs, pick things up, fight, then head somewhere useful.
+fn choose_command(state: &GameState, rng: &mut GameRng) -> PlayerCommand {
+    let player = state.entities.player();
+    let player_id = state.entities.player_id();
+    let (x, y) = player.pos();
+
+    if state.level_up_ready() {
+        let stat = match rng.gen_range(0, 3) {
+            0 => LevelUpStat::Constitution,
+            1 => LevelUpStat::Strength,
+            _ => LevelUpStat::Agility,
+        };
+        return PlayerCommand::LevelUp { stat };
+    }
+
+    // Drinks a healing item when under a third of max health.
+    if let Some(fighter) = player.fighter {
+        if fighter.hp < fighter.max_hp / 3 {
+            if let Some(inventory_id) = healing_item(player) {
+                return PlayerCommand::UseItem { inventory_id, target: None };
+            }
+        }
+    }
+
+    let here: Vec<_> = state.entities.at(x, y).filter(|(id, _)| *id != player_id).map(|(_, o)| o).collect();
+    if here.iter().any(|object| object.name == "Stairs") {
+        return PlayerCommand::Descend;
+    }
+    let carrying = player.inventory.as_ref().map_or(0, |inventory| inventory.len());
+    if carrying < INVENTORY_LIMIT && here.iter().any(|object| object.item.is_some()) {
+        return PlayerCommand::PickUp;
+    }
+
+    // Heads for the closest visible monster, then the stairs if they have been seen, then any visible item.
+    let closest = |wanted: &dyn Fn(&Object) -> bool| {
+        state.entities
+            .iter()
+            .filter(|(id, object)| *id != player_id && wanted(object))
+            .map(|(_, object)| object)
+            .min_by(|a, b| player.distance(a.x, a.y).partial_cmp(&player.distance(b.x, b.y)).unwrap())
+            .map(|object| object.pos())
+    };
+    let goal = closest(&|object| object.fighter.is_some() && state.fov.is_in_fov(object.x, object.y))
+        .or_else(|| closest(&|object| {
+            object.name == "Stairs" && state.game.map[object.x as usize][object.y as usize].explored
+        }))
+        .or_else(|| closest(&|object| object.item.is_some() && state.fov.is_in_fov(object.x, object.y)));
+
+    // Steps straight towards the goal. Moving into a monster attacks it, and moving into a door opens it.
+    // Lava and miasma are stepped around.
+    if let Some((goal_x, goal_y)) = goal {
+        let (dx, dy) = ((goal_x - x).signum(), (goal_y - y).signum());
+        let (to_x, to_y) = (x + dx, y + dy);
+        let attacking = state.entities.at(to_x, to_y).any(|(_, object)| object.fighter.is_some());
+        let door = in_map(&state.game.map, to_x, to_y) && state.game.map[to_x as usize][to_y as usize].is_door();
+        let harmful = in_map(&state.game.map, to_x, to_y) && state.game.map[to_x as usize][to_y as usize].harmful();
+        if attacking || door || (!harmful && !Object::is_blocked(to_x, to_y, &state.game.map, &state.entities)) {
+            return PlayerCommand::MoveOrAttack { dx, dy };
+        }
+    }
+
+    // Otherwise it wanders in a random open direction, or waits if boxed in.
+    for _ in 0..8 {
+        let (dx, dy) = (rng.gen_range(-1, 2), rng.gen_range(-1, 2));
+        let (to_x, to_y) = (x + dx, y + dy);
+        if (dx, dy) != (0, 0) &&
+            in_map(&state.game.map, to_x, to_y) &&
+            !state.game.map[to_x as usize][to_y as usize].harmful() &&
+            !Object::is_blocked(to_x, to_y, &state.game.map, &state.entities)
+        {
+            return PlayerCommand::MoveOrAttack { dx, dy };
+        }
+    }
+    PlayerCommand::Wait
+}
+
+// Finds an item in the inventory that heals the player, if there is one.
+fn healing_item(player: &Object) -> Option<usize> {
+    player.inventory.as_ref()?.iter().position(|object| {
+        object.item.as_ref().and_then(|item| item.def()).map_or(false, |def| {
+            matches!(def.targeting, TargetMode::Player) &&
+                def.effects.iter().any(|effect| matches!(effect, Effect::Heal { .. }))
+        })
+    })
+}
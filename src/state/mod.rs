@@ -0,0 +1,527 @@
+use crate::{ LEVEL_UP_BASE, LEVEL_UP_FACTOR };
+use crate::environment::*;
+use crate::environment::map::recorder::MapRecorder;
+use crate::objects::Object;
+use crate::objects::entities::Entities;
+use crate::replay::{ Replay, Checkpoint };
+use crate::events::{ GameEvent, Subscriber, MessageLog };
+use crate::events::stats::RunStats;
+use crate::events::achievements::Achievements;
+use crate::scheduler::*;
+
+use serde::{ Serialize, Deserialize };
+
+use tcod::colors::*;
+use tcod::map::Map as FovMap;
+
+// Everything the game core can be asked to do by the player.
+// Front-ends (the tcod window, bots, replays) turn their input into these.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    MoveOrAttack { dx: i32, dy: i32 },
+    Wait,
+    Descend,
+    Ascend,
+    PickUp,
+    UseItem { inventory_id: usize, target: Option<(i32, i32)> },
+    DropItem { inventory_id: usize },
+    LevelUp { stat: LevelUpStat },
+    CloseDoor,
+    Search,
+    Disarm,
+}
+
+// The stats that can be raised when the player levels up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LevelUpStat {
+    Constitution,
+    Strength,
+    Agility,
+    Perception,
+}
+
+// Used internally to determine whether or not the monsters get to act after a command.
+// Taking a turn costs the player energy, and the monsters act until the player has enough to go again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerAction {
+    TookTurn { cost: i32 },
+    DidntTakeTurn,
+}
+
+// Holds everything needed to play the game, without needing a libtcod window.
+pub struct GameState {
+    pub game: Game,
+    pub entities: Entities,
+    pub fov: FovMap,
+    pub recorder: MapRecorder,
+    pub replay: Replay,
+    pub stats: RunStats,
+    pub achievements: Achievements,
+}
+
+impl GameState {
+    // Creates a brand new game from a set of run options, and generates the first floor.
+    pub fn new(options: RunOptions, record_map_gen: bool) -> GameState {
+        // Creates game objects
+        let mut entities = Entities::new(Object::new_player());
+        let mut recorder = MapRecorder::new(record_map_gen);
+
+        // Generate map to be rendered
+        let mut game = Game::new(options, &mut entities, &mut recorder);
+
+        // Intro message
+        game.messages.add(
+            "Dive deep. Gain power. Try not to die in these ancient tombs...",
+            GOLD,
+        );
+
+        // Runs that start deeper down count their first floor as the deepest reached so far.
+        let mut stats = RunStats::new();
+        stats.deepest_level = options.start_depth;
+
+        GameState::from_parts(game, entities, recorder, Replay::new(options), stats, Achievements::new())
+    }
+
+    // Puts an existing set of game objects back together, such as after loading a save.
+    pub fn from_parts(
+        game: Game,
+        entities: Entities,
+        recorder: MapRecorder,
+        replay: Replay,
+        stats: RunStats,
+        achievements: Achievements,
+    ) -> GameState {
+        let (map_width, map_height) = map_dimensions(&game.map);
+        let fov = FovMap::new(map_width, map_height);
+        let mut state = GameState { game, entities, fov, recorder, replay, stats, achievements };
+
+        // Starts the FOV based on the current map.
+        state.initialise_fov();
+        state
+    }
+
+    // Populates the FOV map, based on the generated map
+    // The FOV map is rebuilt at the size of the current map, as levels don't have to share a size.
+    pub fn initialise_fov(&mut self) {
+        let (map_width, map_height) = map_dimensions(&self.game.map);
+        self.fov = FovMap::new(map_width, map_height);
+        for y in 0..map_height {
+            for x in 0..map_width {
+                self.fov.set(
+                    x, y,
+                    !self.game.map[x as usize][y as usize].block_sight,
+                    !self.game.map[x as usize][y as usize].blocked,
+                );
+            }
+        }
+        self.compute_fov();
+    }
+
+    // Copies a single tile over to the FOV map, after it has changed.
+    fn update_fov_tile(&mut self, x: i32, y: i32) {
+        let tile = &self.game.map[x as usize][y as usize];
+        self.fov.set(x, y, !tile.block_sight, !tile.blocked);
+    }
+
+    // Recomputes the FOV from the player's position, and marks anything visible as explored.
+    pub fn compute_fov(&mut self) {
+        let (x, y) = self.entities.player().pos();
+        self.fov.compute_fov(x, y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
+
+        let (map_width, map_height) = map_dimensions(&self.game.map);
+        for y in 0..map_height {
+            for x in 0..map_width {
+                if self.fov.is_in_fov(x, y) {
+                    self.game.map[x as usize][y as usize].explored = true;
+                }
+            }
+        }
+    }
+
+    // Exp needed for the player to reach the next level.
+    pub fn level_up_xp(&self) -> i32 {
+        LEVEL_UP_BASE + self.entities.player().level * LEVEL_UP_FACTOR
+    }
+
+    // Checks if the player has enough exp to level up.
+    pub fn level_up_ready(&self) -> bool {
+        self.entities.player().fighter.as_ref().map_or(0, |f| f.exp) >= self.level_up_xp()
+    }
+
+    // Processes a single player command, lets the monsters respond, and reports what happened.
+    pub fn step(&mut self, command: PlayerCommand) -> Vec<GameEvent> {
+        let mut events = vec![];
+
+        // Dead players don't get to do anything.
+        if !self.entities.player().alive {
+            return events;
+        }
+
+        let player_action = self.perform(command);
+        self.dispatch_events(&mut events);
+
+        if let PlayerAction::TookTurn { cost } = player_action {
+            self.game.events.emit(GameEvent::TurnTaken);
+
+            // Every turn, there's a small chance of noticing any secret passages or traps nearby.
+            // Searching has already had a much better chance this turn, so doesn't get another.
+            if command != PlayerCommand::Search {
+                Object::player_search(false, &mut self.game, &mut self.entities);
+                self.dispatch_events(&mut events);
+            }
+
+            // The player may have moved, so the monsters need to see the up to date FOV.
+            self.compute_fov();
+
+            // Spends the player's energy, then lets the monsters act until the player is ready again.
+            if let Some(fighter) = self.entities.player_mut().fighter.as_mut() {
+                fighter.energy -= cost;
+            }
+            self.run_until_player_ready(&mut events);
+        }
+
+        if self.entities.player().alive && self.level_up_ready() {
+            self.game.events.emit(GameEvent::LevelUpReady);
+        }
+        self.dispatch_events(&mut events);
+
+        // Every command is recorded, so that the run can be replayed later on.
+        let checkpoint = self.checkpoint();
+        self.replay.record(command, checkpoint);
+
+        events
+    }
+
+    // Passes game time until the player has enough energy to act.
+    // Each turn, every monster with enough energy acts, faster monsters possibly more than once.
+    fn run_until_player_ready(&mut self, events: &mut Vec<GameEvent>) {
+        loop {
+            let player = self.entities.player();
+            let player_energy = player.fighter.map_or(ACTION_THRESHOLD, |f| f.energy);
+            if !player.alive || player_energy >= ACTION_THRESHOLD {
+                break;
+            }
+
+            // The last turn ends, burning anything left standing in lava or miasma, and the next one begins.
+            Object::terrain_turn(&self.fov, &mut self.game, &mut self.entities);
+            self.dispatch_events(events);
+            if !self.entities.player().alive {
+                break;
+            }
+            pass_turn(&mut self.game, &mut self.entities);
+            self.dispatch_events(events);
+
+            // Ids are collected first, as monsters can die or change during the loop.
+            // They are acted on in id order, so that the same seed always plays out the same way.
+            let monster_ids: Vec<_> = self.entities.with_ai().map(|(id, _)| id).collect();
+            for id in monster_ids {
+                loop {
+                    let ready = self.entities.get(id).map_or(false, |monster| {
+                        monster.ai.is_some() && monster.fighter.map_or(false, |f| f.energy >= ACTION_THRESHOLD)
+                    });
+                    if !ready || !self.entities.player().alive {
+                        break;
+                    }
+                    let cost = Object::ai_take_turn(id, &self.fov, &mut self.game, &mut self.entities);
+                    if let Some(fighter) = self.entities.get_mut(id).and_then(|monster| monster.fighter.as_mut()) {
+                        fighter.energy -= cost;
+                    }
+                    self.dispatch_events(events);
+                }
+            }
+        }
+    }
+
+    // Hands every event emitted so far to the subscribers, then adds them to the events for this step.
+    // This is done after every action, so that the message log stays in the order things happened.
+    fn dispatch_events(&mut self, events: &mut Vec<GameEvent>) {
+        for event in self.game.events.drain() {
+            // Doors and secret passages change what can be seen and walked through, so the FOV map has to follow them.
+            match event {
+                GameEvent::DoorOpened { x, y } |
+                GameEvent::DoorClosed { x, y } |
+                GameEvent::DoorUnlocked { x, y } |
+                GameEvent::DoorBashed { x, y, .. } |
+                GameEvent::SecretFound { x, y } => self.update_fov_tile(x, y),
+                _ => {},
+            }
+            MessageLog.notify(&event, &mut self.game.messages);
+            self.stats.notify(&event, &mut self.game.messages);
+            self.achievements.notify(&event, &mut self.game.messages);
+            self.replay.notify(&event, &mut self.game.messages);
+            events.push(event);
+        }
+    }
+
+    // Summarises the current state of the game, to check replays against.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let fighter = self.entities.player().fighter;
+        let player_id = self.entities.player_id();
+        Checkpoint {
+            dungeon_level: self.game.dungeon_level,
+            player_pos: self.entities.player().pos(),
+            player_hp: fighter.map_or(0, |f| f.hp),
+            player_exp: fighter.map_or(0, |f| f.exp),
+            monsters_alive: self.entities.with_fighter().filter(|(id, _)| *id != player_id).count(),
+            rng_state: self.game.rng.state(),
+            events: Vec::new(), // Filled in by the replay from the events it was sent.
+        }
+    }
+
+    // Carries out the player's part of a command.
+    fn perform(&mut self, command: PlayerCommand) -> PlayerAction {
+        use PlayerAction::*;
+
+        match command {
+            PlayerCommand::MoveOrAttack { dx, dy } => {
+                let cost = Object::player_move_or_attack(dx, dy, &mut self.game, &mut self.entities);
+                TookTurn { cost }
+            },
+
+            // Wait a turn
+            PlayerCommand::Wait => TookTurn { cost: WAIT_COST },
+
+            PlayerCommand::Descend => {
+                // Go down stairs if the player is on top of them.
+                let player_pos = self.entities.player().pos();
+                let player_on_stairs = self.entities
+                    .at(player_pos.0, player_pos.1)
+                    .any(|(_, obj)| obj.name == "Stairs");
+                if player_on_stairs {
+                    next_level(&mut self.game, &mut self.entities, &mut self.recorder);
+                    self.initialise_fov();
+                    TookTurn { cost: DESCEND_COST }
+                } else {
+                    DidntTakeTurn
+                }
+            },
+
+            PlayerCommand::Ascend => {
+                // Go back up if the player is on top of the up stairs.
+                let player_pos = self.entities.player().pos();
+                let player_on_stairs = self.entities
+                    .at(player_pos.0, player_pos.1)
+                    .any(|(_, obj)| obj.name == "Up stairs");
+                if player_on_stairs && previous_level(&mut self.game, &mut self.entities) {
+                    self.initialise_fov();
+                    TookTurn { cost: DESCEND_COST }
+                } else {
+                    DidntTakeTurn
+                }
+            },
+
+            PlayerCommand::PickUp => {
+                // Pick up an item
+                // The lowest id is taken, so that the same item is always picked up from a pile.
+                let player_pos = self.entities.player().pos();
+                let item_id = self.entities
+                    .with_item()
+                    .filter(|(_, item)| item.pos() == player_pos)
+                    .map(|(id, _)| id)
+                    .min();
+
+                match item_id {
+                    Some(item_id) => {
+                        Object::pick_item_up(item_id, &mut self.game, &mut self.entities);
+                        TookTurn { cost: PICK_UP_COST }
+                    },
+                    None => {
+                        self.game.messages.add("There's no item to grab...", RED);
+                        DidntTakeTurn
+                    },
+                }
+            },
+
+            // Asking for an item that isn't in the inventory doesn't use up a turn.
+            PlayerCommand::UseItem { inventory_id, target } => {
+                if self.entities.player().inventory.as_ref().map_or(false, |inv| inventory_id < inv.len()) {
+                    Object::use_item(
+                        inventory_id,
+                        target,
+                        &self.fov,
+                        &mut self.game,
+                        &mut self.entities,
+                    );
+                    TookTurn { cost: USE_ITEM_COST }
+                } else {
+                    DidntTakeTurn
+                }
+            },
+
+            PlayerCommand::DropItem { inventory_id } => {
+                if self.entities.player().inventory.as_ref().map_or(false, |inv| inventory_id < inv.len()) {
+                    Object::drop_item(inventory_id, &mut self.game, &mut self.entities);
+                }
+                DidntTakeTurn
+            },
+
+            PlayerCommand::CloseDoor => {
+                match Object::player_close_door(&mut self.game, &self.entities) {
+                    true => TookTurn { cost: DOOR_COST },
+                    false => {
+                        self.game.messages.add("There's no open door to close...", RED);
+                        DidntTakeTurn
+                    },
+                }
+            },
+
+            // Spends a turn looking around for secret passages and traps.
+            PlayerCommand::Search => {
+                if Object::player_search(true, &mut self.game, &mut self.entities) == 0 {
+                    self.game.messages.add("You search around, but find nothing.", LIGHT_GREY);
+                }
+                TookTurn { cost: SEARCH_COST }
+            },
+
+            PlayerCommand::Disarm => {
+                match Object::player_disarm(&mut self.game, &mut self.entities) {
+                    true => TookTurn { cost: DISARM_COST },
+                    false => {
+                        self.game.messages.add("There's no trap nearby that you know of...", RED);
+                        DidntTakeTurn
+                    },
+                }
+            },
+
+            PlayerCommand::LevelUp { stat } => {
+                if self.level_up_ready() {
+                    let level_up_xp = self.level_up_xp();
+                    Object::level_up(stat, level_up_xp, &mut self.game, self.entities.player_mut());
+                }
+                DidntTakeTurn
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::map::tiles::{ Tile, TileType };
+    use crate::objects::entities::EntityId;
+    use crate::objects::npc::ai::Ai;
+    use crate::rng::GameRng;
+
+    use rand::Rng;
+
+    // A walled off room with the player in it, and nothing else on the level.
+    // A wall down the middle gives monsters somewhere to stand out of sight.
+    fn test_state() -> GameState {
+        let mut state = GameState::new(RunOptions::new(1), false);
+        let mut map = vec![vec![Tile::from_type(TileType::Empty, WHITE, GREY); 12]; 24];
+        for (x, column) in map.iter_mut().enumerate() {
+            for (y, tile) in column.iter_mut().enumerate() {
+                if x == 0 || y == 0 || x == 23 || y == 11 || x == 12 {
+                    *tile = Tile::from_type(TileType::Wall, WHITE, GREY);
+                }
+            }
+        }
+        state.game.map = map;
+        state.entities.clear_level();
+        state.entities.player_mut().set_pos(5, 5);
+
+        // Secret passages and traps are never noticed by a player this unobservant.
+        state.entities.player_mut().fighter.as_mut().unwrap().perception = -1000;
+        state.initialise_fov();
+        state
+    }
+
+    fn add_monster(state: &mut GameState, speed: i32) -> EntityId {
+        let mut monster = Object::new_player();
+        monster.name = "Orc".into();
+        monster.set_pos(18, 5);
+        monster.ai = Some(Ai::Basic);
+        monster.inventory = None;
+        let fighter = monster.fighter.as_mut().unwrap();
+        fighter.speed = speed;
+        fighter.energy = 0;
+        state.entities.insert(monster)
+    }
+
+    fn took_turn(events: &[GameEvent]) -> bool {
+        events.contains(&GameEvent::TurnTaken)
+    }
+
+    // Advances a copy of the game's rng by one search roll per hidden thing.
+    fn after_rolls(rng: &GameRng, rolls: usize) -> [u32; 4] {
+        let mut rng = rng.clone();
+        for _ in 0..rolls {
+            rng.gen_range(0, 100);
+        }
+        rng.state()
+    }
+
+    #[test]
+    fn waiting_takes_a_turn() {
+        let mut state = test_state();
+        let turns = state.stats.turns;
+        let events = state.step(PlayerCommand::Wait);
+        assert!(took_turn(&events));
+        assert_eq!(state.stats.turns, turns + 1);
+        assert_eq!(state.entities.player().fighter.unwrap().energy, ACTION_THRESHOLD);
+    }
+
+    #[test]
+    fn using_a_missing_item_takes_no_turn() {
+        let mut state = test_state();
+        let turns = state.stats.turns;
+        let rng = state.game.rng.state();
+        let events = state.step(PlayerCommand::UseItem { inventory_id: 3, target: None });
+        assert!(!took_turn(&events));
+        assert_eq!(state.stats.turns, turns);
+        assert_eq!(state.game.rng.state(), rng);
+    }
+
+    #[test]
+    fn searching_skips_the_passive_search() {
+        let mut state = test_state();
+        state.game.map[4][4] = Tile::from_type(TileType::SecretPath, WHITE, GREY);
+        state.game.map[6][6] = Tile::from_type(TileType::SecretPath, WHITE, GREY);
+        state.initialise_fov();
+
+        // Searching rolls once for each of the two secret passages...
+        let expected = after_rolls(&state.game.rng, 2);
+        state.step(PlayerCommand::Search);
+        assert_eq!(state.game.rng.state(), expected);
+
+        // ...as does waiting, through the passive search alone.
+        let expected = after_rolls(&state.game.rng, 2);
+        state.step(PlayerCommand::Wait);
+        assert_eq!(state.game.rng.state(), expected);
+    }
+
+    #[test]
+    fn faster_monsters_act_more_often() {
+        let mut state = test_state();
+        let fast = add_monster(&mut state, 200);
+        let slow = add_monster(&mut state, 50);
+        state.entities[slow].set_pos(18, 7);
+        state.step(PlayerCommand::Wait);
+
+        // The fast monster gained enough energy to act twice, the slow one not enough to act at all.
+        assert_eq!(state.entities[fast].fighter.unwrap().energy, 0);
+        assert_eq!(state.entities[slow].fighter.unwrap().energy, 50);
+        assert_eq!(state.entities.player().fighter.unwrap().energy, ACTION_THRESHOLD);
+    }
+
+    #[test]
+    fn standing_in_lava_burns_every_turn() {
+        let mut state = test_state();
+        state.game.map[5][5] = Tile::from_type(TileType::Lava, WHITE, GREY);
+        let hp = |state: &GameState| state.entities.player().fighter.unwrap().hp;
+
+        let before = hp(&state);
+        state.step(PlayerCommand::Wait);
+        let after_one = hp(&state);
+        state.step(PlayerCommand::Wait);
+        assert!(after_one < before);
+        assert_eq!(before - after_one, after_one - hp(&state));
+    }
+
+    #[test]
+    fn dead_players_do_nothing() {
+        let mut state = test_state();
+        state.entities.player_mut().alive = false;
+        assert!(state.step(PlayerCommand::Wait).is_empty());
+    }
+}
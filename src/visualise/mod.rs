@@ -0,0 +1,111 @@
+use crate::environment::{ Map, RunOptions, Generator, make_map, map_dimensions };
+use crate::environment::map::ascii::map_to_ascii;
+use crate::environment::map::recorder::MapRecorder;
+use crate::objects::Object;
+use crate::objects::entities::Entities;
+use crate::rng::GameRng;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use tcod::image::Image;
+
+// Width and height, in pixels, of each tile in an exported image.
+const PNG_TILE_SIZE: i32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameFormat {
+    Ascii,
+    Png,
+}
+
+// Every step taken while making one level, along with the finished level.
+pub struct Recording {
+    pub frames: Vec<(Map, u32)>,
+    pub map: Map,
+    pub entities: Entities,
+}
+
+// Makes a level without a window, keeping a snapshot of each step the generator takes.
+// Without a generator given, this is the same level a run with these options starts on.
+pub fn record_level(options: RunOptions, generator: Option<Generator>) -> Recording {
+    let mut entities = Entities::new(Object::new_player());
+    let mut rng = GameRng::from_seed(options.seed);
+    let mut recorder = MapRecorder::new(true);
+
+    let (map, _) = make_map(&mut entities, options.start_depth, options.map_size, generator, &mut rng, &mut recorder);
+    Recording { frames: recorder.drain(), map, entities }
+}
+
+// Writes each step of a recording to its own numbered file, followed by the finished level.
+// Returns how many files were written.
+pub fn export_frames(recording: &Recording, dir: &Path, format: FrameFormat) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+
+    let last = recording.frames.len();
+    for (number, (map, _)) in recording.frames.iter().enumerate() {
+        write_frame(map, None, &dir.join(frame_name(number, format)), format)?;
+    }
+    // The finished level also shows where everything was spawned.
+    write_frame(&recording.map, Some(&recording.entities), &dir.join(frame_name(last, format)), format)?;
+    Ok(last + 1)
+}
+
+// Frames are numbered from zero, padded so they sort in order.
+fn frame_name(number: usize, format: FrameFormat) -> String {
+    match format {
+        FrameFormat::Ascii => format!("frame-{:04}.txt", number),
+        FrameFormat::Png => format!("frame-{:04}.png", number),
+    }
+}
+
+fn write_frame(map: &Map, entities: Option<&Entities>, path: &Path, format: FrameFormat) -> io::Result<()> {
+    match format {
+        FrameFormat::Ascii => fs::write(path, map_to_ascii(map, entities)),
+        FrameFormat::Png => {
+            // Entities are left out of images, which only show the shape of the level.
+            // Saving an image doesn't report errors, so it is saved to a file that doesn't exist yet,
+            // and only moved over the frame once it has turned up.
+            let not_written = || io::Error::new(io::ErrorKind::Other, format!("{} could not be written", path.display()));
+            let temp_path = path.with_extension("tmp.png");
+            if let Err(e) = fs::remove_file(&temp_path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+            // Image paths are handed to libtcod as text, so anything that can't be is refused up front.
+            match temp_path.to_str() {
+                Some(text) if !text.contains('\0') => {},
+                _ => return Err(not_written()),
+            }
+            map_to_image(map).save(&temp_path);
+            if !temp_path.exists() {
+                return Err(not_written());
+            }
+            fs::rename(&temp_path, path)
+        },
+    }
+}
+
+// Draws a map as an image, shading walls darker than the floor in the same way as watching it in the window.
+fn map_to_image(map: &Map) -> Image {
+    let (map_width, map_height) = map_dimensions(map);
+    let mut image = Image::new(map_width * PNG_TILE_SIZE, map_height * PNG_TILE_SIZE);
+
+    for x in 0..map_width {
+        for y in 0..map_height {
+            let tile = &map[x as usize][y as usize];
+            let color = match tile.block_sight {
+                true => tile.color_dark,
+                false => tile.color_light,
+            };
+            for pixel_x in 0..PNG_TILE_SIZE {
+                for pixel_y in 0..PNG_TILE_SIZE {
+                    image.put_pixel(x * PNG_TILE_SIZE + pixel_x, y * PNG_TILE_SIZE + pixel_y, color);
+                }
+            }
+        }
+    }
+    image
+}